@@ -0,0 +1,45 @@
+//! Contains the trait for converting images into colored ASCII text, so imgii can render ASCII
+//! from converters other than RASCII.
+
+use image::DynamicImage;
+use rascii_art_img::render_image_to;
+
+use crate::{error::ImgiiError, options::ImgiiOptions};
+
+/// Converts an image into colored ASCII text for imgii to render.
+///
+/// The ASCII text must have one line per row of cells, with every character colored by its own
+/// `\x1b[38;2;<R>;<G>;<B>m` (or 256-color `\x1b[38;5;<index>m`) escape sequence, and every row
+/// must have the same number of cells. Grayscale images are converted to color before being passed
+/// in.
+pub trait AsciiSource: std::fmt::Debug + Send + Sync {
+    /// Converts an image into colored ASCII text.
+    ///
+    /// * `image`: The image to convert.
+    /// * `imgii_options`: The imgii options, holding the size of the ASCII grid and more.
+    fn to_ascii(
+        &self,
+        image: &DynamicImage,
+        imgii_options: &ImgiiOptions,
+    ) -> Result<String, ImgiiError>;
+}
+
+/// Converts images into ASCII using RASCII and the RASCII options of the [`ImgiiOptions`]. This is
+/// the default [`AsciiSource`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RasciiSource;
+
+impl AsciiSource for RasciiSource {
+    fn to_ascii(
+        &self,
+        image: &DynamicImage,
+        imgii_options: &ImgiiOptions,
+    ) -> Result<String, ImgiiError> {
+        let rascii_options = imgii_options.rascii_options_for(image.width(), image.height());
+
+        let mut ascii_text = String::new();
+        render_image_to(image, &mut ascii_text, &rascii_options)
+            .map_err(|err| -> ImgiiError { anyhow::Error::new(err).into() })?;
+        Ok(ascii_text)
+    }
+}
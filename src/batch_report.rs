@@ -0,0 +1,70 @@
+//! Contains a report of which images of a batch failed to convert, and why.
+
+use crate::error::ImgiiError;
+
+/// An image of a batch that failed to convert.
+#[derive(Debug)]
+pub struct BatchFailure {
+    /// The input file name of the image.
+    pub(crate) input_file_name: String,
+    /// The output file name of the image.
+    pub(crate) output_file_name: String,
+    /// Why the image failed to convert.
+    pub(crate) error: ImgiiError,
+}
+
+impl BatchFailure {
+    /// Gets the input file name of the image.
+    #[must_use]
+    pub fn input_file_name(&self) -> &str {
+        &self.input_file_name
+    }
+
+    /// Gets the output file name of the image.
+    #[must_use]
+    pub fn output_file_name(&self) -> &str {
+        &self.output_file_name
+    }
+
+    /// Gets why the image failed to convert.
+    #[must_use]
+    pub fn error(&self) -> &ImgiiError {
+        &self.error
+    }
+}
+
+/// A summary of a batch conversion, where images that fail to convert are skipped.
+#[derive(Debug, Default)]
+pub struct BatchReport {
+    /// The number of images in the batch.
+    pub(crate) total: usize,
+    /// The images that failed to convert, in the order they were given.
+    pub(crate) failures: Vec<BatchFailure>,
+}
+
+impl BatchReport {
+    /// Gets the number of images in the batch.
+    #[must_use]
+    pub fn total(&self) -> usize {
+        self.total
+    }
+
+    /// Gets the number of images that were converted and saved.
+    #[must_use]
+    pub fn succeeded(&self) -> usize {
+        self.total - self.failures.len()
+    }
+
+    /// Gets the images that failed to convert, in the order they were given. Images skipped
+    /// because the conversion was cancelled fail with [`ImgiiError::Cancelled`].
+    #[must_use]
+    pub fn failures(&self) -> &[BatchFailure] {
+        &self.failures
+    }
+
+    /// Gets whether every image was converted.
+    #[must_use]
+    pub fn is_complete(&self) -> bool {
+        self.failures.is_empty()
+    }
+}
@@ -0,0 +1,110 @@
+//! Contains types for exporting each unique rendered cell of an image as its own image file.
+
+use std::{collections::HashMap, fmt::Write, path::Path, sync::Arc};
+
+use crate::{
+    conversion::{
+        converters::generic_converter::Imgii2dImage, image_data::ImageData,
+        render_char_to_png::ColoredStr,
+    },
+    error::ImgiiError,
+};
+
+/// Maps each cell of a rendered image to the file its rendered cell was exported to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CellManifest {
+    /// The width (in pixels) of every cell.
+    pub cell_width: u32,
+    /// The height (in pixels) of every cell.
+    pub cell_height: u32,
+    /// The file name (relative to the output directory) of every cell in the image, as rows
+    /// starting from the top of the image. Cells that weren't exported, such as whitespace, are
+    /// `None`.
+    pub grid: Vec<Vec<Option<String>>>,
+}
+
+#[cfg(feature = "serde")]
+impl CellManifest {
+    /// Saves this manifest as JSON.
+    ///
+    /// * `file_name`: The file name to save the JSON to.
+    pub fn save_json(&self, file_name: &str) -> Result<(), ImgiiError> {
+        let file_writer = std::io::BufWriter::new(std::fs::File::create(file_name)?);
+        serde_json::to_writer_pretty(file_writer, self)
+            .map_err(|err| -> ImgiiError { anyhow::Error::new(err).into() })
+    }
+}
+
+/// Gets the file name of an exported cell, as its color in hex and the string rendered in it,
+/// followed by the color of its background in hex if it has one.
+///
+/// Only lowercase ASCII letters and digits are written as-is. Every other character is written as
+/// `u` followed by its code point in hex, so file names are safe on every file system, including
+/// case-insensitive ones (e.g. `A` is written as `u41`, so it never clashes with `a`).
+///
+/// * `colored`: What was rendered in the cell.
+fn cell_file_name(colored: &ColoredStr) -> String {
+    let mut file_name = format!(
+        "{:02x}{:02x}{:02x}_",
+        colored.red, colored.green, colored.blue
+    );
+    for (i, c) in colored.string.chars().enumerate() {
+        if c.is_ascii_lowercase() || c.is_ascii_digit() {
+            file_name.push(c);
+        } else {
+            // separate escaped characters, so their code points can't run together
+            let separator = if i == 0 { "" } else { "-" };
+            // writing to a string can't fail
+            let _ = write!(file_name, "{separator}u{:x}", u32::from(c));
+        }
+    }
+    if let Some([red, green, blue]) = colored.background {
+        // writing to a string can't fail
+        let _ = write!(file_name, "_bg{red:02x}{green:02x}{blue:02x}");
+    }
+    file_name.push_str(".png");
+    file_name
+}
+
+/// Writes each unique rendered cell of an image to its own PNG in a directory, building the
+/// manifest of which cell goes where.
+///
+/// * `the_image`: The rendered image.
+/// * `output_dir`: The directory to write the cells to. Created if it doesn't exist.
+/// * `cell_width`: The width (in pixels) of every cell.
+/// * `cell_height`: The height (in pixels) of every cell.
+pub(crate) fn write_cells(
+    the_image: &Imgii2dImage,
+    output_dir: &Path,
+    cell_width: u32,
+    cell_height: u32,
+) -> Result<CellManifest, ImgiiError> {
+    std::fs::create_dir_all(output_dir)?;
+
+    // the file name of each cached cell, by the address of its shared image
+    let mut file_names: HashMap<*const ImageData, String> = HashMap::new();
+    for (colored, cell) in &the_image.glyph_cache {
+        let file_name = cell_file_name(colored);
+        cell.as_buffer()
+            .save(output_dir.join(&file_name))
+            .map_err(|err| -> ImgiiError { anyhow::Error::new(err).into() })?;
+        file_names.insert(Arc::as_ptr(cell), file_name);
+    }
+
+    let grid = the_image
+        .image_2d
+        .chunks(the_image.width.max(1))
+        .map(|row| {
+            row.iter()
+                .map(|cell| file_names.get(&Arc::as_ptr(cell)).cloned())
+                .collect()
+        })
+        .collect();
+
+    Ok(CellManifest {
+        cell_width,
+        cell_height,
+        grid,
+    })
+}
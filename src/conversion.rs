@@ -2,6 +2,7 @@
 //! handle different image types.
 
 pub(crate) mod converters;
-pub(crate) mod image_data;
+pub mod image_data;
 pub(crate) mod image_writer;
+pub(crate) mod parallel;
 pub(crate) mod render_char_to_png;
@@ -3,3 +3,4 @@
 pub(crate) mod generic_converter;
 pub(crate) mod gif_converter;
 pub(crate) mod png_converter;
+pub(crate) mod typewriter_converter;
@@ -4,12 +4,24 @@ use std::{collections::HashMap, sync::Arc};
 
 use crate::{
     ImgiiOptions,
-    conversion::{image_data::ImageData, render_char_to_png::str_to_png},
+    conversion::{
+        image_data::ImageData,
+        image_writer::check_memory_budget,
+        render_char_to_png::{emphasis_multiplier, str_to_jittered_png, str_to_png},
+    },
     error::{FontError, ImageError, ImgiiError, ParseError},
+    fonts::validate_font_glyphs,
+    grid::{cell_regex, find_dropped_chars, parse_ascii_row},
+    options::GradientDirection,
+    stats::RenderStats,
 };
 
-use super::super::render_char_to_png::{ColoredStr, str_to_transparent_png};
+use super::super::render_char_to_png::{ColoredStr, create_cell, str_to_transparent_png};
 use ab_glyph::FontRef;
+use image::{
+    GrayImage, Luma, Pixel, Rgb,
+    imageops::{self, FilterType},
+};
 use regex::Regex;
 
 /// Simple struct for holding a 2d image with its width and height.
@@ -18,134 +30,290 @@ pub(crate) struct Imgii2dImage {
     pub(crate) image_2d: Vec<Arc<ImageData>>,
     pub(crate) width: usize,
     pub(crate) height: usize,
+    /// Statistics gathered while rendering this image.
+    pub(crate) stats: RenderStats,
+    /// The unique rendered cells of this image. Empty if the glyph cache is disabled.
+    pub(crate) glyph_cache: HashMap<ColoredStr, Arc<ImageData>>,
 }
 
-/// Generic function for parsing and rendering ASCII into an image.
-///
-/// * `imgii_options`: The imgii options for rendering ASCII.
-/// * `ascii_text`: The ASCII text to render.
-///
-/// # Returns
-/// `Ok` containing a 2d `Vec` if `ImageData`, holding each character image, otherwise an `Err`.
-pub(crate) fn render_ascii_generic(
-    imgii_options: &ImgiiOptions,
-    ascii_text: String,
-) -> Result<Imgii2dImage, ImgiiError> {
-    // set up font for rendering
-    let font = FontRef::try_from_slice(imgii_options.font().as_slice())
-        // there's nothing useful in this error, convert it!
-        .map_err(|_| FontError::FontLoad {
-            font_name: String::from(imgii_options.font_name()),
-        })?;
+/// Renders ASCII text one row at a time, reusing rendered glyphs between rows.
+pub(crate) struct AsciiRowRenderer<'a> {
+    /// The imgii options for rendering ASCII.
+    imgii_options: &'a ImgiiOptions<'a>,
+    /// The font to render with.
+    font: FontRef<'a>,
+    /// The regex for finding each colored character in a row.
+    re: Regex,
+    /// Already rendered images, so we don't have to render them more than once! Rendering is slow
+    rendered_images: HashMap<ColoredStr, Arc<ImageData>>,
+    /// The image for whitespace, created once since it will always be the same.
+    transparent_png: Arc<ImageData>,
+    /// The images for whitespace with a cell background, by the color of the background.
+    background_cells: HashMap<[u8; 3], Arc<ImageData>>,
+    /// Statistics gathered while rendering.
+    stats: RenderStats,
+    /// The width of every row, in characters. Unknown until the first row has been rendered.
+    width: Option<usize>,
+    /// The number of rows that will be rendered.
+    height: usize,
+    /// The character rendered like whitespace, if there is one.
+    transparent_char: Option<char>,
+}
 
-    // 2d Vec of images for each character
-    let mut image_2d_vec = Vec::new();
+impl<'a> AsciiRowRenderer<'a> {
+    /// Creates a new [`AsciiRowRenderer`], loading the font.
+    ///
+    /// * `imgii_options`: The imgii options for rendering ASCII.
+    /// * `height`: The number of rows that will be rendered.
+    pub(crate) fn new(
+        imgii_options: &'a ImgiiOptions<'a>,
+        height: usize,
+    ) -> Result<Self, ImgiiError> {
+        // set up font for rendering
+        let font = FontRef::try_from_slice(imgii_options.font().as_slice())
+            // there's nothing useful in this error, convert it!
+            .map_err(|_| FontError::FontLoad {
+                font_name: String::from(imgii_options.font_name()),
+            })?;
+        // make sure the font can actually render something, so we don't silently output a blank
+        // image
+        validate_font_glyphs(&font, imgii_options.font_name())?;
 
-    // width and height, in characters
-    // NOTE: we can know height beforehand but we have to wait until we have parsed a whole line of
-    // text to know the width
-    let (mut width, height) = (0, ascii_text.lines().count());
+        Ok(Self {
+            imgii_options,
+            font,
+            re: cell_regex()?,
+            rendered_images: HashMap::new(),
+            transparent_png: Arc::from(str_to_transparent_png(imgii_options)),
+            background_cells: HashMap::new(),
+            stats: RenderStats::default(),
+            width: None,
+            height,
+            transparent_char: imgii_options.transparent_char(),
+        })
+    }
 
-    // hold already rendered images so we don't have to render them more than once! Rendering is
-    // slow
-    let mut rendered_images: HashMap<ColoredStr, Arc<ImageData>> = HashMap::new();
-    // create transparent image once since it will always be the same
-    let transparent_png = Arc::from(str_to_transparent_png(imgii_options));
+    /// Gets the width of every row, in characters, if a row has been rendered.
+    pub(crate) fn width(&self) -> Option<usize> {
+        self.width
+    }
 
-    // read every line in the file
-    for (i, line) in ascii_text.lines().enumerate() {
-        // we need to find each character that we are going to write
-        // we assume that there's only one character for each color
-        // NOTE: \u{1b} represents the \033 character
-        let pattern_str = concat!('\u{1b}', r"\[38;2;([0-9]+);([0-9]+);([0-9]+)m(.)");
-
-        // TODO: if multiple threads are using this same regex object, maybe we could make it a
-        // static global or compile it early so we can reuse it? Maybe as a "parser" object?
-        let re = Regex::new(pattern_str)?;
-
-        // current line's width
-        let mut line_width = 0;
-
-        // create the image for this character
-        for (_full_str, [r, g, b, the_str]) in re.captures_iter(line).map(|c| c.extract()) {
-            let red = r.parse::<u8>().map_err(|err| ParseError::ParseColor {
-                value_name: String::from("red"),
-                the_str: String::from(the_str),
-                err,
-            })?;
-            let green = g.parse::<u8>().map_err(|err| ParseError::ParseColor {
-                value_name: String::from("green"),
-                the_str: String::from(the_str),
-                err,
-            })?;
-            let blue = b.parse::<u8>().map_err(|err| ParseError::ParseColor {
-                value_name: String::from("blue"),
-                the_str: String::from(the_str),
-                err,
-            })?;
+    /// Gets the image of a blank cell, which is transparent (or the background, if it's enabled).
+    pub(crate) fn blank_cell(&self) -> Arc<ImageData> {
+        self.transparent_png.clone()
+    }
+
+    /// Moves out of this renderer, returning the statistics gathered while rendering, followed by
+    /// the unique rendered cells.
+    pub(crate) fn into_parts(self) -> (RenderStats, HashMap<ColoredStr, Arc<ImageData>>) {
+        (self.stats, self.rendered_images)
+    }
+
+    /// Parses and renders a single row of ASCII text into an image for each character. Parsing is
+    /// done by [`crate::grid`], so tabs are expanded to [`ImgiiOptions::tab_width`] transparent
+    /// cells and carriage returns are ignored. Other control characters are rendered as spaces.
+    ///
+    /// * `row`: The index of this row, starting from 0 as the top row.
+    /// * `line`: The ASCII text of this row.
+    pub(crate) fn render_row(
+        &mut self,
+        row: usize,
+        line: &str,
+    ) -> Result<Vec<Arc<ImageData>>, ImgiiError> {
+        let mut row_images = Vec::with_capacity(self.width.unwrap_or(0));
+        self.render_row_into(row, line, &mut row_images)?;
+        Ok(row_images)
+    }
+
+    /// Parses and renders a single row of ASCII text like [`AsciiRowRenderer::render_row`], but
+    /// appends each image to the end of `images`. The row is parsed as it's rendered, so even an
+    /// extremely long row never has to be held in memory twice.
+    ///
+    /// * `row`: The index of this row, starting from 0 as the top row.
+    /// * `line`: The ASCII text of this row.
+    /// * `images`: The images to append the images of this row to.
+    pub(crate) fn render_row_into(
+        &mut self,
+        row: usize,
+        line: &str,
+        images: &mut Vec<Arc<ImageData>>,
+    ) -> Result<(), ImgiiError> {
+        let imgii_options = self.imgii_options;
+        let row_start = images.len();
+
+        // check the length in bytes first, since it's free and can't be less than the characters
+        let max_line_chars = imgii_options.max_line_chars();
+        if line.len() > max_line_chars && line.chars().count() > max_line_chars {
+            return Err(ParseError::LineTooLong {
+                row,
+                max_line_chars,
+            }
+            .into());
+        }
+
+        if imgii_options.strict_parse()
+            && let Some(dropped) = find_dropped_chars(&self.re, line)
+        {
+            return Err(ParseError::DroppedCharacters { row, dropped }.into());
+        }
+
+        // gradients running across columns need the width of the grid, even for the first row
+        let grid_size = match (imgii_options.gradient_override(), self.width) {
+            (Some(gradient), None) if gradient.direction() == GradientDirection::Columns => (
+                parse_ascii_row(
+                    &self.re,
+                    line,
+                    imgii_options.tab_width(),
+                    imgii_options.zero_width_handling(),
+                )
+                .count(),
+                self.height,
+            ),
+            (_, width) => (width.unwrap_or(0), self.height),
+        };
+
+        // create the image for each character
+        for cell in parse_ascii_row(
+            &self.re,
+            line,
+            imgii_options.tab_width(),
+            imgii_options.zero_width_handling(),
+        ) {
+            let cell = cell?;
+
+            // in sparse mode, only some cells have their glyph rendered
+            // NOTE: width is unknown for the first row, which still gives us the right cell index
+            let column = images.len() - row_start;
+            let cell_idx = row * self.width.unwrap_or(0) + column;
+            let is_sparse_skipped = !cell_idx.is_multiple_of(imgii_options.sparsity() as usize);
 
             let generated_png = {
-                if the_str.trim().is_empty() {
+                let is_transparent_char = self.transparent_char == Some(cell.character);
+                // control characters that slip through have nothing to draw, so they're blank
+                // like a space rather than letting them corrupt the grid
+                let is_blank = cell.is_blank() || is_transparent_char;
+                if is_sparse_skipped {
+                    // skipped cells drop their cell background too, otherwise a grid of cell
+                    // backgrounds would hide the dotted look entirely
+                    self.transparent_png.clone()
+                } else if let (true, Some(background)) = (is_blank, cell.background) {
+                    // the cell background is still filled in, even without a glyph
+                    self.background_cells
+                        .entry(background)
+                        .or_insert_with(|| {
+                            Arc::new(ImageData::new(create_cell(imgii_options, Some(background))))
+                        })
+                        .clone()
+                } else if is_blank {
                     // create a transparent png for a space
-                    transparent_png.clone()
+                    self.transparent_png.clone()
                 } else {
                     // render the actual text if it's not empty
+                    let mut color = Rgb([cell.red, cell.green, cell.blue]);
+                    if let Some(gradient) = imgii_options.gradient_override() {
+                        color = Rgb(gradient.color_at((row, column), grid_size));
+                    }
+                    if imgii_options.grayscale() {
+                        let Luma([brightness]) = color.to_luma();
+                        color = Rgb([brightness; 3]);
+                    }
+                    let [red, green, blue] = color.0;
                     let colored = ColoredStr {
                         red,
                         green,
                         blue,
-                        string: String::from(the_str),
+                        string: cell.grapheme(),
+                        background: cell.background,
                     };
+                    self.stats.record(&colored.string, color);
 
-                    // check if this image was already rendered before
-                    let rendered_img = rendered_images.get(&colored);
-                    match rendered_img {
-                        // we have rendered this image before, so clone it
-                        Some(rendered_img) => rendered_img.clone(),
-                        None => {
-                            // we haven't rendered this image before, so render it
-                            let image_data = Arc::from(str_to_png(&colored, &font, imgii_options));
-                            let result = rendered_images.insert(colored, image_data.clone());
-                            match result {
-                                None => image_data,
-                                Some(colored) => {
-                                    // the returned image from insert should be the same as the one
-                                    // we put in
-                                    return Err(ImageError::Render {
-                                        reason: format!(
-                                            "the image ({colored:?}) should not exist already in the hash map",
-                                        ),
-                                    }.into());
-                                }
-                            }
-                        }
+                    if let Some(jitter) = imgii_options.jitter() {
+                        // every cell is jittered differently, so they can never be cached
+                        Arc::from(str_to_jittered_png(
+                            &colored,
+                            &self.font,
+                            imgii_options,
+                            &jitter,
+                            (row, column),
+                        ))
+                    } else if emphasis_multiplier(&colored, imgii_options) != 1.0 {
+                        // emphasized glyphs bypass the cache
+                        Arc::from(str_to_png(&colored, &self.font, imgii_options))
+                    } else {
+                        render_cached(
+                            colored,
+                            &mut self.rendered_images,
+                            &self.font,
+                            imgii_options,
+                        )?
                     }
                 }
             };
 
-            line_width += 1;
-            image_2d_vec.push(generated_png);
+            images.push(generated_png);
         }
 
-        if i == 0 {
-            // get the width of the entire image. This should always be the same
-            width = line_width;
-            // now we can reserve the rest of the capacity we need for our vec
-            // NOTE: this can panic if the vec is too large
-            image_2d_vec.reserve(width * height);
-        } else {
+        let row_width = images.len() - row_start;
+        match self.width {
             // check that this width is always the same now that we have the width
-            if width != line_width {
+            Some(width) if width != row_width => {
                 return Err(ImageError::Render {
                     reason: format!(
-                        "width {} is not equal to the current line width {}",
-                        width, line_width
+                        "width {width} is not equal to the current line width {row_width}"
                     ),
                 }
                 .into());
             }
+            Some(_) => {}
+            // get the width of the entire image. This should always be the same
+            None => self.width = Some(row_width),
+        }
+
+        Ok(())
+    }
+}
+
+/// Generic function for parsing and rendering ASCII into an image.
+///
+/// * `imgii_options`: The imgii options for rendering ASCII.
+/// * `ascii_text`: The ASCII text to render.
+///
+/// # Returns
+/// `Ok` containing a 2d `Vec` if `ImageData`, holding each character image, otherwise an `Err`.
+pub(crate) fn render_ascii_generic(
+    imgii_options: &ImgiiOptions,
+    ascii_text: String,
+) -> Result<Imgii2dImage, ImgiiError> {
+    // NOTE: we can know height beforehand but we have to wait until we have parsed a whole line of
+    // text to know the width
+    let height = ascii_text.lines().count();
+    let mut row_renderer = AsciiRowRenderer::new(imgii_options, height)?;
+
+    // 2d Vec of images for each character
+    let mut image_2d_vec = Vec::new();
+
+    // read every line in the file
+    for (i, line) in ascii_text.lines().enumerate() {
+        // render straight into the 2d vec, so rows aren't copied
+        row_renderer.render_row_into(i, line, &mut image_2d_vec)?;
+        if i == 0 {
+            // we know how large the image will be now, so make sure it will fit in memory before
+            // rendering any more of it
+            let width = image_2d_vec.len();
+            let (canvas_width, canvas_height) = imgii_options
+                .fixed_grid()
+                .map_or((width, height), |(fixed_width, fixed_height)| {
+                    (fixed_width as usize, fixed_height as usize)
+                });
+            // every cell is rendered as RGBA
+            check_memory_budget(canvas_width, canvas_height, 4, imgii_options)?;
+
+            // now we can reserve the rest of the capacity we need for our vec
+            // NOTE: this can panic if the vec is too large
+            image_2d_vec.reserve(width * (height - 1));
         }
     }
+    let width = row_renderer.width().unwrap_or(0);
 
     // Check that the length of the final vector is what we expect. If not, something has gone
     // terribly wrong, and we should not continue.
@@ -160,9 +328,513 @@ pub(crate) fn render_ascii_generic(
         .into());
     }
 
+    let (image_2d_vec, width, height) = match imgii_options.fixed_grid() {
+        Some((fixed_width, fixed_height)) => {
+            let fixed_size = (fixed_width as usize, fixed_height as usize);
+            let blank_cell = row_renderer.blank_cell();
+            (
+                fit_to_grid(image_2d_vec, (width, height), fixed_size, &blank_cell),
+                fixed_size.0,
+                fixed_size.1,
+            )
+        }
+        None => (image_2d_vec, width, height),
+    };
+    let image_2d_vec = match imgii_options.opacity_mask() {
+        Some(mask) => apply_opacity_mask(image_2d_vec, (width, height), mask),
+        None => image_2d_vec,
+    };
+
+    let (stats, glyph_cache) = row_renderer.into_parts();
     Ok(Imgii2dImage {
         image_2d: image_2d_vec,
         width,
         height,
+        stats,
+        glyph_cache,
     })
 }
+
+/// Crops and pads a 2d `Vec` of cell images to a fixed grid size. Cells past the right or bottom
+/// edge are dropped, and any missing cells are filled with `blank_cell`.
+///
+/// * `image_2d_vec`: The cell images, row by row.
+/// * `(width, height)`: The size of the grid the cells are in.
+/// * `(fixed_width, fixed_height)`: The size of the grid to fit the cells to.
+/// * `blank_cell`: The image of a blank cell, for padding.
+fn fit_to_grid(
+    image_2d_vec: Vec<Arc<ImageData>>,
+    (width, height): (usize, usize),
+    (fixed_width, fixed_height): (usize, usize),
+    blank_cell: &Arc<ImageData>,
+) -> Vec<Arc<ImageData>> {
+    if (width, height) == (fixed_width, fixed_height) {
+        // already the right size
+        return image_2d_vec;
+    }
+
+    let mut fitted = Vec::with_capacity(fixed_width * fixed_height);
+    for row in 0..fixed_height {
+        let row_cells = if row < height {
+            &image_2d_vec[row * width..row * width + width.min(fixed_width)]
+        } else {
+            &[]
+        };
+        fitted.extend_from_slice(row_cells);
+        fitted.resize(
+            fitted.len() + fixed_width - row_cells.len(),
+            blank_cell.clone(),
+        );
+    }
+    fitted
+}
+
+/// Fades out each cell of a 2d `Vec` of cell images by an opacity mask, multiplying the alpha of
+/// every pixel in a cell by the brightness of the mask at that cell.
+///
+/// * `image_2d_vec`: The cell images, row by row.
+/// * `(width, height)`: The size of the grid the cells are in.
+/// * `mask`: The grayscale mask, which is stretched over the grid.
+fn apply_opacity_mask(
+    image_2d_vec: Vec<Arc<ImageData>>,
+    (width, height): (usize, usize),
+    mask: &GrayImage,
+) -> Vec<Arc<ImageData>> {
+    if width == 0 || height == 0 {
+        // nothing to fade
+        return image_2d_vec;
+    }
+    // a smooth filter, so the edges of the mask fade gradually across cells
+    let mask = imageops::resize(mask, width as u32, height as u32, FilterType::Triangle);
+
+    // cells are shared between every place they're used, so only fade each one once per opacity
+    let mut faded_cells: HashMap<(*const ImageData, u8), Arc<ImageData>> = HashMap::new();
+    image_2d_vec
+        .into_iter()
+        .enumerate()
+        .map(|(i, cell)| {
+            let Luma([opacity]) = *mask.get_pixel((i % width) as u32, (i / width) as u32);
+            if opacity == u8::MAX {
+                return cell;
+            }
+            faded_cells
+                .entry((Arc::as_ptr(&cell), opacity))
+                .or_insert_with(|| {
+                    let mut faded = cell.as_buffer().clone();
+                    for pixel in faded.pixels_mut() {
+                        pixel.0[3] =
+                            (u16::from(pixel.0[3]) * u16::from(opacity) / u16::from(u8::MAX)) as u8;
+                    }
+                    Arc::new(ImageData::new(faded))
+                })
+                .clone()
+        })
+        .collect()
+}
+
+/// Renders a colored string, reusing the image if it has been rendered before. Rendering is slow,
+/// so this avoids rendering the same colored string more than once.
+///
+/// * `colored`: The colored string to render.
+/// * `rendered_images`: The already rendered images.
+/// * `font`: The font to render with.
+/// * `imgii_options`: The imgii options for rendering ASCII.
+fn render_cached(
+    colored: ColoredStr,
+    rendered_images: &mut HashMap<ColoredStr, Arc<ImageData>>,
+    font: &FontRef<'_>,
+    imgii_options: &ImgiiOptions,
+) -> Result<Arc<ImageData>, ImgiiError> {
+    // check if this image was already rendered before
+    let rendered_img = rendered_images.get(&colored);
+    match rendered_img {
+        // we have rendered this image before, so clone it
+        Some(rendered_img) => Ok(rendered_img.clone()),
+        None => {
+            // we haven't rendered this image before, so render it
+            let image_data = Arc::from(str_to_png(&colored, font, imgii_options));
+            if imgii_options.disable_glyph_cache() {
+                // never store anything in the cache, so every character is rendered fresh
+                return Ok(image_data);
+            }
+
+            let result = rendered_images.insert(colored, image_data.clone());
+            match result {
+                None => Ok(image_data),
+                Some(colored) => {
+                    // the returned image from insert should be the same as the one we put in
+                    Err(ImageError::Render {
+                        reason: format!(
+                            "the image ({colored:?}) should not exist already in the hash map",
+                        ),
+                    }
+                    .into())
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        conversion::{
+            converters::png_converter::image_to_ascii, image_writer::AsciiImageWriter,
+            render_char_to_png::calculate_char_dimensions,
+        },
+        options::{CellRounding, GradientDirection, GradientSpec, SpaceHandling},
+        test_support::{ansi_cell, test_dir, test_options_builder},
+    };
+    use image::{DynamicImage, RgbImage};
+
+    /// Creates colored ASCII text of a grid of characters, alternating between a glyph and a space
+    /// so both kinds of cell are rendered.
+    ///
+    /// * `(width, height)`: The size of the grid, in characters.
+    fn ascii_grid((width, height): (usize, usize)) -> String {
+        (0..height)
+            .map(|row| {
+                (0..width)
+                    .map(|column| {
+                        let character = if (row + column) % 2 == 0 { '#' } else { ' ' };
+                        ansi_cell([0, 128, 255], character)
+                    })
+                    .collect::<String>()
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Gets the (lowest, highest) alpha of the pixels of a cell.
+    fn alpha_range(cell: &ImageData) -> (u8, u8) {
+        cell.as_buffer()
+            .pixels()
+            .fold((u8::MAX, 0), |(lowest, highest), pixel| {
+                (lowest.min(pixel.0[3]), highest.max(pixel.0[3]))
+            })
+    }
+
+    #[test]
+    fn transparent_spaces_ignore_the_background() {
+        let imgii_options = test_options_builder()
+            .background(true)
+            .space_handling(SpaceHandling::Transparent)
+            .build()
+            .unwrap();
+        let ascii_text = [
+            ansi_cell([255, 0, 0], '#'),
+            ansi_cell([255, 0, 0], ' '),
+            ansi_cell([255, 0, 0], '#'),
+        ]
+        .concat();
+
+        let image = render_ascii_generic(&imgii_options, ascii_text).unwrap();
+        assert_eq!((image.width, image.height), (3, 1));
+        // the glyphs keep their background, but the space punches a hole through it
+        assert!(alpha_range(&image.image_2d[0]).0 > 0);
+        assert_eq!(alpha_range(&image.image_2d[1]), (0, 0));
+        assert!(alpha_range(&image.image_2d[2]).0 > 0);
+    }
+
+    #[test]
+    fn ligature_pairs_render_cell_by_cell() {
+        let imgii_options = test_options_builder().build().unwrap();
+
+        // DejaVu Sans Mono has ligatures for these pairs, which must never form across cells
+        for pair in ["->", "fi", "fl", "=="] {
+            let ascii_text: String = pair
+                .chars()
+                .map(|character| ansi_cell([255, 255, 255], character))
+                .collect();
+            let image = render_ascii_generic(&imgii_options, ascii_text).unwrap();
+            assert_eq!((image.width, image.height), (2, 1));
+
+            for (cell, character) in image.image_2d.iter().zip(pair.chars()) {
+                let alone =
+                    render_ascii_generic(&imgii_options, ansi_cell([255, 255, 255], character))
+                        .unwrap();
+                assert!(
+                    *cell.as_buffer() == *alone.image_2d[0].as_buffer(),
+                    "{character:?} of {pair:?} differs from {character:?} rendered alone"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn gradient_ends_are_exactly_the_first_and_last_stops() {
+        let stops = vec![[255, 0, 0], [0, 255, 0], [0, 0, 255]];
+        for direction in [GradientDirection::Rows, GradientDirection::Columns] {
+            let gradient = GradientSpec::new(stops.clone())
+                .unwrap()
+                .with_direction(direction);
+            let imgii_options = test_options_builder()
+                .gradient_override(gradient)
+                .build()
+                .unwrap();
+            let row = ansi_cell([128, 128, 128], '#').repeat(5);
+            let ascii_text = vec![row; 4].join("\n");
+
+            let image = render_ascii_generic(&imgii_options, ascii_text).unwrap();
+            assert_eq!((image.width, image.height), (5, 4));
+            // the end cells are drawn exactly like glyphs colored by the first and last stops
+            let drawn_in = |color| {
+                let plain_options = test_options_builder().build().unwrap();
+                render_ascii_generic(&plain_options, ansi_cell(color, '#'))
+                    .unwrap()
+                    .image_2d
+                    .remove(0)
+            };
+            let (first_stop, last_stop) = (drawn_in([255, 0, 0]), drawn_in([0, 0, 255]));
+            let (first_cells, last_cells): (Vec<_>, Vec<_>) = match direction {
+                GradientDirection::Rows => ((0..5).collect(), (15..20).collect()),
+                GradientDirection::Columns => (
+                    (0..4).map(|row| row * 5).collect(),
+                    (0..4).map(|row| row * 5 + 4).collect(),
+                ),
+            };
+            for i in first_cells {
+                assert!(
+                    *image.image_2d[i].as_buffer() == *first_stop.as_buffer(),
+                    "cell {i} isn't the first stop of the {direction:?} gradient"
+                );
+            }
+            for i in last_cells {
+                assert!(
+                    *image.image_2d[i].as_buffer() == *last_stop.as_buffer(),
+                    "cell {i} isn't the last stop of the {direction:?} gradient"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn black_halves_of_opacity_masks_are_transparent() {
+        let dir = test_dir("black_halves_of_opacity_masks_are_transparent");
+        let mask_file_name = dir.join("mask.png");
+        GrayImage::from_fn(8, 4, |x, _| Luma([if x < 4 { 0 } else { u8::MAX }]))
+            .save(&mask_file_name)
+            .unwrap();
+        let plain_options = test_options_builder().background(true).build().unwrap();
+        let masked_options = test_options_builder()
+            .background(true)
+            .opacity_mask(mask_file_name)
+            .build()
+            .unwrap();
+        let row = ansi_cell([255, 0, 0], '#').repeat(8);
+        let ascii_text = vec![row; 4].join("\n");
+
+        let plain = render_ascii_generic(&plain_options, ascii_text.clone()).unwrap();
+        let masked = render_ascii_generic(&masked_options, ascii_text).unwrap();
+        assert_eq!((masked.width, masked.height), (8, 4));
+        for (i, (plain_cell, masked_cell)) in
+            plain.image_2d.iter().zip(&masked.image_2d).enumerate()
+        {
+            if i % 8 < 4 {
+                assert_eq!(
+                    alpha_range(masked_cell),
+                    (0, 0),
+                    "cell {i} isn't transparent"
+                );
+            } else {
+                assert!(
+                    *masked_cell.as_buffer() == *plain_cell.as_buffer(),
+                    "cell {i} was faded"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn background_spaces_keep_the_background() {
+        let imgii_options = test_options_builder()
+            .background(true)
+            .space_handling(SpaceHandling::Background)
+            .build()
+            .unwrap();
+
+        let image = render_ascii_generic(&imgii_options, ansi_cell([255, 0, 0], ' ')).unwrap();
+        assert_eq!(alpha_range(&image.image_2d[0]), (u8::MAX, u8::MAX));
+    }
+
+    #[test]
+    fn renders_exactly_the_grid_of_the_ascii_text() {
+        let imgii_options = test_options_builder().build().unwrap();
+
+        let image = render_ascii_generic(&imgii_options, ascii_grid((200, 10))).unwrap();
+        assert_eq!((image.width, image.height), (200, 10));
+        assert_eq!(image.image_2d.len(), 200 * 10);
+
+        let (char_width, char_height) = calculate_char_dimensions(&imgii_options);
+        let stitched = AsciiImageWriter::from_2d_vec(image, &imgii_options).unwrap();
+        assert_eq!(
+            stitched.imagebuf.as_buffer().dimensions(),
+            (200 * char_width, 10 * char_height)
+        );
+    }
+
+    #[test]
+    fn fixed_grids_are_the_same_size_for_any_input() {
+        let imgii_options = test_options_builder().fixed_grid(8, 5).build().unwrap();
+        let (char_width, char_height) = calculate_char_dimensions(&imgii_options);
+
+        // smaller than the fixed grid, larger than it, and larger in only one direction
+        for size in [(3, 2), (20, 9), (12, 1), (1, 12)] {
+            let image = render_ascii_generic(&imgii_options, ascii_grid(size)).unwrap();
+            assert_eq!((image.width, image.height), (8, 5), "{size:?}");
+            assert_eq!(image.image_2d.len(), 8 * 5, "{size:?}");
+
+            let stitched = AsciiImageWriter::from_2d_vec(image, &imgii_options).unwrap();
+            assert_eq!(
+                stitched.imagebuf.as_buffer().dimensions(),
+                (8 * char_width, 5 * char_height),
+                "{size:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn rounded_cells_keep_the_rightmost_column() {
+        // the last glyph is a different color, so it can be told apart from the rest of the row
+        let ascii_text = ansi_cell([0, 128, 255], '#').repeat(499) + &ansi_cell([255, 0, 0], '@');
+
+        for cell_rounding in [
+            CellRounding::Floor,
+            CellRounding::Nearest,
+            CellRounding::Ceil,
+        ] {
+            // an odd font size, so cells are a fractional number of pixels wide
+            let imgii_options = test_options_builder()
+                .font_size(15)
+                .cell_rounding(cell_rounding)
+                .build()
+                .unwrap();
+            let (char_width, char_height) = calculate_char_dimensions(&imgii_options);
+
+            let image = render_ascii_generic(&imgii_options, ascii_text.clone()).unwrap();
+            let last_cell = image.image_2d[499].as_buffer().clone();
+            let stitched = AsciiImageWriter::from_2d_vec(image, &imgii_options).unwrap();
+            let stitched = stitched.imagebuf.as_buffer();
+            assert_eq!(
+                stitched.dimensions(),
+                (char_width * 500, char_height),
+                "{cell_rounding:?}"
+            );
+            let rightmost =
+                image::imageops::crop_imm(stitched, char_width * 499, 0, char_width, char_height)
+                    .to_image();
+            assert!(
+                rightmost == last_cell,
+                "the rightmost column is clipped with {cell_rounding:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn width_and_height_are_not_corrected_for_aspect_ratio() {
+        // a square image would be far taller than 10 rows if the aspect ratio were kept
+        let imgii_options = test_options_builder()
+            .width(200)
+            .height(10)
+            .build()
+            .unwrap();
+        let source = DynamicImage::ImageRgb8(RgbImage::from_fn(64, 64, |x, y| {
+            image::Rgb([(x * 4) as u8, (y * 4) as u8, 128])
+        }));
+
+        let ascii_text = image_to_ascii(&source, &imgii_options).unwrap();
+        let image = render_ascii_generic(&imgii_options, ascii_text).unwrap();
+        assert_eq!((image.width, image.height), (200, 10));
+        assert_eq!(image.image_2d.len(), 200 * 10);
+    }
+
+    #[test]
+    fn sparse_mode_renders_every_nth_cell() {
+        let imgii_options = test_options_builder().sparsity(3).build().unwrap();
+        // every cell has a cell background, which would otherwise make every cell visible
+        let row = format!("\x1b[48;2;0;0;255m{}", ansi_cell([255, 0, 0], '#')).repeat(7);
+        let ascii_text = vec![row; 5].join("\n");
+
+        let image = render_ascii_generic(&imgii_options, ascii_text).unwrap();
+        assert_eq!((image.width, image.height), (7, 5));
+        let visible = image
+            .image_2d
+            .iter()
+            .filter(|cell| alpha_range(cell).1 > 0)
+            .count();
+        assert_eq!(visible, (7 * 5usize).div_ceil(3));
+        for (cell_idx, cell) in image.image_2d.iter().enumerate() {
+            assert_eq!(alpha_range(cell).1 > 0, cell_idx % 3 == 0);
+        }
+    }
+
+    #[test]
+    fn control_characters_render_as_blank_cells() {
+        let imgii_options = test_options_builder().build().unwrap();
+        let ascii_text = [
+            [
+                ansi_cell([255, 0, 0], '#'),
+                ansi_cell([255, 0, 0], '\u{7}'),
+                ansi_cell([255, 0, 0], '#'),
+            ]
+            .concat(),
+            ansi_cell([255, 0, 0], '#').repeat(3),
+        ]
+        .join("\n");
+
+        let image = render_ascii_generic(&imgii_options, ascii_text).unwrap();
+        // the control character still takes up its cell, so the rows line up
+        assert_eq!((image.width, image.height), (3, 2));
+        assert_eq!(alpha_range(&image.image_2d[1]), (0, 0));
+        for cell_idx in [0, 2, 3, 4, 5] {
+            assert!(alpha_range(&image.image_2d[cell_idx]).1 > 0);
+        }
+    }
+
+    #[test]
+    fn very_long_lines_share_their_cell_images() {
+        let imgii_options = test_options_builder().build().unwrap();
+        let ascii_text = [ansi_cell([255, 0, 0], '#'), ansi_cell([255, 0, 0], ' ')]
+            .concat()
+            .repeat(50_000);
+
+        let image = render_ascii_generic(&imgii_options, ascii_text).unwrap();
+        assert_eq!((image.width, image.height), (100_000, 1));
+        // the row holds only pointers, so its memory is bounded by the unique cells rather than
+        // the width of the line
+        let unique_cells = image
+            .image_2d
+            .iter()
+            .map(Arc::as_ptr)
+            .collect::<std::collections::HashSet<_>>();
+        assert_eq!(unique_cells.len(), 2);
+    }
+
+    #[test]
+    fn oversized_lines_are_an_error() {
+        let imgii_options = test_options_builder().max_line_chars(1000).build().unwrap();
+        let ascii_text = [
+            ansi_cell([255, 0, 0], '#').repeat(10),
+            ansi_cell([255, 0, 0], '#').repeat(100),
+        ]
+        .join("\n");
+
+        let result = render_ascii_generic(&imgii_options, ascii_text);
+        assert!(matches!(
+            result,
+            Err(ImgiiError::Parse(ParseError::LineTooLong {
+                row: 1,
+                max_line_chars: 1000
+            }))
+        ));
+
+        // the default limit stops a huge line before it's parsed
+        let imgii_options = test_options_builder().build().unwrap();
+        let result = render_ascii_generic(&imgii_options, "#".repeat(8 * 1024 * 1024));
+        assert!(matches!(
+            result,
+            Err(ImgiiError::Parse(ParseError::LineTooLong { row: 0, .. }))
+        ));
+    }
+}
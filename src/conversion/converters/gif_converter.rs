@@ -1,20 +1,44 @@
 //! Handles rendering for GIF.
 
-use std::{fs::File, io::BufReader};
+use std::{
+    fs::File,
+    io::{BufReader, Write},
+    time::Duration,
+};
 
 use crate::{
-    conversion::converters::generic_converter::{Imgii2dImage, render_ascii_generic},
-    error::ImgiiError,
-    options::{ImgiiOptions, RasciiOptions},
+    conversion::{
+        converters::{
+            generic_converter::{Imgii2dImage, render_ascii_generic},
+            png_converter::image_to_ascii,
+        },
+        image_data::InternalImage,
+        image_writer::{write_if_changed, write_output_file},
+        parallel::{IntoParallelIterator, ParallelIterator},
+    },
+    error::{FontError, ImageError, ImgiiError},
+    frame_report::{FrameFailure, GifConversionReport},
+    options::{GifDisposal, ImgiiOptions},
+    progress::ProgressTracker,
 };
 
-use image::{AnimationDecoder, Delay, DynamicImage, codecs::gif::GifDecoder};
-use rascii_art_img::render_image_to;
-use rayon::iter::{IntoParallelIterator, ParallelIterator};
+use ab_glyph::{FontRef, PxScale};
+use image::{
+    AnimationDecoder, Delay, DynamicImage, Frame, ImageBuffer, ImageDecoder, Luma, Pixel, Rgba,
+    codecs::gif::GifDecoder, imageops,
+};
+use imageproc::drawing::{draw_text_mut, text_size};
 
+/// The padding above and below each frame label of a contact sheet, in pixels.
+const CONTACT_SHEET_LABEL_PADDING: u32 = 2;
+/// The delay used in place of a delay of zero in a CSS sprite animation, matching how browsers
+/// play GIF frames without a delay.
+const CSS_SPRITE_ZERO_DELAY: Duration = Duration::from_millis(100);
 /// Holds the metadata for a frame that has been deconstructed.
 #[derive(Debug, Clone)]
 pub(crate) struct FrameMetadata {
+    /// The index of this frame in the input GIF.
+    index: usize,
     /// The left value for this frame.
     left: u32,
     /// The top value for this frame.
@@ -49,8 +73,19 @@ pub(crate) struct RenderedFramePart {
 impl FrameMetadata {
     /// Creates a new [`FrameMetadata`].
     #[must_use]
-    pub(crate) fn new(left: u32, top: u32, delay: Delay) -> Self {
-        Self { left, top, delay }
+    pub(crate) fn new(index: usize, left: u32, top: u32, delay: Delay) -> Self {
+        Self {
+            index,
+            left,
+            top,
+            delay,
+        }
+    }
+
+    /// Gets the index of this frame in the input GIF.
+    #[must_use]
+    pub(crate) fn index(&self) -> usize {
+        self.index
     }
 
     /// Gets the x offset for this frame.
@@ -129,69 +164,157 @@ impl NonRenderedFramePart {
 * Standalone functions
 */
 
-/// Reads a gif as a list of ascii strings, with the frame metadata for the related frame.
-/// Performs a best-effort conversion to ASCII. Some frames may fail to be rendered, which
-/// can be handled by the caller.
+/// Converts deconstructed GIF frames to a list of ascii strings, with the frame metadata for the
+/// related frame. Performs a best-effort conversion to ASCII. Some frames may fail to be rendered,
+/// which are returned as a [`FrameFailure`] for the caller to handle.
 ///
-/// * `input_file_name`: The input file name.
-/// * `rascii_options`: The RASCII options for converting to ASCII.
-pub(crate) fn read_gif_as_deconstructed_ascii(
-    input_file_name: &str,
-    rascii_options: &RasciiOptions,
-) -> Result<Vec<Option<NonRenderedFramePart>>, ImgiiError> {
-    // render the ascii text as images
-    let deconstructed_gif = read_deconstructed_gif(input_file_name)?;
+/// * `deconstructed_gif`: The frames to convert, with their frame metadata.
+/// * `imgii_options`: The imgii options, holding the RASCII options for converting to ASCII.
+pub(crate) fn deconstructed_gif_to_ascii(
+    deconstructed_gif: Vec<(DynamicImage, FrameMetadata)>,
+    imgii_options: &ImgiiOptions,
+) -> Vec<Result<NonRenderedFramePart, FrameFailure>> {
+    // drop frames before converting them so we don't do any extra work
+    let deconstructed_gif = drop_excess_frames(deconstructed_gif, imgii_options);
+    frames_to_ascii(deconstructed_gif, imgii_options)
+}
+
+/// Drops the frames of a deconstructed GIF that won't be in the output, keeping at most
+/// [`ImgiiOptions::max_frames`] frames and decimating to [`ImgiiOptions::target_duration_ms`].
+///
+/// * `deconstructed_gif`: The frames of the GIF, with their frame metadata.
+/// * `imgii_options`: The imgii options, holding how many frames to keep.
+pub(crate) fn drop_excess_frames(
+    mut deconstructed_gif: Vec<(DynamicImage, FrameMetadata)>,
+    imgii_options: &ImgiiOptions,
+) -> Vec<(DynamicImage, FrameMetadata)> {
+    if let Some(max_frames) = imgii_options.max_frames() {
+        deconstructed_gif = limit_frame_count(deconstructed_gif, max_frames);
+    }
+    if let Some(target_duration_ms) = imgii_options.target_duration_ms() {
+        deconstructed_gif = decimate_to_target_duration(deconstructed_gif, target_duration_ms);
+    }
+    deconstructed_gif
+}
 
+/// Converts deconstructed GIF frames to ASCII, like [`deconstructed_gif_to_ascii`], but converts
+/// every frame given rather than dropping any of them first.
+///
+/// * `deconstructed_gif`: The frames to convert, with their frame metadata.
+/// * `imgii_options`: The imgii options, holding the RASCII options for converting to ASCII.
+pub(crate) fn frames_to_ascii(
+    deconstructed_gif: Vec<(DynamicImage, FrameMetadata)>,
+    imgii_options: &ImgiiOptions,
+) -> Vec<Result<NonRenderedFramePart, FrameFailure>> {
     // convert the GIF frames to ASCII in parallel
-    Ok(deconstructed_gif
+    deconstructed_gif
         .into_par_iter()
         .map(|(image, deconstructed_frame)| {
-            let mut ascii_text = String::new();
+            // stop converting frames as soon as we can once cancelled
+            if let Err(err) = imgii_options.check_cancelled() {
+                return Err(FrameFailure::new(deconstructed_frame.index(), err));
+            }
+
             // this failing for even a single frame of a GIF is not good, but let's try our best!
-            if render_image_to(&image, &mut ascii_text, rascii_options).is_err() {
-                None
-            } else {
-                Some(NonRenderedFramePart::new(ascii_text, deconstructed_frame))
+            match image_to_ascii(&image, imgii_options) {
+                Ok(ascii_text) => Ok(NonRenderedFramePart::new(ascii_text, deconstructed_frame)),
+                Err(err) => Err(FrameFailure::new(deconstructed_frame.index(), err)),
             }
         })
-        .collect())
+        .collect()
 }
 
-/// Reads a gif and converts it to ascii. returns the result containing the image data and frame
-/// metadata required to stitch the images back together. the images returned contain the ascii
-/// representation of the original gif.
+/// Renders the ascii text of deconstructed frames, keeping the frame metadata for each frame.
 ///
-/// NOTE: performs a best-effort conversion, some frames may fail and will be returned as a `None`.
+/// NOTE: performs a best-effort conversion, some frames may fail and will be returned as a
+/// [`FrameFailure`]. Frames that already failed are passed through untouched.
 ///
-/// * `input_file_name`: the input file name.
+/// * `ascii_text`: The ascii text of each frame, with its frame metadata.
 /// * `imgii_options`: the imgii options for rendering ascii.
-pub(crate) fn read_as_deconstructed_rendered_gif_vec(
-    input_file_name: &str,
+pub(crate) fn render_deconstructed_ascii(
+    ascii_text: Vec<Result<NonRenderedFramePart, FrameFailure>>,
     imgii_options: &ImgiiOptions,
-) -> Result<Vec<Option<RenderedFramePart>>, ImgiiError> {
-    let ascii_text =
-        read_gif_as_deconstructed_ascii(input_file_name, imgii_options.rascii_options())?;
+) -> Vec<Result<RenderedFramePart, FrameFailure>> {
+    render_deconstructed_ascii_with_progress(ascii_text, imgii_options, None)
+}
 
+/// Renders the ascii text of deconstructed frames, like [`render_deconstructed_ascii`], but
+/// records each frame as finished with a progress tracker, whether or not it failed.
+///
+/// * `ascii_text`: The ascii text of each frame, with its frame metadata.
+/// * `imgii_options`: the imgii options for rendering ascii.
+/// * `progress`: The progress tracker of the render stage, if any.
+pub(crate) fn render_deconstructed_ascii_with_progress(
+    ascii_text: Vec<Result<NonRenderedFramePart, FrameFailure>>,
+    imgii_options: &ImgiiOptions,
+    progress: Option<&ProgressTracker>,
+) -> Vec<Result<RenderedFramePart, FrameFailure>> {
     // create image data for each frame and keep the frame metadata so we can use it again later
-    Ok(ascii_text
+    ascii_text
         .into_par_iter()
-        .filter_map(|frame| frame) // since we can have bad frames, let's just get rid of them
         .map(|frame_part| {
-            let rendered_image_res = render_ascii_generic(imgii_options, frame_part.image_ascii);
-
-            match rendered_image_res {
-                Ok(rendered_image) => Some(RenderedFramePart::new(
-                    rendered_image,
-                    frame_part.frame_metadata,
-                )),
-                Err(err) => {
-                    // let's keep trying our best upon error, just give a warning
-                    log::warn!("A frame was detected with an error ({err})");
-                    None
-                }
+            let rendered_frame = render_frame_part(frame_part, imgii_options);
+            if let Some(progress) = progress {
+                progress.frame_done();
             }
+            rendered_frame
         })
-        .collect())
+        .collect()
+}
+
+/// Renders the ascii text of a deconstructed frame, passing through a frame that already failed.
+///
+/// * `frame_part`: The ascii text of the frame, with its frame metadata.
+/// * `imgii_options`: the imgii options for rendering ascii.
+fn render_frame_part(
+    frame_part: Result<NonRenderedFramePart, FrameFailure>,
+    imgii_options: &ImgiiOptions,
+) -> Result<RenderedFramePart, FrameFailure> {
+    let frame_part = frame_part?;
+    if let Err(err) = imgii_options.check_cancelled() {
+        return Err(FrameFailure::new(frame_part.frame_metadata.index(), err));
+    }
+    let rendered_image_res = render_ascii_generic(imgii_options, frame_part.image_ascii);
+
+    match rendered_image_res {
+        Ok(rendered_image) => Ok(RenderedFramePart::new(
+            rendered_image,
+            frame_part.frame_metadata,
+        )),
+        Err(err) => Err(FrameFailure::new(frame_part.frame_metadata.index(), err)),
+    }
+}
+
+/// Splits the results of converting each frame into the frames that were converted and a report
+/// of the frames that failed. A warning is logged for each failed frame.
+///
+/// * `frame_results`: The result of converting each frame, in order.
+pub(crate) fn split_frame_failures<T>(
+    frame_results: Vec<Result<T, FrameFailure>>,
+) -> (Vec<T>, GifConversionReport) {
+    let mut report = GifConversionReport {
+        frames_total: frame_results.len(),
+        ..GifConversionReport::default()
+    };
+
+    let mut frames = Vec::with_capacity(frame_results.len());
+    for frame_result in frame_results {
+        match frame_result {
+            Ok(frame) => frames.push(frame),
+            Err(failure) => {
+                // let's keep trying our best upon error, just give a warning
+                log::warn!(
+                    "Frame {} was detected with an error ({})",
+                    failure.index(),
+                    failure.error()
+                );
+                report.failures.push(failure);
+            }
+        }
+    }
+    report.frames_rendered = frames.len();
+
+    (frames, report)
 }
 
 /// Reads a GIF and deconstructs it into an image and its frame metadata for use with converting to
@@ -219,6 +342,9 @@ pub(crate) fn read_deconstructed_gif(
         }
     };
 
+    // the logical screen size, which every frame should be normalized to
+    let (screen_width, screen_height) = decoder.dimensions();
+
     // decode all of the frames of the gif and then convert each frame into a DynamicImage
     let frames = match decoder.into_frames().collect_frames() {
         Ok(frames) => frames,
@@ -230,17 +356,695 @@ pub(crate) fn read_deconstructed_gif(
     };
     let ret = frames
         .into_iter()
-        .map(|frame| {
-            let left = frame.left();
-            let top = frame.top();
+        .enumerate()
+        .map(|(index, frame)| {
+            let mut left = frame.left();
+            let mut top = frame.top();
             let delay = frame.delay();
+            let mut buffer = frame.into_buffer();
+            if buffer.dimensions() != (screen_width, screen_height) {
+                // frames of differing sizes would render to differing sizes, so place this frame
+                // onto a transparent canvas the size of the logical screen
+                log::debug!(
+                    "Normalizing frame of size {:?} at ({left}, {top}) to logical screen size {:?}",
+                    buffer.dimensions(),
+                    (screen_width, screen_height)
+                );
+                let mut canvas = ImageBuffer::new(screen_width, screen_height);
+                imageops::replace(&mut canvas, &buffer, left.into(), top.into());
+                buffer = canvas;
+                left = 0;
+                top = 0;
+            }
             (
                 // we split this from the frame metadata because we will not want the original image once we have converted it to ASCII
-                frame.into_buffer().into(),
-                FrameMetadata::new(left, top, delay),
+                buffer.into(),
+                FrameMetadata::new(index, left, top, delay),
             )
         })
         .collect();
 
     Ok(ret)
 }
+
+/// Uniformly drops frames from a deconstructed GIF until the total delay of the remaining frames
+/// fits within the target duration. Kept frames retain their original delays. Always keeps at
+/// least the first frame.
+///
+/// * `frames`: The deconstructed GIF frames.
+/// * `target_duration_ms`: The maximum total delay of the kept frames, in milliseconds.
+pub(crate) fn decimate_to_target_duration(
+    frames: Vec<(DynamicImage, FrameMetadata)>,
+    target_duration_ms: u32,
+) -> Vec<(DynamicImage, FrameMetadata)> {
+    let target_duration = Duration::from_millis(target_duration_ms.into());
+    let frame_durations: Vec<Duration> = frames
+        .iter()
+        .map(|(_, frame_metadata)| Duration::from(frame_metadata.delay()))
+        .collect();
+
+    // find the smallest step between kept frames that fits the target duration
+    let step = (1..=frames.len().max(1))
+        .find(|step| frame_durations.iter().step_by(*step).sum::<Duration>() <= target_duration)
+        // even the first frame alone is too long, so only keep that one
+        .unwrap_or(frames.len().max(1));
+
+    if step > 1 {
+        log::debug!(
+            "Keeping every {step} frame(s) of {} to fit target duration of {target_duration_ms}ms",
+            frames.len()
+        );
+    }
+    frames.into_iter().step_by(step).collect()
+}
+
+/// Keeps at most `max_frames` evenly spaced frames of a deconstructed GIF, regardless of their
+/// delays. The delays of dropped frames are added to the kept frame before them, so the total
+/// duration stays the same.
+///
+/// * `frames`: The deconstructed GIF frames.
+/// * `max_frames`: The maximum number of frames to keep.
+pub(crate) fn limit_frame_count(
+    frames: Vec<(DynamicImage, FrameMetadata)>,
+    max_frames: usize,
+) -> Vec<(DynamicImage, FrameMetadata)> {
+    let num_frames = frames.len();
+    if max_frames == 0 || num_frames <= max_frames {
+        return frames;
+    }
+    log::debug!("Keeping {max_frames} of {num_frames} frame(s)");
+
+    // the index of the first source frame that each kept frame covers
+    let kept_frame_start = |kept_idx: usize| kept_idx * num_frames / max_frames;
+
+    let mut kept_frames: Vec<(DynamicImage, FrameMetadata)> = Vec::with_capacity(max_frames);
+    let mut kept_duration = Duration::ZERO;
+    for (i, (image, frame_metadata)) in frames.into_iter().enumerate() {
+        let frame_duration = Duration::from(frame_metadata.delay());
+        if kept_frame_start(kept_frames.len()) == i {
+            // finish off the last kept frame before starting the next one
+            if let Some((_, last_metadata)) = kept_frames.last_mut() {
+                last_metadata.delay = Delay::from_saturating_duration(kept_duration);
+            }
+            kept_frames.push((image, frame_metadata));
+            kept_duration = frame_duration;
+        } else {
+            kept_duration += frame_duration;
+        }
+    }
+    if let Some((_, last_metadata)) = kept_frames.last_mut() {
+        last_metadata.delay = Delay::from_saturating_duration(kept_duration);
+    }
+
+    kept_frames
+}
+
+/// Packs frames left to right into a single atlas image, and generates the CSS animating an
+/// element through them by stepping its `background-position`. Every frame gets a slot the size of
+/// the largest frame, with the frame at the top left.
+///
+/// * `frames`: Each frame along with how long it's shown for, in order.
+/// * `atlas_url`: The URL of the atlas image, used by the CSS.
+/// * `class_name`: The CSS class of the animated element. The keyframes are named after it.
+///
+/// # Returns
+/// * The atlas image and the CSS.
+pub(crate) fn build_css_sprite(
+    frames: &[(InternalImage, Duration)],
+    atlas_url: &str,
+    class_name: &str,
+) -> (InternalImage, String) {
+    let frame_width = frames
+        .iter()
+        .map(|(frame, _)| frame.width())
+        .max()
+        .unwrap_or(0);
+    let frame_height = frames
+        .iter()
+        .map(|(frame, _)| frame.height())
+        .max()
+        .unwrap_or(0);
+    let mut atlas: InternalImage =
+        ImageBuffer::new(frame_width * frames.len() as u32, frame_height);
+    for (i, (frame, _)) in frames.iter().enumerate() {
+        imageops::replace(&mut atlas, frame, i64::from(frame_width) * i as i64, 0);
+    }
+
+    let delays: Vec<Duration> = frames
+        .iter()
+        .map(|(_, delay)| {
+            if delay.is_zero() {
+                CSS_SPRITE_ZERO_DELAY
+            } else {
+                *delay
+            }
+        })
+        .collect();
+    let total_duration: Duration = delays.iter().sum();
+
+    let mut css = format!(
+        "/* {} frames of {frame_width}x{frame_height} pixels, packed left to right in {atlas_url} */\n",
+        frames.len()
+    );
+    css.push_str(&format!(
+        ".{class_name} {{\n  width: {frame_width}px;\n  height: {frame_height}px;\n  background-image: url(\"{atlas_url}\");\n  background-repeat: no-repeat;\n  animation: {class_name}-frames {}ms steps(1, end) infinite;\n}}\n\n",
+        total_duration.as_millis()
+    ));
+    css.push_str(&format!("@keyframes {class_name}-frames {{\n"));
+    // each keyframe holds its frame until the next keyframe starts, because of steps(1, end)
+    let mut elapsed = Duration::ZERO;
+    for (i, delay) in delays.iter().enumerate() {
+        let percent = elapsed.as_secs_f64() / total_duration.as_secs_f64() * 100.0;
+        css.push_str(&format!(
+            "  {percent:.4}% {{ background-position: {}px 0; }}\n",
+            -(i64::from(frame_width) * i as i64)
+        ));
+        elapsed += *delay;
+    }
+    if let Some(last) = delays.len().checked_sub(1) {
+        // the last frame is held until the animation loops
+        css.push_str(&format!(
+            "  100% {{ background-position: {}px 0; }}\n",
+            -(i64::from(frame_width) * last as i64)
+        ));
+    }
+    css.push_str("}\n");
+
+    (atlas, css)
+}
+
+/// Lays out frames in a grid, drawing the index of each frame beneath it with the font of the
+/// options. Every frame gets a tile the size of the largest frame, with the frame at the top left.
+///
+/// * `frames`: The index of each frame, followed by the frame, in order.
+/// * `columns`: The number of frames in each row of the grid.
+/// * `imgii_options`: The imgii options, holding the font and background.
+pub(crate) fn build_contact_sheet(
+    frames: &[(usize, InternalImage)],
+    columns: u32,
+    imgii_options: &ImgiiOptions,
+) -> Result<InternalImage, ImgiiError> {
+    let font = FontRef::try_from_slice(imgii_options.font().as_slice()).map_err(|_| {
+        FontError::FontLoad {
+            font_name: String::from(imgii_options.font_name()),
+        }
+    })?;
+
+    let tile_width = frames
+        .iter()
+        .map(|(_, frame)| frame.width())
+        .max()
+        .unwrap_or(0);
+    let frame_height = frames
+        .iter()
+        .map(|(_, frame)| frame.height())
+        .max()
+        .unwrap_or(0);
+    // leave a little room around each label
+    let label_scale = PxScale::from(imgii_options.font_size() as f32);
+    let label_height = imgii_options.font_size() + CONTACT_SHEET_LABEL_PADDING * 2;
+    let tile_height = frame_height + label_height;
+    let rows = (frames.len() as u32).div_ceil(columns);
+
+    let (background, label_color) = if imgii_options.background() {
+        let background = imgii_options.background_color();
+        // pick whichever label color stands out against the background
+        let Luma([brightness]) = background.to_luma();
+        let label_color = if brightness < 128 {
+            Rgba([u8::MAX; 4])
+        } else {
+            Rgba([0, 0, 0, u8::MAX])
+        };
+        (background, label_color)
+    } else {
+        (Rgba([0, 0, 0, 0]), Rgba([u8::MAX; 4]))
+    };
+    let mut contact_sheet =
+        ImageBuffer::from_pixel(tile_width * columns, tile_height * rows, background);
+
+    for (i, (index, frame)) in frames.iter().enumerate() {
+        let tile_x = (i as u32 % columns) * tile_width;
+        let tile_y = (i as u32 / columns) * tile_height;
+        imageops::replace(&mut contact_sheet, frame, tile_x.into(), tile_y.into());
+
+        // center the label under the tile
+        let label = index.to_string();
+        let (label_width, _) = text_size(label_scale, &font, &label);
+        draw_text_mut(
+            &mut contact_sheet,
+            label_color,
+            (tile_x + tile_width.saturating_sub(label_width) / 2) as i32,
+            (tile_y + frame_height + CONTACT_SHEET_LABEL_PADDING) as i32,
+            label_scale,
+            &font,
+            &label,
+        );
+    }
+
+    Ok(contact_sheet)
+}
+
+/// Reads how many times a GIF repeats, without decoding any frames.
+///
+/// NOTE: only a loop count that comes before the first frame is found, which is where encoders put
+/// it. GIFs without one are played once.
+///
+/// * `input_file_name`: The input file name.
+pub(crate) fn read_gif_repeat(input_file_name: &str) -> Result<gif::Repeat, ImgiiError> {
+    let to_imgii_err = |err: gif::DecodingError| -> ImgiiError { anyhow::Error::new(err).into() };
+
+    let file_in = BufReader::new(File::open(input_file_name)?);
+    let mut decoder = gif::DecodeOptions::new()
+        .read_info(file_in)
+        .map_err(to_imgii_err)?;
+    // the loop count is an extension before the first frame, so read up to the first frame
+    decoder.next_frame_info().map_err(to_imgii_err)?;
+    Ok(decoder.repeat())
+}
+
+/// Encodes frames as a GIF that repeats infinitely and saves it. Frames are encoded as they are
+/// yielded, so they can still be rendering while earlier frames are encoded.
+///
+/// * `frames`: The frames of the GIF, in order.
+/// * `output_file_name`: The output file name.
+/// * `imgii_options`: The imgii options, holding how each frame is disposed of and whether
+///   unchanged files are written.
+pub(crate) fn save_gif(
+    frames: impl IntoIterator<Item = Frame>,
+    output_file_name: &str,
+    imgii_options: &ImgiiOptions,
+) -> Result<(), ImgiiError> {
+    save_gif_with_repeat(
+        frames,
+        output_file_name,
+        imgii_options,
+        gif::Repeat::Infinite,
+        None,
+    )
+}
+
+/// Encodes frames as a GIF and saves it, like [`save_gif`], but repeats the GIF the given number
+/// of times.
+///
+/// * `frames`: The frames of the GIF, in order.
+/// * `output_file_name`: The output file name.
+/// * `imgii_options`: The imgii options, holding how each frame is disposed of and whether
+///   unchanged files are written.
+/// * `repeat`: How many times the GIF repeats.
+/// * `progress`: The progress tracker of the encode stage, if any.
+pub(crate) fn save_gif_with_repeat(
+    frames: impl IntoIterator<Item = Frame>,
+    output_file_name: &str,
+    imgii_options: &ImgiiOptions,
+    repeat: gif::Repeat,
+    progress: Option<&ProgressTracker>,
+) -> Result<(), ImgiiError> {
+    if imgii_options.skip_if_unchanged() {
+        // encode in memory first, so we can compare against the existing file
+        let mut output_bytes = Vec::new();
+        write_gif(frames, &mut output_bytes, imgii_options, repeat, progress)?;
+        return write_if_changed(output_file_name, &output_bytes, imgii_options);
+    }
+
+    write_output_file(output_file_name, imgii_options, |file_writer| {
+        write_gif(frames, file_writer, imgii_options, repeat, progress)
+    })
+}
+
+/// Encodes frames as a GIF to a writer.
+///
+/// * `frames`: The frames of the GIF, in order.
+/// * `writer`: The writer to encode the GIF to.
+/// * `imgii_options`: The imgii options, holding how each frame is disposed of before the next
+///   frame is drawn and the transparent color key.
+/// * `repeat`: How many times the GIF repeats.
+/// * `progress`: The progress tracker of the encode stage, if any.
+fn write_gif<W: Write>(
+    frames: impl IntoIterator<Item = Frame>,
+    writer: W,
+    imgii_options: &ImgiiOptions,
+    repeat: gif::Repeat,
+    progress: Option<&ProgressTracker>,
+) -> Result<(), ImgiiError> {
+    let to_imgii_err = |err: gif::EncodingError| -> ImgiiError { anyhow::Error::new(err).into() };
+
+    // the GIF is the size of the first frame, like the image crate's encoder
+    let mut frames = frames.into_iter().peekable();
+    let Some(first_frame) = frames.peek() else {
+        // no GIF to build
+        return Err(ImgiiError::InvalidArgument);
+    };
+    let (width, height) =
+        gif_dimensions(first_frame.buffer().width(), first_frame.buffer().height())?;
+
+    // NOTE: we use the gif encoder directly since the image crate's encoder always disposes of
+    // frames to the background
+    let mut gif_encoder = gif::Encoder::new(writer, width, height, &[]).map_err(to_imgii_err)?;
+
+    gif_encoder.set_repeat(repeat).map_err(to_imgii_err)?;
+
+    let dispose = match imgii_options.gif_disposal() {
+        GifDisposal::Keep => gif::DisposalMethod::Keep,
+        GifDisposal::Background => gif::DisposalMethod::Background,
+        GifDisposal::Previous => gif::DisposalMethod::Previous,
+    };
+
+    // FUTURE: the longest part of the GIF creation process is encoding...is there any way to speed
+    // it up?
+
+    let transparent_key = imgii_options
+        .gif_transparent_key()
+        .map(|Rgba([red, green, blue, _])| [red, green, blue]);
+
+    // encode the frames
+    for (i, frame) in frames.enumerate() {
+        let (left, top) = (frame.left(), frame.top());
+        let (delay_ms, _) = quantize_to_centiseconds(frame.delay()).numer_denom_ms();
+        let mut buffer = frame.into_buffer();
+        let (frame_width, frame_height) = gif_dimensions(buffer.width(), buffer.height())?;
+
+        if let Some(key) = transparent_key {
+            let shifted = reserve_transparent_key(&mut buffer, key);
+            if shifted > 0 {
+                log::warn!(
+                    "Shifted {shifted} pixel(s) of frame {i} away from the transparent key color {key:?}"
+                );
+            }
+        }
+
+        // quantize with the best quality, like the image crate's encoder
+        let mut gif_frame = gif::Frame::from_rgba_speed(frame_width, frame_height, &mut buffer, 1);
+        if let Some(key) = transparent_key {
+            apply_transparent_key(&mut gif_frame, &buffer, key);
+        }
+        // GIF delays are in centiseconds
+        gif_frame.delay = u16::try_from(delay_ms / 10).unwrap_or(u16::MAX);
+        gif_frame.left = u16::try_from(left).unwrap_or(u16::MAX);
+        gif_frame.top = u16::try_from(top).unwrap_or(u16::MAX);
+        gif_frame.dispose = dispose;
+
+        gif_encoder.write_frame(&gif_frame).map_err(to_imgii_err)?;
+        if let Some(progress) = progress {
+            progress.frame_done();
+        }
+    }
+
+    Ok(())
+}
+
+/// Prepares the pixels of a frame for a transparent color key, before they're quantized. Every
+/// transparent pixel is set to the key, so they all share one palette entry, and every visible
+/// pixel of the key color is shifted slightly so it isn't mistaken for transparency.
+///
+/// * `pixels`: The RGBA pixels of the frame.
+/// * `key`: The RGB of the transparent color key.
+///
+/// # Returns
+/// * The number of visible pixels that were shifted away from the key.
+fn reserve_transparent_key(pixels: &mut [u8], key: [u8; 3]) -> usize {
+    let mut shifted = 0;
+    for pixel in pixels.chunks_exact_mut(4) {
+        if pixel[3] == 0 {
+            pixel[..3].copy_from_slice(&key);
+        } else if pixel[..3] == key {
+            pixel[1] = shift_from_key(pixel[1]);
+            shifted += 1;
+        }
+    }
+    shifted
+}
+
+/// Makes the transparent palette entry of a quantized frame the transparent color key, making
+/// sure no visible pixel uses it and no other palette entry is the key color.
+///
+/// * `gif_frame`: The quantized frame.
+/// * `pixels`: The RGBA pixels the frame was quantized from, where only transparent pixels have an
+///   alpha of zero.
+/// * `key`: The RGB of the transparent color key.
+fn apply_transparent_key(gif_frame: &mut gif::Frame, pixels: &[u8], key: [u8; 3]) {
+    let Some(palette) = gif_frame.palette.as_mut() else {
+        return;
+    };
+    // quantizing can give colors close to the key, so shift them away like the pixels
+    for color in palette.chunks_exact_mut(3) {
+        if color == key {
+            color[1] = shift_from_key(color[1]);
+        }
+    }
+    let Some(transparent) = gif_frame.transparent else {
+        // nothing is transparent in this frame
+        return;
+    };
+    let transparent_entry = usize::from(transparent) * 3;
+    palette[transparent_entry..transparent_entry + 3].copy_from_slice(&key);
+
+    // when quantizing merges visible pixels into the transparent entry, move them to the closest
+    // visible entry
+    let palette = &*palette;
+    let buffer = gif_frame.buffer.to_mut();
+    for (index, pixel) in buffer.iter_mut().zip(pixels.chunks_exact(4)) {
+        if *index == transparent && pixel[3] != 0 {
+            *index = closest_palette_index(palette, &pixel[..3], transparent);
+        }
+    }
+}
+
+/// Finds the palette entry closest to a color, skipping one entry.
+///
+/// * `palette`: The RGB palette.
+/// * `color`: The RGB color to find.
+/// * `skip`: The index of the entry to skip.
+fn closest_palette_index(palette: &[u8], color: &[u8], skip: u8) -> u8 {
+    palette
+        .chunks_exact(3)
+        .enumerate()
+        .filter(|(index, _)| *index != usize::from(skip))
+        .min_by_key(|(_, entry)| {
+            entry
+                .iter()
+                .zip(color)
+                .map(|(a, b)| u32::from(a.abs_diff(*b)).pow(2))
+                .sum::<u32>()
+        })
+        // a palette with only the transparent entry has nothing closer
+        .map_or(skip, |(index, _)| index as u8)
+}
+
+/// Shifts a color channel by one step toward the middle, so a color that matches the transparent
+/// color key no longer does.
+///
+/// * `channel`: The channel to shift.
+fn shift_from_key(channel: u8) -> u8 {
+    if channel < 128 {
+        channel + 1
+    } else {
+        channel - 1
+    }
+}
+
+/// Rounds a delay to the nearest centisecond, the precision of GIF frame delays. Delays from the
+/// `image` crate are ratios that may not divide evenly, which would otherwise drift when encoded.
+///
+/// * `delay`: The delay to round.
+pub(crate) fn quantize_to_centiseconds(delay: Delay) -> Delay {
+    let (numer, denom) = delay.numer_denom_ms();
+    let (numer, denom) = (u64::from(numer), u64::from(denom));
+    let centiseconds = (numer + denom * 5) / (denom * 10);
+    Delay::from_numer_denom_ms(u32::try_from(centiseconds * 10).unwrap_or(u32::MAX), 1)
+}
+
+/// Converts the dimensions of a frame to GIF dimensions.
+///
+/// * `width`: The width of the frame.
+/// * `height`: The height of the frame.
+///
+/// # Returns
+/// * `Err` if the dimensions are too large for a GIF, otherwise the dimensions.
+fn gif_dimensions(width: u32, height: u32) -> Result<(u16, u16), ImgiiError> {
+    match (u16::try_from(width), u16::try_from(height)) {
+        (Ok(width), Ok(height)) => Ok((width, height)),
+        _ => Err(ImageError::Render {
+            reason: format!("{width}x{height} is too large for a GIF"),
+        }
+        .into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{TestFrame, test_dir, test_options_builder, write_test_gif};
+
+    #[test]
+    fn frames_are_normalized_to_the_logical_screen() {
+        let dir = test_dir("frames_are_normalized_to_the_logical_screen");
+        let input = dir.join("variable_size.gif");
+        write_test_gif(
+            &input,
+            (8, 6),
+            &[
+                TestFrame {
+                    left: 0,
+                    top: 0,
+                    width: 8,
+                    height: 6,
+                    color: [255, 0, 0, 255],
+                    delay: 10,
+                },
+                TestFrame {
+                    left: 4,
+                    top: 3,
+                    width: 3,
+                    height: 2,
+                    color: [0, 255, 0, 255],
+                    delay: 10,
+                },
+            ],
+        );
+
+        let frames = read_deconstructed_gif(input.to_str().unwrap()).unwrap();
+        assert_eq!(frames.len(), 2);
+        for (image, frame_metadata) in &frames {
+            assert_eq!((image.width(), image.height()), (8, 6));
+            assert_eq!((frame_metadata.left(), frame_metadata.top()), (0, 0));
+        }
+        // the small frame's content is at its (left, top) on the screen
+        let second = frames[1].0.to_rgba8();
+        assert_eq!(*second.get_pixel(4, 3), Rgba([0, 255, 0, 255]));
+        assert_eq!(*second.get_pixel(6, 4), Rgba([0, 255, 0, 255]));
+        assert_ne!(*second.get_pixel(3, 3), Rgba([0, 255, 0, 255]));
+        assert_ne!(*second.get_pixel(7, 5), Rgba([0, 255, 0, 255]));
+
+        // and every rendered frame comes out the same size, so it encodes cleanly
+        let imgii_options = test_options_builder().width(4).build().unwrap();
+        let output = dir.join("variable_size_ascii.gif");
+        crate::convert_to_ascii_gif(
+            input.to_str().unwrap(),
+            output.to_str().unwrap(),
+            &imgii_options,
+        )
+        .unwrap();
+        let decoder = GifDecoder::new(BufReader::new(File::open(&output).unwrap())).unwrap();
+        let output_frames = decoder.into_frames().collect_frames().unwrap();
+        assert_eq!(output_frames.len(), 2);
+        assert_eq!(
+            output_frames[0].buffer().dimensions(),
+            output_frames[1].buffer().dimensions()
+        );
+    }
+
+    /// Encodes two transparent frames, the first with its left half red and the second with its
+    /// right half green, then decodes the second frame as it appears on screen.
+    ///
+    /// * `gif_disposal`: How each frame is disposed of.
+    fn second_frame_on_screen(gif_disposal: GifDisposal) -> image::RgbaImage {
+        let imgii_options = test_options_builder()
+            .gif_disposal(gif_disposal)
+            .build()
+            .unwrap();
+        let half_frame = |color: [u8; 4], left_half: bool| {
+            Frame::new(image::RgbaImage::from_fn(8, 4, |x, _| {
+                if (x < 4) == left_half {
+                    Rgba(color)
+                } else {
+                    Rgba([0, 0, 0, 0])
+                }
+            }))
+        };
+        let frames = [
+            half_frame([255, 0, 0, 255], true),
+            half_frame([0, 255, 0, 255], false),
+        ];
+
+        let mut output_bytes = Vec::new();
+        write_gif(
+            frames,
+            &mut output_bytes,
+            &imgii_options,
+            gif::Repeat::Infinite,
+            None,
+        )
+        .unwrap();
+        let decoder = GifDecoder::new(std::io::Cursor::new(output_bytes)).unwrap();
+        let mut output_frames = decoder.into_frames().collect_frames().unwrap();
+        assert_eq!(output_frames.len(), 2);
+        output_frames.remove(1).into_buffer()
+    }
+
+    #[test]
+    fn transparent_frames_do_not_ghost_by_default() {
+        let second = second_frame_on_screen(GifDisposal::default());
+        // the first frame was cleared, so nothing of it shows through the transparent half
+        assert_eq!(second.get_pixel(1, 1).0[3], 0);
+        assert_eq!(*second.get_pixel(6, 1), Rgba([0, 255, 0, 255]));
+
+        // keeping the first frame is what ghosts
+        let second = second_frame_on_screen(GifDisposal::Keep);
+        assert_eq!(*second.get_pixel(1, 1), Rgba([255, 0, 0, 255]));
+    }
+
+    #[test]
+    fn delays_round_to_the_nearest_centisecond() {
+        let quantized_ms = |numer, denom| {
+            quantize_to_centiseconds(Delay::from_numer_denom_ms(numer, denom)).numer_denom_ms()
+        };
+        assert_eq!(quantized_ms(14, 1), (10, 1));
+        assert_eq!(quantized_ms(15, 1), (20, 1));
+        assert_eq!(quantized_ms(1, 3), (0, 1));
+        assert_eq!(quantized_ms(1000, 3), (330, 1));
+        assert_eq!(quantized_ms(u32::MAX, 1), (u32::MAX, 1));
+    }
+
+    #[test]
+    fn encoded_delays_are_centisecond_accurate() {
+        let imgii_options = test_options_builder().build().unwrap();
+        let frame = |numer| {
+            Frame::from_parts(
+                image::RgbaImage::from_pixel(2, 2, Rgba([255, 0, 0, 255])),
+                0,
+                0,
+                Delay::from_numer_denom_ms(numer, 3),
+            )
+        };
+
+        let mut output_bytes = Vec::new();
+        write_gif(
+            [frame(1000), frame(44)],
+            &mut output_bytes,
+            &imgii_options,
+            gif::Repeat::Infinite,
+            None,
+        )
+        .unwrap();
+        let decoder = GifDecoder::new(std::io::Cursor::new(output_bytes)).unwrap();
+        let delays = decoder
+            .into_frames()
+            .map(|frame| frame.unwrap().delay().numer_denom_ms())
+            .collect::<Vec<_>>();
+        // 333.3ms and 14.7ms
+        assert_eq!(delays, [(330, 1), (10, 1)]);
+    }
+
+    #[test]
+    fn limiting_frames_keeps_evenly_spaced_frames() {
+        let frames = (0..100)
+            .map(|index| {
+                (
+                    DynamicImage::new_rgba8(1, 1),
+                    FrameMetadata::new(index, 0, 0, Delay::from_numer_denom_ms(20, 1)),
+                )
+            })
+            .collect();
+
+        let kept = limit_frame_count(frames, 10);
+        let kept_indices = kept
+            .iter()
+            .map(|(_, frame_metadata)| frame_metadata.index())
+            .collect::<Vec<_>>();
+        assert_eq!(kept_indices, [0, 10, 20, 30, 40, 50, 60, 70, 80, 90]);
+        // each kept frame lasts as long as the frames it stands in for
+        for (_, frame_metadata) in &kept {
+            assert_eq!(frame_metadata.delay().numer_denom_ms(), (200, 1));
+        }
+    }
+}
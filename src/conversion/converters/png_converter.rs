@@ -1,14 +1,34 @@
 //! Handles implementation for rendering PNG.
 
-use super::generic_converter::render_ascii_generic;
+use super::generic_converter::{AsciiRowRenderer, render_ascii_generic};
 use crate::{
-    conversion::converters::generic_converter::Imgii2dImage,
-    error::ImgiiError,
-    options::{ImgiiOptions, RasciiOptions},
+    conversion::{
+        converters::generic_converter::Imgii2dImage,
+        image_data::ImageData,
+        image_writer::{canvas_background, fill_background, write_if_changed, write_output_file},
+        render_char_to_png::calculate_char_dimensions,
+    },
+    encoder::EncodeFn,
+    error::{ImageError, ImgiiError},
+    image_types::ImageInfo,
+    options::{BitDepth, CellColorMode, ImgiiOptions, PngAlpha},
 };
 
-use image::open;
-use rascii_art_img::render_image_to;
+use std::{
+    borrow::Cow,
+    io::{Cursor, Seek, Write},
+    ops::Range,
+    path::Path,
+};
+
+use image::{
+    ColorType, DynamicImage, ImageBuffer, ImageDecoder, ImageEncoder, ImageFormat, ImageReader,
+    Luma, RgbImage, Rgba,
+    codecs::png::{FilterType as PngFilterType, PngEncoder},
+    open,
+};
+use imageproc::gradients::sobel_gradients;
+use regex::Regex;
 
 /// Reads and converts an image to ASCII and renders it into image.
 ///
@@ -23,7 +43,7 @@ pub(crate) fn parse_ascii_to_2d_png_vec(
     input_file_name: &str,
     imgii_options: &ImgiiOptions,
 ) -> Result<Imgii2dImage, ImgiiError> {
-    let ascii_text = read_png_as_ascii(input_file_name, imgii_options.rascii_options())?;
+    let ascii_text = read_png_as_ascii(input_file_name, imgii_options)?;
     render_ascii_generic(imgii_options, ascii_text)
 }
 
@@ -31,20 +51,947 @@ pub(crate) fn parse_ascii_to_2d_png_vec(
 ///
 /// # Params
 /// * `input_file_name`: The input file name of the image to convert.
-/// * `rascii_options`: The RASCII image options.
+/// * `imgii_options`: The imgii options, holding the RASCII options for converting to ASCII.
 ///
 /// # Returns
 /// * `String` containing the colored image data as ASCII, colored using terminal escape sequences.
 pub(crate) fn read_png_as_ascii(
     input_file_name: &str,
-    rascii_options: &RasciiOptions,
+    imgii_options: &ImgiiOptions,
 ) -> Result<String, ImgiiError> {
-    // render the ascii text with RASCII
-    let mut ascii_text = String::new();
-    let loaded_img =
-        open(input_file_name).map_err(|err| -> ImgiiError { anyhow::Error::new(err).into() })?;
-    render_image_to(&loaded_img, &mut ascii_text, rascii_options)
+    let loaded_img = open_image(input_file_name)?;
+    image_to_ascii(&loaded_img, imgii_options)
+}
+
+/// Reads the format and dimensions of an image from its header, without decoding the whole image.
+///
+/// # Params
+/// * `input_file_name`: The input file name of the image to read.
+pub(crate) fn read_image_info(input_file_name: &str) -> Result<ImageInfo, ImgiiError> {
+    let reader = ImageReader::open(input_file_name)?.with_guessed_format()?;
+    let Some(format) = reader.format() else {
+        return Err(ImageError::UnsupportedInputType {
+            file_name: String::from(input_file_name),
+        }
+        .into());
+    };
+    let (width, height) = reader
+        .into_dimensions()
+        .map_err(|err| -> ImgiiError { anyhow::Error::new(err).into() })?;
+
+    Ok(ImageInfo {
+        format,
+        width,
+        height,
+    })
+}
+
+/// The file extensions of inputs that are read as colored ASCII text rather than decoded as images.
+const ANSI_INPUT_EXTENSIONS: &[&str] = &["ans", "txt"];
+
+/// Reads an input file as colored ASCII text, if it is text rather than an image. Files with a text
+/// extension (such as ".ans") are always read as text. Any other file is read as text only if its
+/// image format can't be recognized and it's UTF-8 text holding escape sequences.
+///
+/// # Params
+/// * `input_file_name`: The input file name.
+///
+/// # Returns
+/// * The colored ASCII text of the input file, or `None` if it should be decoded as an image.
+pub(crate) fn read_ansi_input(input_file_name: &str) -> Result<Option<String>, ImgiiError> {
+    let has_text_extension = Path::new(input_file_name)
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| {
+            ANSI_INPUT_EXTENSIONS
+                .iter()
+                .any(|text_extension| extension.eq_ignore_ascii_case(text_extension))
+        });
+    if has_text_extension {
+        return Ok(Some(std::fs::read_to_string(input_file_name)?));
+    }
+
+    if ImageReader::open(input_file_name)?
+        .with_guessed_format()?
+        .format()
+        .is_some()
+    {
+        // it's an image, even if it might fail to decode
+        return Ok(None);
+    }
+    Ok(std::fs::read_to_string(input_file_name)
+        .ok()
+        .filter(|text| text.contains("\u{1b}[")))
+}
+
+/// Opens and decodes an image.
+///
+/// # Params
+/// * `input_file_name`: The input file name of the image to open.
+pub(crate) fn open_image(input_file_name: &str) -> Result<DynamicImage, ImgiiError> {
+    open(input_file_name).map_err(|err| -> ImgiiError { anyhow::Error::new(err).into() })
+}
+
+/// Opens and decodes an image, also reading its embedded ICC profile.
+///
+/// # Params
+/// * `input_file_name`: The input file name of the image to open.
+///
+/// # Returns
+/// * The decoded image, followed by its ICC profile if it has one.
+pub(crate) fn open_image_with_icc_profile(
+    input_file_name: &str,
+) -> Result<(DynamicImage, Option<Vec<u8>>), ImgiiError> {
+    let to_imgii_err = |err: image::ImageError| -> ImgiiError { anyhow::Error::new(err).into() };
+
+    let mut decoder = ImageReader::open(input_file_name)?
+        .with_guessed_format()?
+        .into_decoder()
+        .map_err(to_imgii_err)?;
+    let icc_profile = decoder.icc_profile().map_err(to_imgii_err)?;
+    let image = DynamicImage::from_decoder(decoder).map_err(to_imgii_err)?;
+    Ok((image, icc_profile))
+}
+
+/// Saves an image. If there is an ICC profile, the image is always encoded as a PNG with the
+/// profile embedded, otherwise the format is chosen by the file extension.
+///
+/// # Params
+/// * `image`: The image to save.
+/// * `output_file_name`: The output file name.
+/// * `icc_profile`: The ICC profile to embed, if any.
+/// * `source_file_name`: The file name of the source image, embedded as metadata if enabled.
+/// * `imgii_options`: The imgii options, deciding whether unchanged files are written and whether
+///   metadata is embedded.
+pub(crate) fn save_png(
+    image: &DynamicImage,
+    output_file_name: &str,
+    icc_profile: Option<Vec<u8>>,
+    source_file_name: Option<&str>,
+    imgii_options: &ImgiiOptions,
+) -> Result<(), ImgiiError> {
+    let metadata = if imgii_options.embed_metadata() {
+        png_metadata(source_file_name, imgii_options)
+    } else {
+        Vec::new()
+    };
+
+    if imgii_options.skip_if_unchanged() {
+        // encode in memory first, so we can compare against the existing file
+        let mut output_bytes = Vec::new();
+        write_image(
+            image,
+            &mut Cursor::new(&mut output_bytes),
+            output_file_name,
+            icc_profile,
+            &metadata,
+            imgii_options,
+        )?;
+        return write_if_changed(output_file_name, &output_bytes, imgii_options);
+    }
+
+    write_output_file(output_file_name, imgii_options, |file_writer| {
+        write_image(
+            image,
+            file_writer,
+            output_file_name,
+            icc_profile,
+            &metadata,
+            imgii_options,
+        )
+    })
+}
+
+/// Gets the text metadata describing how an image was rendered, as (keyword, text) pairs.
+///
+/// * `source_file_name`: The file name of the source image, if there is one.
+/// * `imgii_options`: The imgii options the image was rendered with.
+fn png_metadata(
+    source_file_name: Option<&str>,
+    imgii_options: &ImgiiOptions,
+) -> Vec<(String, String)> {
+    let mut metadata = vec![(String::from("Software"), String::from("imgii"))];
+    if let Some(source_file_name) = source_file_name {
+        metadata.push((String::from("Source"), source_file_name.to_string()));
+    }
+    metadata.push((
+        String::from("Charset"),
+        imgii_options.rascii_options().charset.concat(),
+    ));
+    metadata.push((String::from("Font"), imgii_options.font_name().to_string()));
+    metadata.push((
+        String::from("Font Size"),
+        imgii_options.font_size().to_string(),
+    ));
+    metadata
+}
+
+/// Saves an image with a custom encoder.
+///
+/// # Params
+/// * `image`: The image to save.
+/// * `output_file_name`: The output file name.
+/// * `encoder`: The custom encoder to encode the image with.
+/// * `imgii_options`: The imgii options, deciding whether unchanged files are written.
+pub(crate) fn save_with_encoder(
+    image: &ImageData,
+    output_file_name: &str,
+    encoder: &EncodeFn,
+    imgii_options: &ImgiiOptions,
+) -> Result<(), ImgiiError> {
+    if imgii_options.skip_if_unchanged() {
+        // encode in memory first, so we can compare against the existing file
+        let mut output_bytes = Vec::new();
+        encoder(image, &mut output_bytes)?;
+        return write_if_changed(output_file_name, &output_bytes, imgii_options);
+    }
+
+    write_output_file(output_file_name, imgii_options, |file_writer| {
+        encoder(image, file_writer)
+    })
+}
+
+/// Encodes an image to a writer. If there is an ICC profile, the image is always encoded as a PNG
+/// with the profile embedded, otherwise the format is chosen by the file extension.
+///
+/// # Params
+/// * `image`: The image to encode.
+/// * `writer`: The writer to encode the image to.
+/// * `output_file_name`: The output file name, used for choosing the format.
+/// * `icc_profile`: The ICC profile to embed, if any.
+/// * `metadata`: The text metadata to embed as (keyword, text) pairs. Only embedded in PNGs.
+/// * `imgii_options`: The imgii options, deciding how PNGs are compressed and whether they're
+///   indexed.
+fn write_image<W: Write + Seek>(
+    image: &DynamicImage,
+    writer: &mut W,
+    output_file_name: &str,
+    icc_profile: Option<Vec<u8>>,
+    metadata: &[(String, String)],
+    imgii_options: &ImgiiOptions,
+) -> Result<(), ImgiiError> {
+    let to_imgii_err = |err: image::ImageError| -> ImgiiError { anyhow::Error::new(err).into() };
+
+    let is_png = icc_profile.is_some()
+        || ImageFormat::from_path(output_file_name).is_ok_and(|format| format == ImageFormat::Png);
+    if !is_png {
+        // NOTE: the JPEG encoder of the image crate doesn't support chroma subsampling, so JPEGs
+        // are always 4:4:4, which is what we want for crisp glyph edges anyway
+        let format = ImageFormat::from_path(output_file_name).map_err(to_imgii_err)?;
+        return image.write_to(writer, format).map_err(to_imgii_err);
+    }
+
+    // the image crate can't write text chunks or indexed color, so those PNGs are written with png
+    // directly
+    if !metadata.is_empty() || imgii_options.png_palette() {
+        return write_png_with_info(image, writer, icc_profile, metadata, imgii_options);
+    }
+
+    let mut encoder = PngEncoder::new_with_quality(
+        writer,
+        imgii_options.png_compression().into(),
+        PngFilterType::Adaptive,
+    );
+    if let Some(icc_profile) = icc_profile
+        && let Err(err) = encoder.set_icc_profile(icc_profile)
+    {
+        // not fatal, the colors will just be interpreted as sRGB
+        log::warn!("Could not embed ICC profile in {output_file_name} ({err})");
+    }
+    image.write_with_encoder(encoder).map_err(to_imgii_err)
+}
+
+/// Encodes an image as a PNG with the png crate, for PNGs the image crate can't write. Text
+/// metadata that can be encoded as Latin-1 is written in `tEXt` chunks, anything else in UTF-8
+/// `iTXt` chunks. The ICC profile is embedded if there is one.
+///
+/// # Params
+/// * `image`: The image to encode.
+/// * `writer`: The writer to encode the image to.
+/// * `icc_profile`: The ICC profile to embed, if any.
+/// * `metadata`: The text metadata to embed as (keyword, text) pairs.
+/// * `imgii_options`: The imgii options, deciding how the PNG is compressed and whether it's
+///   indexed.
+fn write_png_with_info<W: Write>(
+    image: &DynamicImage,
+    writer: &mut W,
+    icc_profile: Option<Vec<u8>>,
+    metadata: &[(String, String)],
+    imgii_options: &ImgiiOptions,
+) -> Result<(), ImgiiError> {
+    let to_imgii_err = |err: png::EncodingError| -> ImgiiError { anyhow::Error::new(err).into() };
+
+    let indexed = if imgii_options.png_palette() {
+        index_colors(image)
+    } else {
+        None
+    };
+    let converted_image;
+    let (image, color_type, bit_depth) = match image.color() {
+        ColorType::L8 => (image, png::ColorType::Grayscale, png::BitDepth::Eight),
+        ColorType::La8 => (image, png::ColorType::GrayscaleAlpha, png::BitDepth::Eight),
+        ColorType::Rgb8 => (image, png::ColorType::Rgb, png::BitDepth::Eight),
+        ColorType::Rgba8 => (image, png::ColorType::Rgba, png::BitDepth::Eight),
+        ColorType::L16 => (image, png::ColorType::Grayscale, png::BitDepth::Sixteen),
+        ColorType::La16 => (
+            image,
+            png::ColorType::GrayscaleAlpha,
+            png::BitDepth::Sixteen,
+        ),
+        ColorType::Rgb16 => (image, png::ColorType::Rgb, png::BitDepth::Sixteen),
+        ColorType::Rgba16 => (image, png::ColorType::Rgba, png::BitDepth::Sixteen),
+        // PNGs can't hold floats
+        _ => {
+            converted_image = DynamicImage::ImageRgba16(image.to_rgba16());
+            (
+                &converted_image,
+                png::ColorType::Rgba,
+                png::BitDepth::Sixteen,
+            )
+        }
+    };
+
+    let mut info = png::Info::with_size(image.width(), image.height());
+    info.color_type = color_type;
+    info.bit_depth = bit_depth;
+    if let Some(indexed) = &indexed {
+        info.color_type = png::ColorType::Indexed;
+        info.bit_depth = indexed.bit_depth;
+        info.palette = Some(Cow::Borrowed(&indexed.palette));
+        info.trns = indexed.trns.as_deref().map(Cow::Borrowed);
+    }
+    info.icc_profile = icc_profile.map(Cow::Owned);
+    let mut encoder = png::Encoder::with_info(writer, info).map_err(to_imgii_err)?;
+    encoder.set_compression(imgii_options.png_compression().into());
+    add_text_chunks(&mut encoder, metadata)?;
+
+    let mut png_writer = encoder.write_header().map_err(to_imgii_err)?;
+    if let Some(indexed) = &indexed {
+        png_writer
+            .write_image_data(&indexed.data)
+            .map_err(to_imgii_err)?;
+        return png_writer.finish().map_err(to_imgii_err);
+    }
+    match bit_depth {
+        // PNGs are big endian, but the image holds native endian samples
+        png::BitDepth::Sixteen => {
+            let samples = image
+                .as_bytes()
+                .chunks_exact(2)
+                .flat_map(|sample| u16::from_ne_bytes([sample[0], sample[1]]).to_be_bytes())
+                .collect::<Vec<_>>();
+            png_writer.write_image_data(&samples)
+        }
+        _ => png_writer.write_image_data(image.as_bytes()),
+    }
+    .map_err(to_imgii_err)?;
+    png_writer.finish().map_err(to_imgii_err)
+}
+
+/// Adds text metadata to a PNG. Text that can be encoded as Latin-1 is written in `tEXt` chunks,
+/// anything else in UTF-8 `iTXt` chunks.
+///
+/// * `encoder`: The encoder of the PNG, before its header is written.
+/// * `metadata`: The text metadata to embed as (keyword, text) pairs.
+fn add_text_chunks<W: Write>(
+    encoder: &mut png::Encoder<W>,
+    metadata: &[(String, String)],
+) -> Result<(), ImgiiError> {
+    for (keyword, text) in metadata {
+        if text
+            .chars()
+            .all(|character| u32::from(character) <= u32::from(u8::MAX))
+        {
+            encoder.add_text_chunk(keyword.clone(), text.clone())
+        } else {
+            encoder.add_itxt_chunk(keyword.clone(), text.clone())
+        }
         .map_err(|err| -> ImgiiError { anyhow::Error::new(err).into() })?;
+    }
+    Ok(())
+}
+
+/// An image converted to indexed color, ready to be written as a PNG.
+struct IndexedImage {
+    /// The colors of the palette, as RGB.
+    palette: Vec<u8>,
+    /// The alpha of each color of the palette, if any color isn't fully opaque. Colors past the
+    /// end are fully opaque.
+    trns: Option<Vec<u8>>,
+    /// The number of bits each pixel is stored in.
+    bit_depth: png::BitDepth,
+    /// The palette index of every pixel, packed into rows of `bit_depth` bits per pixel.
+    data: Vec<u8>,
+}
+
+/// Converts an image to indexed color, with the smallest palette that holds every color of the
+/// image. Colors are picked the same way as for GIF frames, so images with more than 256 colors
+/// are quantized.
+///
+/// * `image`: The image to convert.
+///
+/// # Returns
+/// * The indexed image, or `None` if the image is too large to be quantized.
+fn index_colors(image: &DynamicImage) -> Option<IndexedImage> {
+    let (Ok(width), Ok(height)) = (u16::try_from(image.width()), u16::try_from(image.height()))
+    else {
+        log::warn!(
+            "{}x{} image is too large to be indexed, writing it without a palette",
+            image.width(),
+            image.height()
+        );
+        return None;
+    };
+
+    let mut pixels = image.to_rgba8().into_raw();
+    // quantize with the best quality, like GIF frames
+    let frame = gif::Frame::from_rgba_speed(width, height, &mut pixels, 1);
+    let palette = frame.palette.unwrap_or_default();
+    let trns = frame.transparent.map(|transparent| {
+        let mut trns = vec![u8::MAX; usize::from(transparent) + 1];
+        trns[usize::from(transparent)] = 0;
+        trns
+    });
+
+    let (bit_depth, bits) = match palette.len() / 3 {
+        0..=2 => (png::BitDepth::One, 1),
+        3..=4 => (png::BitDepth::Two, 2),
+        5..=16 => (png::BitDepth::Four, 4),
+        _ => (png::BitDepth::Eight, 8),
+    };
+    // each row starts on a new byte, with the first pixel in the highest bits
+    let pixels_per_byte = 8 / bits;
+    let data = frame
+        .buffer
+        .chunks_exact(usize::from(width).max(1))
+        .flat_map(|row| {
+            row.chunks(pixels_per_byte).map(|pixels| {
+                pixels
+                    .iter()
+                    .enumerate()
+                    .fold(0, |byte, (i, index)| byte | (index << (8 - bits * (i + 1))))
+            })
+        })
+        .collect();
+
+    Some(IndexedImage {
+        palette,
+        trns,
+        bit_depth,
+        data,
+    })
+}
+
+/// Converts a decoded image to an ASCII string using the [`crate::ascii_source::AsciiSource`] of
+/// the options (`RASCII` by default), preprocessing the image first if the options call for it.
+/// Posterizing replaces the source image, so it also changes the colors of each cell.
+///
+/// # Params
+/// * `image`: The decoded image.
+/// * `imgii_options`: The imgii options, holding the RASCII options for converting to ASCII.
+///
+/// # Returns
+/// * `String` containing the colored image data as ASCII, colored using terminal escape sequences.
+pub(crate) fn image_to_ascii(
+    image: &DynamicImage,
+    imgii_options: &ImgiiOptions,
+) -> Result<String, ImgiiError> {
+    let posterized;
+    let image = match imgii_options.posterize() {
+        Some(levels) => {
+            posterized = posterize(image, levels);
+            &posterized
+        }
+        None => image,
+    };
+    let source_image = image;
+    let edges;
+    let image = if imgii_options.edge_detect() {
+        edges = detect_edges(image);
+        &edges
+    } else {
+        image
+    };
+    // give the ASCII source a color image, so grayscale sources are colored the same way
+    let color_image;
+    let image = if image.color().has_color() {
+        image
+    } else {
+        color_image = DynamicImage::ImageRgba8(image.to_rgba8());
+        &color_image
+    };
+
+    // convert to ascii text, with RASCII by default
+    let ascii_text = imgii_options
+        .ascii_source()
+        .to_ascii(image, imgii_options)?;
+
+    // NOTE: these use the colors of the source image, even if it was preprocessed
+    let ascii_text = match imgii_options.cell_color_mode() {
+        CellColorMode::Sampled => ascii_text,
+        CellColorMode::Average => recolor_cells(
+            &ascii_text,
+            source_image,
+            average_color,
+            |[r, g, b], _, the_char| format!("\u{1b}[38;2;{r};{g};{b}m{the_char}"),
+        )?,
+        CellColorMode::Center => recolor_cells(
+            &ascii_text,
+            source_image,
+            center_color,
+            |[r, g, b], _, the_char| format!("\u{1b}[38;2;{r};{g};{b}m{the_char}"),
+        )?,
+    };
+    if imgii_options.cell_bg_from_source() {
+        recolor_cells(
+            &ascii_text,
+            source_image,
+            average_color,
+            |[r, g, b], color, the_char| format!("\u{1b}[48;2;{r};{g};{b}m{color}{the_char}"),
+        )
+    } else {
+        Ok(ascii_text)
+    }
+}
+
+/// Rewrites each cell of ASCII text using a color taken from the region of the source image that
+/// it covers.
+///
+/// * `ascii_text`: The colored ASCII text of the image.
+/// * `image`: The image that the ASCII text was converted from.
+/// * `region_color`: Takes the color of a region of the source image from its rows and columns.
+/// * `recolor`: Builds the new text of a cell from the color of its region as RGB, the escape
+///   sequence coloring the cell, and the character in the cell.
+fn recolor_cells(
+    ascii_text: &str,
+    image: &DynamicImage,
+    region_color: fn(&RgbImage, Range<u32>, Range<u32>) -> [u64; 3],
+    recolor: impl Fn([u64; 3], &str, &str) -> String,
+) -> Result<String, ImgiiError> {
+    // NOTE: \u{1b} represents the \033 character
+    let re = Regex::new(concat!(
+        '(',
+        '\u{1b}',
+        r"\[38;(?:2;[0-9]+;[0-9]+;[0-9]+|5;[0-9]+)m)(.)"
+    ))?;
+    let image = image.to_rgb8();
+    let grid_height = ascii_text.lines().count() as u64;
+    let grid_width = ascii_text
+        .lines()
+        .next()
+        .map_or(0, |line| re.find_iter(line).count()) as u64;
+    if grid_width == 0 || image.width() == 0 || image.height() == 0 {
+        // nothing to recolor
+        return Ok(String::from(ascii_text));
+    }
+
+    // the pixel range [start, end) of a cell along one axis, always covering at least one pixel
+    let cell_range = |cell: u64, cells: u64, pixels: u32| {
+        let pixels = u64::from(pixels);
+        let start = (cell * pixels / cells).min(pixels - 1);
+        let end = ((cell + 1) * pixels / cells).clamp(start + 1, pixels);
+        (start as u32)..(end as u32)
+    };
+
+    let mut recolored = String::with_capacity(ascii_text.len());
+    for (row, line) in ascii_text.lines().enumerate() {
+        let rows = cell_range(row as u64, grid_height, image.height());
+        let mut column = 0;
+        let line = re.replace_all(line, |captures: &regex::Captures| {
+            let columns = cell_range(column, grid_width, image.width());
+            column += 1;
+
+            recolor(
+                region_color(&image, rows.clone(), columns),
+                &captures[1],
+                &captures[2],
+            )
+        });
+        recolored.push_str(&line);
+        recolored.push('\n');
+    }
+
+    Ok(recolored)
+}
+
+/// Gets the average color of a region of an image as RGB.
+///
+/// * `image`: The image.
+/// * `rows`: The rows of the region. Must not be empty.
+/// * `columns`: The columns of the region. Must not be empty.
+fn average_color(image: &RgbImage, rows: Range<u32>, columns: Range<u32>) -> [u64; 3] {
+    let mut sum = [0u64; 3];
+    let mut count = 0u64;
+    for y in rows {
+        for x in columns.clone() {
+            for (total, channel) in sum.iter_mut().zip(image.get_pixel(x, y).0) {
+                *total += u64::from(channel);
+            }
+            count += 1;
+        }
+    }
+    sum.map(|total| total / count)
+}
+
+/// Gets the color of the pixel nearest to the center of a region of an image as RGB.
+///
+/// * `image`: The image.
+/// * `rows`: The rows of the region. Must not be empty.
+/// * `columns`: The columns of the region. Must not be empty.
+fn center_color(image: &RgbImage, rows: Range<u32>, columns: Range<u32>) -> [u64; 3] {
+    // the pixel the center falls in, which is the lower middle pixel for an even length
+    let center = |range: Range<u32>| (range.start + range.end) / 2;
+    image
+        .get_pixel(center(columns), center(rows))
+        .0
+        .map(u64::from)
+}
+
+/// Reduces each color channel of an image to a number of evenly spaced levels, including black
+/// and full brightness. Alpha is left untouched.
+///
+/// * `image`: The image to posterize.
+/// * `levels`: The number of levels per color channel. Must be at least 2.
+fn posterize(image: &DynamicImage, levels: u8) -> DynamicImage {
+    let steps = u16::from(levels.max(2) - 1);
+    let max = u16::from(u8::MAX);
+    let mut posterized = image.to_rgba8();
+    for pixel in posterized.pixels_mut() {
+        for channel in pixel.0.iter_mut().take(3) {
+            // round to the nearest level, then back to the full range
+            let level = (u16::from(*channel) * steps + max / 2) / max;
+            *channel = (level * max / steps) as u8;
+        }
+    }
+    DynamicImage::ImageRgba8(posterized)
+}
+
+/// Finds the edges of an image with a Sobel filter. Edges are bright and everything else is dark.
+///
+/// * `image`: The image to find the edges of.
+fn detect_edges(image: &DynamicImage) -> DynamicImage {
+    let gradients = sobel_gradients(&image.to_luma8());
+    DynamicImage::ImageLuma8(ImageBuffer::from_fn(
+        gradients.width(),
+        gradients.height(),
+        |x, y| {
+            // gradients can be much larger than a u8, so clamp them
+            let gradient = gradients.get_pixel(x, y).0[0];
+            Luma([gradient.min(u16::from(u8::MAX)) as u8])
+        },
+    ))
+}
+
+/// Renders ASCII text and writes it to a PNG stream one grid row at a time, flushing after each
+/// grid row.
+///
+/// At most one grid row of rendered pixels is held in memory at once, along with the glyph cache
+/// (unless it's disabled). The stitched image is never built, so post-processing that needs the
+/// whole image (`pad_to_multiple`, `bloom`, `scanlines` and `scale_output`) is not applied. The
+/// grid isn't fit to `fixed_grid` or faded by `opacity_mask` either. The PNG is compressed by
+/// `png_compression`, written at `bit_depth` and holds the metadata of `embed_metadata`.
+///
+/// # Params
+/// * `ascii_text`: The ASCII text to render.
+/// * `writer`: The writer to write the PNG stream to.
+/// * `imgii_options`: The imgii options for rendering ASCII.
+///
+/// # Returns
+/// * `Err(ImgiiError::InvalidArgument)` if the options ask for an indexed PNG (`png_palette` or
+///   `optimize_size`) or one without an alpha channel (`png_alpha`), since every grid row is
+///   written as RGBA as soon as it's rendered.
+pub(crate) fn stream_ascii_as_png<W: Write>(
+    ascii_text: &str,
+    writer: W,
+    imgii_options: &ImgiiOptions,
+) -> Result<(), ImgiiError> {
+    let to_imgii_err = |err: png::EncodingError| -> ImgiiError { anyhow::Error::new(err).into() };
+    if imgii_options.png_palette() || imgii_options.png_alpha() != PngAlpha::Keep {
+        // these need every color of the image before the first row can be written
+        return Err(ImgiiError::InvalidArgument);
+    }
+
+    let height = ascii_text.lines().count();
+    let mut row_renderer = AsciiRowRenderer::new(imgii_options, height)?;
+    let (char_width, char_height) = calculate_char_dimensions(imgii_options);
+    let canvas_background = canvas_background(imgii_options);
+    let mut lines = ascii_text.lines().enumerate();
+
+    // we have to render the first row to know the width of the image
+    let Some((_, first_line)) = lines.next() else {
+        // no image to build
+        return Err(ImgiiError::InvalidArgument);
+    };
+    let first_row = row_renderer.render_row(0, first_line)?;
+    if first_row.is_empty() {
+        return Err(ImgiiError::InvalidArgument);
+    }
+
+    let mut encoder = png::Encoder::new(
+        writer,
+        char_width * first_row.len() as u32,
+        char_height * height as u32,
+    );
+    encoder.set_color(png::ColorType::Rgba);
+    let bit_depth = match imgii_options.bit_depth() {
+        BitDepth::Eight => png::BitDepth::Eight,
+        BitDepth::Sixteen => png::BitDepth::Sixteen,
+    };
+    encoder.set_depth(bit_depth);
+    encoder.set_compression(imgii_options.png_compression().into());
+    if imgii_options.embed_metadata() {
+        add_text_chunks(&mut encoder, &png_metadata(None, imgii_options))?;
+    }
+    let mut png_writer = encoder.write_header().map_err(to_imgii_err)?;
+    let mut stream_writer = png_writer.stream_writer().map_err(to_imgii_err)?;
+
+    let mut row_images = first_row;
+    let mut next_lines = lines;
+    loop {
+        // copy each pixel row of every cell in this grid row, from left to right
+        let row_stride = char_width as usize * 4;
+        let mut row_bytes =
+            Vec::with_capacity(row_stride * row_images.len() * char_height as usize);
+        for y in 0..char_height as usize {
+            for cell in &row_images {
+                let cell = cell.as_buffer();
+                if (cell.width(), cell.height()) != (char_width, char_height) {
+                    return Err(ImageError::Render {
+                        reason: format!(
+                            "cell size {}x{} is not equal to the expected cell size {}x{}",
+                            cell.width(),
+                            cell.height(),
+                            char_width,
+                            char_height
+                        ),
+                    }
+                    .into());
+                }
+                row_bytes.extend_from_slice(&cell.as_raw()[y * row_stride..(y + 1) * row_stride]);
+            }
+        }
+        if let Some(background) = canvas_background {
+            for pixel in row_bytes.chunks_exact_mut(4) {
+                let filled = fill_background(
+                    Rgba([pixel[0], pixel[1], pixel[2], pixel[3]]),
+                    Some(background),
+                );
+                pixel.copy_from_slice(&filled.0);
+            }
+        }
+        if bit_depth == png::BitDepth::Sixteen {
+            // widen each sample to 16 bits, where repeating the byte maps 255 to 65535
+            row_bytes = row_bytes.iter().flat_map(|&sample| [sample; 2]).collect();
+        }
+        stream_writer.write_all(&row_bytes)?;
+        stream_writer.flush()?;
+
+        match next_lines.next() {
+            Some((i, line)) => row_images = row_renderer.render_row(i, line)?,
+            None => break,
+        }
+    }
+
+    stream_writer.finish().map_err(to_imgii_err)?;
+    png_writer.finish().map_err(to_imgii_err)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+    use crate::{
+        grid::parse_ascii_grid,
+        options::{PngCompression, ZeroWidthHandling},
+        test_support::{TEST_CHARSET, ansi_cell, test_dir, test_options_builder},
+    };
+
+    /// Reads the (horizontal, vertical) sampling factors of each component from the start of frame
+    /// segment of a baseline JPEG.
+    ///
+    /// * `jpeg`: The encoded JPEG.
+    fn jpeg_sampling_factors(jpeg: &[u8]) -> Vec<(u8, u8)> {
+        let sof = jpeg
+            .windows(2)
+            .position(|marker| marker == [0xFF, 0xC0])
+            .expect("no baseline start of frame segment");
+        // skip the marker, length, precision, height and width
+        let components = &jpeg[sof + 9..];
+        components[1..]
+            .chunks_exact(3)
+            .take(usize::from(components[0]))
+            .map(|component| (component[1] >> 4, component[1] & 0x0F))
+            .collect()
+    }
+
+    #[test]
+    fn jpegs_are_not_chroma_subsampled() {
+        let imgii_options = test_options_builder().build().unwrap();
+        let image = DynamicImage::ImageRgb8(RgbImage::from_fn(16, 16, |x, y| {
+            image::Rgb([(x * 16) as u8, (y * 16) as u8, 200])
+        }));
+
+        let mut jpeg = Cursor::new(Vec::new());
+        write_image(&image, &mut jpeg, "out.jpg", None, &[], &imgii_options).unwrap();
+        assert_eq!(
+            jpeg_sampling_factors(jpeg.get_ref()),
+            [(1, 1), (1, 1), (1, 1)]
+        );
+    }
+
+    #[test]
+    fn metadata_is_embedded_as_text_chunks() {
+        let dir = test_dir("metadata_is_embedded_as_text_chunks");
+        let output = dir.join("out.png");
+        let imgii_options = test_options_builder().embed_metadata(true).build().unwrap();
+        let image = DynamicImage::ImageRgba8(ImageBuffer::new(4, 4));
+
+        save_png(
+            &image,
+            output.to_str().unwrap(),
+            None,
+            Some("input.png"),
+            &imgii_options,
+        )
+        .unwrap();
+        let decoder = png::Decoder::new(std::io::BufReader::new(
+            std::fs::File::open(&output).unwrap(),
+        ));
+        let reader = decoder.read_info().unwrap();
+        let text = reader
+            .info()
+            .uncompressed_latin1_text
+            .iter()
+            .map(|chunk| (chunk.keyword.as_str(), chunk.text.as_str()))
+            .collect::<Vec<_>>();
+        assert!(text.contains(&("Charset", TEST_CHARSET.concat().as_str())));
+        assert!(text.contains(&("Source", "input.png")));
+    }
+
+    #[test]
+    fn center_mode_colors_cells_from_the_center_pixel() {
+        let imgii_options = test_options_builder()
+            .cell_color_mode(CellColorMode::Center)
+            .width(4)
+            .height(2)
+            .build()
+            .unwrap();
+        // every pixel is a different color
+        let source =
+            RgbImage::from_fn(8, 4, |x, y| image::Rgb([(x * 30) as u8, (y * 60) as u8, 7]));
+
+        let ascii_text =
+            image_to_ascii(&DynamicImage::ImageRgb8(source.clone()), &imgii_options).unwrap();
+        let grid = parse_ascii_grid(&ascii_text, 4, ZeroWidthHandling::Combine).unwrap();
+        assert_eq!((grid.width, grid.height), (4, 2));
+        for (cell_idx, cell) in grid.cells.iter().enumerate() {
+            // each cell covers 2x2 pixels, and the lower middle pixel is its center
+            let (column, row) = ((cell_idx % 4) as u32, (cell_idx / 4) as u32);
+            let center = source.get_pixel(column * 2 + 1, row * 2 + 1);
+            assert_eq!(
+                [cell.red, cell.green, cell.blue],
+                center.0,
+                "cell {cell_idx}"
+            );
+        }
+    }
+
+    #[test]
+    fn posterized_gradients_have_fewer_colors() {
+        // a smooth gradient, where nearly every cell is a different color
+        let source = DynamicImage::ImageRgb8(RgbImage::from_fn(64, 8, |x, y| {
+            image::Rgb([(x * 4) as u8, (y * 32) as u8, 255 - (x * 4) as u8])
+        }));
+        let distinct_colors = |imgii_options: &ImgiiOptions| {
+            let ascii_text = image_to_ascii(&source, imgii_options).unwrap();
+            let grid = parse_ascii_grid(&ascii_text, 4, ZeroWidthHandling::Combine).unwrap();
+            assert_eq!((grid.width, grid.height), (64, 8));
+            grid.cells
+                .iter()
+                .map(|cell| [cell.red, cell.green, cell.blue])
+                .collect::<HashSet<_>>()
+        };
+
+        let smooth = distinct_colors(&test_options_builder().width(64).height(8).build().unwrap());
+        let posterized = distinct_colors(
+            &test_options_builder()
+                .width(64)
+                .height(8)
+                .posterize(3)
+                .build()
+                .unwrap(),
+        );
+        assert!(posterized.len() < smooth.len());
+        // every channel is one of the 3 levels
+        assert!(
+            posterized
+                .iter()
+                .flatten()
+                .all(|channel| [0, 127, 255].contains(channel))
+        );
+    }
+
+    #[test]
+    fn streamed_pngs_honor_the_encoding_options() {
+        let ascii_text = [
+            [ansi_cell([255, 0, 0], '#'), ansi_cell([0, 255, 0], '@')].concat(),
+            [ansi_cell([0, 0, 255], ':'), ansi_cell([255, 255, 0], '.')].concat(),
+        ]
+        .join("\n");
+        let mut eight_bit = Vec::new();
+        stream_ascii_as_png(
+            &ascii_text,
+            &mut eight_bit,
+            &test_options_builder().build().unwrap(),
+        )
+        .unwrap();
+        let mut sixteen_bit = Vec::new();
+        stream_ascii_as_png(
+            &ascii_text,
+            &mut sixteen_bit,
+            &test_options_builder()
+                .bit_depth(BitDepth::Sixteen)
+                .png_compression(PngCompression::Best)
+                .embed_metadata(true)
+                .build()
+                .unwrap(),
+        )
+        .unwrap();
+
+        let decoder = png::Decoder::new(Cursor::new(&sixteen_bit));
+        let reader = decoder.read_info().unwrap();
+        assert_eq!(reader.info().bit_depth, png::BitDepth::Sixteen);
+        assert!(
+            reader
+                .info()
+                .uncompressed_latin1_text
+                .iter()
+                .any(|chunk| chunk.keyword == "Software" && chunk.text == "imgii")
+        );
+        // the same pixels, only widened
+        let eight_bit = image::load_from_memory(&eight_bit).unwrap().to_rgba16();
+        let sixteen_bit = image::load_from_memory(&sixteen_bit).unwrap().to_rgba16();
+        assert!(eight_bit == sixteen_bit);
+    }
 
-    Ok(ascii_text)
+    #[test]
+    fn streamed_pngs_refuse_options_they_cannot_write() {
+        let ascii_text = ansi_cell([255, 0, 0], '#');
+        for imgii_options in [
+            test_options_builder().png_palette(true).build().unwrap(),
+            test_options_builder().optimize_size(true).build().unwrap(),
+            test_options_builder()
+                .png_alpha(PngAlpha::Flatten)
+                .build()
+                .unwrap(),
+        ] {
+            let mut output = Vec::new();
+            assert!(matches!(
+                stream_ascii_as_png(&ascii_text, &mut output, &imgii_options),
+                Err(ImgiiError::InvalidArgument)
+            ));
+            assert!(output.is_empty());
+        }
+    }
 }
@@ -0,0 +1,93 @@
+//! Handles rendering a typewriter animation, where an image's ASCII is revealed cell-by-cell.
+
+use std::sync::Arc;
+
+use crate::{
+    conversion::{
+        converters::generic_converter::Imgii2dImage,
+        image_data::ImageData,
+        image_writer::AsciiImageWriter,
+        parallel::{IntoParallelIterator, ParallelIterator},
+    },
+    error::ImgiiError,
+    options::{ImgiiOptions, RevealOrder, TypewriterOptions},
+    stats::RenderStats,
+};
+use image::{Delay, Frame, ImageBuffer};
+
+/// Renders the frames of a typewriter animation from an already rendered image. Each frame
+/// reveals more cells than the last, with unrevealed cells left transparent. The final frame
+/// shows the entire image.
+///
+/// * `the_image`: The rendered image to reveal.
+/// * `imgii_options`: The imgii options for rendering ASCII.
+/// * `typewriter_options`: The typewriter animation options.
+pub(crate) fn render_typewriter_frames(
+    the_image: &Imgii2dImage,
+    imgii_options: &ImgiiOptions,
+    typewriter_options: &TypewriterOptions,
+) -> Result<Vec<Frame>, ImgiiError> {
+    let Some(first_cell) = the_image.image_2d.first() else {
+        // no image to reveal
+        return Err(ImgiiError::InvalidArgument);
+    };
+    // every cell is the same size, so we can use the same hidden cell for all of them
+    let hidden_cell = Arc::new(ImageData::new(ImageBuffer::new(
+        first_cell.as_buffer().width(),
+        first_cell.as_buffer().height(),
+    )));
+
+    let num_cells = the_image.image_2d.len();
+    let num_frames = num_cells.div_ceil(typewriter_options.cells_per_frame());
+    let delay = Delay::from_numer_denom_ms(typewriter_options.delay_ms(), 1);
+
+    (1..=num_frames)
+        .into_par_iter()
+        .map(|frame_idx| {
+            let revealed_cells = (frame_idx * typewriter_options.cells_per_frame()).min(num_cells);
+            let image_2d = the_image
+                .image_2d
+                .iter()
+                .enumerate()
+                .map(|(i, cell)| {
+                    if reveal_rank(i, the_image, typewriter_options.reveal_order()) < revealed_cells
+                    {
+                        cell.clone()
+                    } else {
+                        hidden_cell.clone()
+                    }
+                })
+                .collect();
+
+            let image_writer = AsciiImageWriter::from_2d_vec(
+                Imgii2dImage {
+                    image_2d,
+                    width: the_image.width,
+                    height: the_image.height,
+                    stats: RenderStats::default(),
+                    glyph_cache: Default::default(),
+                },
+                imgii_options,
+            )?;
+            Ok(Frame::from_parts(image_writer.imagebuf.into(), 0, 0, delay))
+        })
+        .collect()
+}
+
+/// Gets the position of a cell in the reveal order, where cells with a lower rank are revealed
+/// first.
+///
+/// * `cell_idx`: The row-major index of the cell in the image.
+/// * `the_image`: The image holding the cell.
+/// * `reveal_order`: The order that cells are revealed in.
+#[inline]
+fn reveal_rank(cell_idx: usize, the_image: &Imgii2dImage, reveal_order: RevealOrder) -> usize {
+    match reveal_order {
+        RevealOrder::RowMajor => cell_idx,
+        RevealOrder::ColumnMajor => {
+            let row = cell_idx / the_image.width;
+            let column = cell_idx % the_image.width;
+            column * the_image.height + row
+        }
+    }
+}
@@ -1,6 +1,11 @@
 //! Implementation for generic image data.
 
-use image::ImageBuffer;
+use image::{GrayImage, ImageBuffer, Luma, Rgb, RgbImage};
+
+use crate::{
+    error::{ImageError, ImgiiError},
+    options::ChannelOrder,
+};
 
 // easier to read
 pub(crate) type InternalImage = ImageBuffer<image::Rgba<u8>, Vec<u8>>;
@@ -8,9 +13,49 @@ pub(crate) type InternalImage = ImageBuffer<image::Rgba<u8>, Vec<u8>>;
 /// Represents the image data to work with.
 /// Holds an `ImageBuffer` with the image data.
 #[derive(Debug, Clone)]
-pub(crate) struct ImageData(InternalImage);
+pub struct ImageData(InternalImage);
 
 impl ImageData {
+    /// Creates image data from raw RGBA bytes, such as a pre-rendered cell.
+    ///
+    /// * `width`: The width of the image, in pixels.
+    /// * `height`: The height of the image, in pixels.
+    /// * `raw`: The RGBA bytes of the image, row by row starting from the top left pixel.
+    ///
+    /// # Returns
+    /// * `Err` if the length of `raw` isn't `width * height * 4`, otherwise the image data.
+    pub fn from_raw(width: u32, height: u32, raw: Vec<u8>) -> Result<Self, ImgiiError> {
+        match ImageBuffer::from_raw(width, height, raw) {
+            // NOTE: from_raw allows buffers that are too long, but extra bytes are most likely a
+            // mistake
+            Some(image_buffer) if image_buffer.len() == width as usize * height as usize * 4 => {
+                Ok(Self(image_buffer))
+            }
+            _ => Err(ImageError::InvalidParameter {
+                parameter_name: String::from("raw"),
+            }
+            .into()),
+        }
+    }
+
+    /// Gets the width of the image, in pixels.
+    #[must_use]
+    pub fn width(&self) -> u32 {
+        self.0.width()
+    }
+
+    /// Gets the height of the image, in pixels.
+    #[must_use]
+    pub fn height(&self) -> u32 {
+        self.0.height()
+    }
+
+    /// Gets the RGBA bytes of the image, row by row starting from the top left pixel.
+    #[must_use]
+    pub fn as_raw(&self) -> &[u8] {
+        self.0.as_raw()
+    }
+
     /// Create a new ImageData struct as this image buffer.
     pub(crate) fn new(image_buffer: InternalImage) -> Self {
         Self(image_buffer)
@@ -20,6 +65,51 @@ impl ImageData {
     pub(crate) fn as_buffer(&self) -> &InternalImage {
         &self.0
     }
+
+    /// Converts this image data to RGB, dropping the alpha channel.
+    ///
+    /// * `flatten`: If true, pixels that aren't fully opaque are blended onto a black background.
+    ///   Otherwise, an error is returned if there are any pixels that aren't fully opaque.
+    pub(crate) fn to_rgb(&self, flatten: bool) -> Result<RgbImage, ImgiiError> {
+        if !flatten && self.0.pixels().any(|pixel| pixel.0[3] != u8::MAX) {
+            return Err(ImageError::Render {
+                reason: String::from(
+                    "the image has transparent pixels, so its alpha channel can't be stripped",
+                ),
+            }
+            .into());
+        }
+
+        Ok(ImageBuffer::from_fn(
+            self.0.width(),
+            self.0.height(),
+            |x, y| {
+                let [r, g, b, a] = self.0.get_pixel(x, y).0;
+                // blending onto black is just scaling each channel by the alpha
+                let blend =
+                    |channel: u8| (u16::from(channel) * u16::from(a) / u16::from(u8::MAX)) as u8;
+                Rgb([blend(r), blend(g), blend(b)])
+            },
+        ))
+    }
+
+    /// Gets the alpha channel of this image data as a grayscale matte, where opaque pixels are
+    /// white and transparent pixels are black.
+    pub(crate) fn to_alpha_matte(&self) -> GrayImage {
+        ImageBuffer::from_fn(self.0.width(), self.0.height(), |x, y| {
+            Luma([self.0.get_pixel(x, y).0[3]])
+        })
+    }
+
+    /// Gets the raw bytes of this image data, with each pixel's channels in the given order.
+    ///
+    /// * `channel_order`: The order to write each pixel's channels in.
+    pub(crate) fn to_channel_ordered_bytes(&self, channel_order: ChannelOrder) -> Vec<u8> {
+        self.0
+            .pixels()
+            .flat_map(|pixel| channel_order.swizzle(pixel.0))
+            .collect()
+    }
 }
 
 // Simple conversion to make it possible to convert to and from an ImageData
@@ -1,13 +1,34 @@
 //! Implementation for writing ascii as an image.
 
+#[cfg(all(feature = "mmap", not(feature = "wasm")))]
+use crate::conversion::parallel::IndexedParallelIterator;
+#[cfg(feature = "mmap")]
+use crate::conversion::parallel::ParallelSliceMut;
 use crate::{
     conversion::{
         converters::generic_converter::Imgii2dImage,
         image_data::{ImageData, InternalImage},
+        parallel::{IntoParallelIterator, ParallelIterator, enumerate_pixels_mut},
+        render_char_to_png::calculate_char_dimensions,
     },
-    error::ImgiiError,
+    error::{ImageError, ImgiiError},
+    options::{ImgiiOptions, ResizeFilter},
 };
-use rayon::prelude::*;
+use image::{GrayImage, ImageBuffer, Luma, Pixel, Primitive, Rgb, Rgba, imageops};
+use imageproc::{definitions::Clamp, filter::gaussian_blur_f32};
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+/// A 16-bit RGBA image.
+pub(crate) type Rgba16Image = ImageBuffer<Rgba<u16>, Vec<u16>>;
+/// A 16-bit RGB image.
+pub(crate) type Rgb16Image = ImageBuffer<Rgb<u16>, Vec<u16>>;
+/// A 16-bit grayscale image.
+pub(crate) type Gray16Image = ImageBuffer<Luma<u16>, Vec<u16>>;
 
 /// An image writer which holds a rendered ASCII image.
 #[derive(Debug, Clone)]
@@ -32,44 +53,16 @@ impl AsciiImageWriter {
     /// - `parts` - A 2d `Vec` of images, with the `parts` array containing the rows (starting from 0
     ///   as the top of the image) and the inner array containing the columns (starting from 0 as
     ///   the leftmost part of the image).
+    /// - `imgii_options` - The imgii options, used for post-processing the stitched image.
     ///
     /// # Returns
     /// - An `Option` containing `Some` `AsciiImageWriter` upon success, or a
     ///   `None` upon failure.
-    pub(crate) fn from_2d_vec(the_image: Imgii2dImage) -> Result<Self, ImgiiError> {
-        if the_image.image_2d.is_empty() {
-            // no image to build
-            return Err(ImgiiError::InvalidArgument);
-        }
-
-        // find out the new canvas size
-        // this should always exist
-        let char_width = the_image.image_2d[0].as_buffer().width();
-        let char_height = the_image.image_2d[0].as_buffer().height();
-
-        // calculate image resolution in pixels based on this reference image
-        let height = char_height * the_image.height as u32;
-        let width = char_width * the_image.width as u32;
-
-        // create the new canvas to write to
-        let mut canvas: InternalImage = image::ImageBuffer::new(width, height);
-
-        // copy over pixels to canvas
-        canvas.par_enumerate_pixels_mut().for_each(|(x, y, pixel)| {
-            // the index into the row and column from the image_2d vec
-            let row = y / char_height;
-            let column = x / char_width;
-
-            // the index into the inner image that we want to read from
-            let inner_x = x % char_width;
-            let inner_y = y % char_height;
-
-            let new_pixel = the_image.image_2d[column as usize + row as usize * the_image.width]
-                .as_buffer()
-                .get_pixel(inner_x, inner_y);
-            // write the pixel we have chosen
-            *pixel = *new_pixel;
-        });
+    pub(crate) fn from_2d_vec(
+        the_image: Imgii2dImage,
+        imgii_options: &ImgiiOptions,
+    ) -> Result<Self, ImgiiError> {
+        let canvas = stitch_2d_vec(&the_image, imgii_options, |pixel| *pixel)?;
 
         // save the new image buffer
         Ok(Self {
@@ -77,3 +70,1110 @@ impl AsciiImageWriter {
         })
     }
 }
+
+/// Builds a new grayscale image from a 2d `Vec` of image parts, using a quarter of the memory of
+/// [`AsciiImageWriter::from_2d_vec`]. The alpha channel of every image part is dropped, so this
+/// should only be used when the image parts are opaque.
+///
+/// # Params
+/// - `the_image` - The image parts to stitch together.
+/// - `imgii_options` - The imgii options, used for post-processing the stitched image.
+///
+/// # Returns
+/// - The stitched grayscale image upon success, `Err` otherwise.
+pub(crate) fn luma_from_2d_vec(
+    the_image: &Imgii2dImage,
+    imgii_options: &ImgiiOptions,
+) -> Result<GrayImage, ImgiiError> {
+    stitch_2d_vec(the_image, imgii_options, |pixel| pixel.to_luma())
+}
+
+/// Builds a new 16-bit image from a 2d `Vec` of image parts, like [`AsciiImageWriter::from_2d_vec`].
+/// Each image part is widened to 16 bits before it's stitched, so post-processing (such as
+/// scaling the output) is done at 16 bits, avoiding banding in smooth gradients.
+///
+/// # Params
+/// - `the_image` - The image parts to stitch together.
+/// - `imgii_options` - The imgii options, used for post-processing the stitched image.
+///
+/// # Returns
+/// - The stitched 16-bit image upon success, `Err` otherwise.
+pub(crate) fn rgba16_from_2d_vec(
+    the_image: &Imgii2dImage,
+    imgii_options: &ImgiiOptions,
+) -> Result<Rgba16Image, ImgiiError> {
+    stitch_2d_vec(the_image, imgii_options, |pixel| Rgba(pixel.0.map(widen)))
+}
+
+/// Builds a new 16-bit grayscale image from a 2d `Vec` of image parts, like [`luma_from_2d_vec`].
+///
+/// # Params
+/// - `the_image` - The image parts to stitch together.
+/// - `imgii_options` - The imgii options, used for post-processing the stitched image.
+///
+/// # Returns
+/// - The stitched 16-bit grayscale image upon success, `Err` otherwise.
+pub(crate) fn luma16_from_2d_vec(
+    the_image: &Imgii2dImage,
+    imgii_options: &ImgiiOptions,
+) -> Result<Gray16Image, ImgiiError> {
+    stitch_2d_vec(the_image, imgii_options, |pixel| {
+        Luma([widen(pixel.to_luma().0[0])])
+    })
+}
+
+/// Converts a 16-bit image to RGB, dropping the alpha channel, like [`ImageData::to_rgb`].
+///
+/// * `image`: The 16-bit image to convert.
+/// * `flatten`: If true, pixels that aren't fully opaque are blended onto a black background.
+///   Otherwise, an error is returned if there are any pixels that aren't fully opaque.
+pub(crate) fn rgba16_to_rgb16(
+    image: &Rgba16Image,
+    flatten: bool,
+) -> Result<Rgb16Image, ImgiiError> {
+    if !flatten && image.pixels().any(|pixel| pixel.0[3] != u16::MAX) {
+        return Err(ImageError::Render {
+            reason: String::from(
+                "the image has transparent pixels, so its alpha channel can't be stripped",
+            ),
+        }
+        .into());
+    }
+
+    Ok(ImageBuffer::from_fn(
+        image.width(),
+        image.height(),
+        |x, y| {
+            let [r, g, b, a] = image.get_pixel(x, y).0;
+            // blending onto black is just scaling each channel by the alpha
+            let blend =
+                |channel: u16| (u32::from(channel) * u32::from(a) / u32::from(u16::MAX)) as u16;
+            Rgb([blend(r), blend(g), blend(b)])
+        },
+    ))
+}
+
+/// Widens an 8-bit channel to 16 bits, so 255 maps to 65535.
+///
+/// * `channel`: The 8-bit channel.
+fn widen(channel: u8) -> u16 {
+    u16::from(channel) * 257
+}
+
+/// Stitches an image together from a 2d `Vec` of image parts, then post-processes it, converting
+/// every pixel along the way.
+///
+/// # Params
+/// - `the_image` - The image parts to stitch together.
+/// - `imgii_options` - The imgii options, used for post-processing the stitched image.
+/// - `to_pixel` - Converts a pixel of an image part into a pixel of the canvas.
+///
+/// # Returns
+/// - The stitched image upon success, `Err` otherwise.
+fn stitch_2d_vec<P>(
+    the_image: &Imgii2dImage,
+    imgii_options: &ImgiiOptions,
+    to_pixel: impl Fn(&Rgba<u8>) -> P + Send + Sync,
+) -> Result<ImageBuffer<P, Vec<P::Subpixel>>, ImgiiError>
+where
+    P: Pixel + Send + Sync + 'static,
+    P::Subpixel: Darken + Into<f32> + Clamp<f32> + Send + Sync,
+{
+    if the_image.image_2d.is_empty() {
+        // no image to build
+        return Err(ImgiiError::InvalidArgument);
+    }
+
+    // composite every cell over the continuous background, if there is one, as it's copied in.
+    // This is the same as filling the canvas first, without a second pass over it.
+    let canvas_background = canvas_background(imgii_options);
+    let to_canvas_pixel = |pixel: &Rgba<u8>| to_pixel(&fill_background(*pixel, canvas_background));
+
+    // make sure we can actually allocate the canvas
+    check_memory_budget(
+        the_image.width,
+        the_image.height,
+        size_of::<P>() as u64,
+        imgii_options,
+    )?;
+
+    // find out the new canvas size from the same cell size every cell was rendered with, so the
+    // stitched image never drifts from the grid
+    let (char_width, char_height) = calculate_char_dimensions(imgii_options);
+    if let Some((index, cell)) = the_image
+        .image_2d
+        .iter()
+        .enumerate()
+        .find(|(_, cell)| cell.as_buffer().dimensions() != (char_width, char_height))
+    {
+        let (cell_width, cell_height) = cell.as_buffer().dimensions();
+        return Err(ImageError::Render {
+            reason: format!(
+                "cell {index} is {cell_width}x{cell_height} pixels, expected {char_width}x{char_height}"
+            ),
+        }
+        .into());
+    }
+
+    if let Some(source_resolution) = imgii_options
+        .source_resolution()
+        .filter(|_| imgii_options.subpixel_cells())
+    {
+        // stitch straight at the source resolution, so no cell is resized across its neighbors
+        let mut canvas = stitch_subpixel(
+            the_image,
+            source_resolution,
+            imgii_options.resize_filter_output() == ResizeFilter::Nearest,
+            &to_canvas_pixel,
+        );
+        if let Some((radius, strength)) = imgii_options.bloom() {
+            draw_bloom(&mut canvas, radius, strength);
+        }
+        if let Some((spacing, darkness)) = imgii_options.scanlines() {
+            draw_scanlines(&mut canvas, spacing, darkness);
+        }
+        if let Some((multiple, color)) = imgii_options.pad_to_multiple() {
+            canvas = pad_to_multiple(canvas, multiple, to_pixel(&color));
+        }
+        return Ok(canvas);
+    }
+
+    // calculate image resolution in pixels based on this reference image
+    let height = char_height * the_image.height as u32;
+    let width = char_width * the_image.width as u32;
+
+    // create the new canvas to write to
+    let mut canvas: ImageBuffer<P, Vec<P::Subpixel>> = ImageBuffer::new(width, height);
+
+    // copy over pixels to canvas
+    enumerate_pixels_mut(&mut canvas).for_each(|(x, y, pixel)| {
+        // the index into the row and column from the image_2d vec
+        let row = y / char_height;
+        let column = x / char_width;
+
+        // the index into the inner image that we want to read from
+        let inner_x = x % char_width;
+        let inner_y = y % char_height;
+
+        let new_pixel = the_image.image_2d[column as usize + row as usize * the_image.width]
+            .as_buffer()
+            .get_pixel(inner_x, inner_y);
+        // write the pixel we have chosen
+        *pixel = to_canvas_pixel(new_pixel);
+    });
+
+    // post-process the canvas now that it's been stitched together
+    if let Some((radius, strength)) = imgii_options.bloom() {
+        draw_bloom(&mut canvas, radius, strength);
+    }
+    if let Some((spacing, darkness)) = imgii_options.scanlines() {
+        draw_scanlines(&mut canvas, spacing, darkness);
+    }
+    if let Some((new_width, new_height)) = output_size(width, height, imgii_options) {
+        canvas = imageops::resize(
+            &canvas,
+            new_width,
+            new_height,
+            imgii_options.resize_filter_output().into(),
+        );
+    }
+    // pad last so the padding keeps its exact color, which isn't composited over the background
+    if let Some((multiple, color)) = imgii_options.pad_to_multiple() {
+        canvas = pad_to_multiple(canvas, multiple, to_pixel(&color));
+    }
+
+    Ok(canvas)
+}
+
+/// Gets the background filled under the whole image once it's stitched together, if the
+/// background is enabled and continuous.
+///
+/// A fully opaque background is always filled into each cell instead, since it can't leave seams
+/// between cells and the result is the same, without compositing every pixel.
+///
+/// * `imgii_options`: The imgii options for rendering ASCII.
+pub(crate) fn canvas_background(imgii_options: &ImgiiOptions) -> Option<Rgba<u8>> {
+    let background_color = imgii_options.background_color();
+    (imgii_options.background()
+        && imgii_options.continuous_background()
+        && background_color.0[3] != u8::MAX)
+        .then_some(background_color)
+}
+
+/// Composites a pixel of a cell over the background, if there is one.
+///
+/// * `pixel`: The pixel of the cell.
+/// * `background`: The background to composite the pixel over, if any.
+#[inline]
+pub(crate) fn fill_background(pixel: Rgba<u8>, background: Option<Rgba<u8>>) -> Rgba<u8> {
+    match (background, pixel.0[3]) {
+        // opaque pixels cover the background entirely, so skip blending them
+        (None, _) | (_, u8::MAX) => pixel,
+        (Some(background), 0) => background,
+        (Some(mut background), _) => {
+            background.blend(&pixel);
+            background
+        }
+    }
+}
+
+/// Stitches an image together at an exact size from a 2d `Vec` of image parts, where each cell
+/// covers a fractional number of pixels. Every cell starts at its exact fractional position rather
+/// than a rounded one, so cell boundaries never drift across the image, and each pixel is sampled
+/// only from the cell it falls in, so cells never bleed into their neighbors.
+///
+/// # Params
+/// - `the_image` - The image parts to stitch together. Every part must be the same size.
+/// - `(width, height)` - The size of the stitched image, in pixels.
+/// - `nearest` - Whether to sample the nearest pixel of each cell, rather than interpolating.
+/// - `to_pixel` - Converts a pixel of an image part into a pixel of the canvas.
+fn stitch_subpixel<P>(
+    the_image: &Imgii2dImage,
+    (width, height): (u32, u32),
+    nearest: bool,
+    to_pixel: &(impl Fn(&Rgba<u8>) -> P + Send + Sync),
+) -> ImageBuffer<P, Vec<P::Subpixel>>
+where
+    P: Pixel + Send + Sync + 'static,
+    P::Subpixel: Send + Sync,
+{
+    let (char_width, char_height) = the_image.image_2d[0].as_buffer().dimensions();
+    let mut canvas: ImageBuffer<P, Vec<P::Subpixel>> = ImageBuffer::new(width, height);
+
+    enumerate_pixels_mut(&mut canvas).for_each(|(x, y, pixel)| {
+        // where the center of this pixel falls on the grid, in cells
+        let grid_x = (f64::from(x) + 0.5) * the_image.width as f64 / f64::from(width);
+        let grid_y = (f64::from(y) + 0.5) * the_image.height as f64 / f64::from(height);
+        let column = (grid_x as usize).min(the_image.width - 1);
+        let row = (grid_y as usize).min(the_image.height - 1);
+
+        // where it falls inside of the cell, in pixels of the cell
+        let inner_x = (grid_x - column as f64) * f64::from(char_width) - 0.5;
+        let inner_y = (grid_y - row as f64) * f64::from(char_height) - 0.5;
+        let cell = the_image.image_2d[column + row * the_image.width].as_buffer();
+        *pixel = to_pixel(&sample_cell(cell, inner_x, inner_y, nearest));
+    });
+
+    canvas
+}
+
+/// Samples a cell at a fractional position, clamped to the edges of the cell.
+///
+/// * `cell`: The cell to sample.
+/// * `x`: The x position to sample, in pixels of the cell.
+/// * `y`: The y position to sample, in pixels of the cell.
+/// * `nearest`: Whether to sample the nearest pixel, rather than interpolating between the four
+///   nearest pixels.
+fn sample_cell(cell: &InternalImage, x: f64, y: f64, nearest: bool) -> Rgba<u8> {
+    let max_x = f64::from(cell.width() - 1);
+    let max_y = f64::from(cell.height() - 1);
+    let (x, y) = (x.clamp(0.0, max_x), y.clamp(0.0, max_y));
+    if nearest {
+        return *cell.get_pixel(x.round() as u32, y.round() as u32);
+    }
+
+    let (left, top) = (x.floor(), y.floor());
+    let (right, bottom) = ((left + 1.0).min(max_x), (top + 1.0).min(max_y));
+    let (weight_x, weight_y) = (x - left, y - top);
+    let corners = [
+        (left, top, (1.0 - weight_x) * (1.0 - weight_y)),
+        (right, top, weight_x * (1.0 - weight_y)),
+        (left, bottom, (1.0 - weight_x) * weight_y),
+        (right, bottom, weight_x * weight_y),
+    ];
+
+    // blend premultiplied by alpha, so transparent pixels don't darken the edges of glyphs
+    let mut sum = [0.0; 4];
+    for (corner_x, corner_y, weight) in corners {
+        let [r, g, b, a] = cell.get_pixel(corner_x as u32, corner_y as u32).0;
+        let alpha = f64::from(a) * weight;
+        sum[0] += f64::from(r) * alpha;
+        sum[1] += f64::from(g) * alpha;
+        sum[2] += f64::from(b) * alpha;
+        sum[3] += alpha;
+    }
+    if sum[3] <= 0.0 {
+        return Rgba([0, 0, 0, 0]);
+    }
+    let unpremultiply = |channel: f64| (channel / sum[3]).round().clamp(0.0, 255.0) as u8;
+    Rgba([
+        unpremultiply(sum[0]),
+        unpremultiply(sum[1]),
+        unpremultiply(sum[2]),
+        sum[3].round().clamp(0.0, 255.0) as u8,
+    ])
+}
+
+/// Calculates the size of an image after scaling it, never going below 1 pixel.
+///
+/// * `width`: The width of the image.
+/// * `height`: The height of the image.
+/// * `scale`: The factor to scale the image by.
+fn scaled_dimensions(width: u32, height: u32, scale: f32) -> (u32, u32) {
+    (
+        ((width as f32 * scale).round() as u32).max(1),
+        ((height as f32 * scale).round() as u32).max(1),
+    )
+}
+
+/// Calculates the size to resize a stitched image to, matching the source resolution or scaling
+/// it.
+///
+/// * `width`: The width of the stitched image.
+/// * `height`: The height of the stitched image.
+/// * `imgii_options`: The imgii options, used for post-processing.
+///
+/// # Returns
+/// * The (width, height) to resize to, or `None` if the image isn't resized.
+fn output_size(width: u32, height: u32, imgii_options: &ImgiiOptions) -> Option<(u32, u32)> {
+    imgii_options.source_resolution().or_else(|| {
+        imgii_options
+            .scale_output()
+            .map(|scale| scaled_dimensions(width, height, scale))
+    })
+}
+
+/// Calculates the size of a stitched image once it has been post-processed, without rendering
+/// anything.
+///
+/// * `width`: The width of the stitched image.
+/// * `height`: The height of the stitched image.
+/// * `imgii_options`: The imgii options, used for post-processing.
+pub(crate) fn output_dimensions(
+    width: u32,
+    height: u32,
+    imgii_options: &ImgiiOptions,
+) -> (u32, u32) {
+    let (width, height) = output_size(width, height, imgii_options).unwrap_or((width, height));
+    match imgii_options.pad_to_multiple() {
+        Some((multiple, _)) => (
+            width.next_multiple_of(multiple),
+            height.next_multiple_of(multiple),
+        ),
+        None => (width, height),
+    }
+}
+
+/// Stitches a 2d `Vec` of image parts directly into a memory-mapped file as raw pixel bytes, so
+/// the stitched image never has to fit in memory. The file is created (or truncated) at exactly
+/// the size of the raw image. No post-processing is applied.
+///
+/// # Params
+/// - `the_image` - The image parts to stitch together.
+/// - `output_file_name` - The output file name.
+/// - `imgii_options` - The imgii options, holding the order to write each pixel's channels in.
+///
+/// # Returns
+/// - The (width, height) of the written image, in pixels, upon success.
+#[cfg(feature = "mmap")]
+pub(crate) fn write_2d_vec_to_mmap(
+    the_image: &Imgii2dImage,
+    output_file_name: &str,
+    imgii_options: &ImgiiOptions,
+) -> Result<(u32, u32), ImgiiError> {
+    let channel_order = imgii_options.channel_order();
+    let background = canvas_background(imgii_options);
+    if the_image.image_2d.is_empty() {
+        // no image to build
+        return Err(ImgiiError::InvalidArgument);
+    }
+
+    // calculate image resolution in pixels based on the first cell
+    let char_width = the_image.image_2d[0].as_buffer().width() as usize;
+    let char_height = the_image.image_2d[0].as_buffer().height() as usize;
+    let width = char_width * the_image.width;
+    let height = char_height * the_image.height;
+    let row_len = width * 4;
+
+    prepare_output_dir(output_file_name, imgii_options)?;
+    let file = std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(output_file_name)?;
+    file.set_len((row_len * height) as u64)?;
+    if row_len * height == 0 {
+        // there's nothing to map
+        return Ok((width as u32, height as u32));
+    }
+    // SAFETY: we just created this file and nothing else should be modifying it while it's mapped
+    let mut mmap = unsafe { memmap2::MmapMut::map_mut(&file)? };
+
+    // copy over pixels to the mapped file, one row of pixels at a time
+    mmap.par_chunks_mut(row_len)
+        .enumerate()
+        .for_each(|(y, row)| {
+            let grid_row = y / char_height;
+            let inner_y = (y % char_height) as u32;
+            for (x, pixel) in row.chunks_exact_mut(4).enumerate() {
+                let cell = &the_image.image_2d[x / char_width + grid_row * the_image.width];
+                let inner_x = (x % char_width) as u32;
+                let cell_pixel = *cell.as_buffer().get_pixel(inner_x, inner_y);
+                pixel.copy_from_slice(
+                    &channel_order.swizzle(fill_background(cell_pixel, background).0),
+                );
+            }
+        });
+    mmap.flush()?;
+
+    Ok((width as u32, height as u32))
+}
+
+/// Writes bytes to a file, unless the file already holds exactly these bytes. Leaving unchanged
+/// files alone keeps their modification time.
+///
+/// # Params
+/// - `output_file_name` - The output file name.
+/// - `bytes` - The bytes to write.
+/// - `imgii_options` - The imgii options, deciding whether the file is written atomically.
+pub(crate) fn write_if_changed(
+    output_file_name: &str,
+    bytes: &[u8],
+    imgii_options: &ImgiiOptions,
+) -> Result<(), ImgiiError> {
+    match std::fs::read(output_file_name) {
+        Ok(existing_bytes) if existing_bytes == bytes => {
+            log::info!("Skipping writing {output_file_name}, it is unchanged");
+            Ok(())
+        }
+        // the file is different or can't be read, so write it
+        _ => write_output_file(output_file_name, imgii_options, |writer| {
+            Ok(writer.write_all(bytes)?)
+        }),
+    }
+}
+
+/// Creates an output file and writes to it. If atomic writes are enabled, the file is written to
+/// a temporary file in the same directory first, then renamed over the output file once it's
+/// complete, so the output file is never seen partially written.
+///
+/// # Params
+/// - `output_file_name` - The output file name.
+/// - `imgii_options` - The imgii options, deciding whether the file is written atomically.
+/// - `write` - Writes the contents of the file.
+pub(crate) fn write_output_file<T>(
+    output_file_name: &str,
+    imgii_options: &ImgiiOptions,
+    write: impl FnOnce(&mut BufWriter<File>) -> Result<T, ImgiiError>,
+) -> Result<T, ImgiiError> {
+    prepare_output_dir(output_file_name, imgii_options)?;
+    if !imgii_options.atomic_writes() {
+        let mut file_writer = BufWriter::new(File::create(output_file_name)?);
+        let result = write(&mut file_writer)?;
+        file_writer.flush()?;
+        return Ok(result);
+    }
+
+    let temp_file_name = temp_file_name(output_file_name);
+    let written = File::create(&temp_file_name)
+        .map_err(ImgiiError::from)
+        .and_then(|file| {
+            let mut file_writer = BufWriter::new(file);
+            let result = write(&mut file_writer)?;
+            // make sure the contents are on disk before the output file can point at them
+            file_writer
+                .into_inner()
+                .map_err(|err| err.into_error())?
+                .sync_all()?;
+            Ok(result)
+        })
+        .and_then(|result| {
+            rename_over(&temp_file_name, Path::new(output_file_name))?;
+            Ok(result)
+        });
+    if written.is_err() {
+        // don't leave the partial file lying around, the error is more useful than this one
+        let _ = std::fs::remove_file(&temp_file_name);
+    }
+    written
+}
+
+/// Makes sure the directory an output file is written to exists, creating it if the options allow.
+///
+/// # Params
+/// - `output_file_name` - The output file name.
+/// - `imgii_options` - The imgii options, deciding whether missing directories are created.
+///
+/// # Returns
+/// - `Err` naming the directory if it's missing and can't be created, `Ok` otherwise.
+pub(crate) fn prepare_output_dir(
+    output_file_name: &str,
+    imgii_options: &ImgiiOptions,
+) -> Result<(), ImgiiError> {
+    let Some(output_dir) = Path::new(output_file_name)
+        .parent()
+        .filter(|dir| !dir.as_os_str().is_empty() && !dir.exists())
+    else {
+        // written to the current directory, or the directory is already there
+        return Ok(());
+    };
+
+    if imgii_options.create_dirs() {
+        std::fs::create_dir_all(output_dir)?;
+        return Ok(());
+    }
+    Err(std::io::Error::new(
+        std::io::ErrorKind::NotFound,
+        format!(
+            "the output directory {} does not exist (enable create_dirs to create it)",
+            output_dir.display()
+        ),
+    )
+    .into())
+}
+
+/// Gets the name of a temporary file to write an output file to, in the same directory as the
+/// output file so it can be renamed over it. The name is hidden and unique to this write.
+///
+/// * `output_file_name`: The output file name.
+fn temp_file_name(output_file_name: &str) -> PathBuf {
+    static TEMP_FILE_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+    let output_path = Path::new(output_file_name);
+    let file_name = output_path
+        .file_name()
+        .map_or_else(|| output_file_name.into(), |name| name.to_string_lossy());
+    output_path.with_file_name(format!(
+        ".{file_name}.{}-{}.tmp",
+        std::process::id(),
+        TEMP_FILE_COUNTER.fetch_add(1, Ordering::Relaxed)
+    ))
+}
+
+/// Renames a file over another, replacing it if it exists.
+///
+/// * `from`: The file to rename.
+/// * `to`: The file to replace.
+fn rename_over(from: &Path, to: &Path) -> std::io::Result<()> {
+    match std::fs::rename(from, to) {
+        // renaming over an existing file can fail on Windows (such as when it's read-only), so
+        // remove it and try again. This isn't atomic, but the file is never partially written.
+        #[cfg(windows)]
+        Err(_) if to.exists() => {
+            std::fs::remove_file(to)?;
+            std::fs::rename(from, to)
+        }
+        result => result,
+    }
+}
+
+/// Checks that an image stitched from a grid of cells fits in the memory budget, so we can return
+/// an error instead of running out of memory.
+///
+/// * `grid_width`: The width of the grid, in cells.
+/// * `grid_height`: The height of the grid, in cells.
+/// * `bytes_per_pixel`: The number of bytes each pixel of the image takes up.
+/// * `imgii_options`: The imgii options, holding the memory budget.
+pub(crate) fn check_memory_budget(
+    grid_width: usize,
+    grid_height: usize,
+    bytes_per_pixel: u64,
+    imgii_options: &ImgiiOptions,
+) -> Result<(), ImgiiError> {
+    let Some(budget) = imgii_options.effective_memory_budget() else {
+        // no budget to check against
+        return Ok(());
+    };
+
+    let (char_width, char_height) = calculate_char_dimensions(imgii_options);
+    let required = (grid_width as u64)
+        .saturating_mul(grid_height as u64)
+        .saturating_mul(u64::from(char_width) * u64::from(char_height) * bytes_per_pixel);
+    if required > budget {
+        return Err(ImageError::ExceedsMemoryBudget { required, budget }.into());
+    }
+    Ok(())
+}
+
+/// Stacks images on top of each other into a single image, with the first image at the top.
+/// Images narrower than the widest image are padded on the right with transparent pixels.
+///
+/// The stacked image is allocated once, then each image is copied into it in parallel.
+///
+/// * `images`: The images to stack, from top to bottom.
+/// * `separator`: The height and color of a line drawn between each image, if any.
+///
+/// # Returns
+/// * The stacked image, or `Err` if it would be too large.
+pub(crate) fn stack_vertically(
+    images: &[InternalImage],
+    separator: Option<(u32, Rgba<u8>)>,
+) -> Result<InternalImage, ImgiiError> {
+    let (separator_height, separator_color) = separator.unwrap_or((0, Rgba([0, 0, 0, 0])));
+    let width = images.iter().map(|image| image.width()).max().unwrap_or(0);
+    let height = stacked_length(images.iter().map(|image| image.height()), separator_height)?;
+    if width == 0 || height == 0 {
+        // nothing to copy, and empty rows can't be split into chunks
+        return Ok(InternalImage::new(width, height));
+    }
+
+    // every row of the stacked image, in bytes
+    let row_len = width as usize * 4;
+    let mut stacked = stacked_buffer(width, height)?;
+
+    // split the stacked image into the rows for each image, so they can be copied in parallel
+    let mut parts = Vec::with_capacity(images.len());
+    let mut remaining = stacked.as_mut_slice();
+    for (i, image) in images.iter().enumerate() {
+        if i > 0 {
+            let (separator_rows, rest) =
+                remaining.split_at_mut(row_len * separator_height as usize);
+            for pixel in separator_rows.chunks_exact_mut(4) {
+                pixel.copy_from_slice(&separator_color.0);
+            }
+            remaining = rest;
+        }
+        let (image_rows, rest) = remaining.split_at_mut(row_len * image.height() as usize);
+        // images without any width have nothing to copy, and can't be split into rows
+        if image.width() > 0 {
+            parts.push((image, image_rows));
+        }
+        remaining = rest;
+    }
+
+    parts.into_par_iter().for_each(|(image, image_rows)| {
+        let image_row_len = image.width() as usize * 4;
+        for (row, source_row) in image_rows
+            .chunks_exact_mut(row_len)
+            .zip(image.as_raw().chunks_exact(image_row_len))
+        {
+            row[..image_row_len].copy_from_slice(source_row);
+        }
+    });
+
+    // NOTE: this can't fail since the buffer is exactly the right size
+    Ok(image::ImageBuffer::from_raw(width, height, stacked).unwrap_or_default())
+}
+
+/// Places images next to each other into a single image, with the first image on the left.
+/// Images shorter than the tallest image are padded at the bottom with transparent pixels.
+///
+/// The stacked image is allocated once, then each row of it is filled in parallel.
+///
+/// * `images`: The images to stack, from left to right.
+/// * `separator`: The width and color of a line drawn between each image, if any.
+///
+/// # Returns
+/// * The stacked image, or `Err` if it would be too large.
+pub(crate) fn stack_horizontally(
+    images: &[InternalImage],
+    separator: Option<(u32, Rgba<u8>)>,
+) -> Result<InternalImage, ImgiiError> {
+    let (separator_width, separator_color) = separator.unwrap_or((0, Rgba([0, 0, 0, 0])));
+    let height = images.iter().map(|image| image.height()).max().unwrap_or(0);
+    let width = stacked_length(images.iter().map(|image| image.width()), separator_width)?;
+    if width == 0 || height == 0 {
+        // nothing to copy, and empty rows can't be split into chunks
+        return Ok(InternalImage::new(width, height));
+    }
+
+    // every row of the stacked image, in bytes
+    let row_len = width as usize * 4;
+    let mut stacked = stacked_buffer(width, height)?;
+
+    // each row holds a row of every image that's tall enough, so rows can be filled in parallel
+    let rows = stacked
+        .chunks_exact_mut(row_len)
+        .enumerate()
+        .collect::<Vec<_>>();
+    rows.into_par_iter().for_each(|(y, row)| {
+        let mut remaining = row;
+        for (i, image) in images.iter().enumerate() {
+            if i > 0 {
+                let (separator_pixels, rest) = remaining.split_at_mut(separator_width as usize * 4);
+                for pixel in separator_pixels.chunks_exact_mut(4) {
+                    pixel.copy_from_slice(&separator_color.0);
+                }
+                remaining = rest;
+            }
+            let image_row_len = image.width() as usize * 4;
+            let (image_pixels, rest) = remaining.split_at_mut(image_row_len);
+            // shorter images leave the rest of their column transparent
+            if let Some(source_row) = image
+                .as_raw()
+                .get(image_row_len * y..image_row_len * (y + 1))
+            {
+                image_pixels.copy_from_slice(source_row);
+            }
+            remaining = rest;
+        }
+    });
+
+    // NOTE: this can't fail since the buffer is exactly the right size
+    Ok(image::ImageBuffer::from_raw(width, height, stacked).unwrap_or_default())
+}
+
+/// Adds up the lengths of images placed end to end, along with a separator between each of them.
+///
+/// * `lengths`: The length of each image, in the direction they're stacked in.
+/// * `separator_length`: The length of each separator.
+///
+/// # Returns
+/// * The total length, or `Err` if it's too long for an image.
+fn stacked_length(
+    lengths: impl ExactSizeIterator<Item = u32>,
+    separator_length: u32,
+) -> Result<u32, ImgiiError> {
+    let separator_count = (lengths.len() as u64).saturating_sub(1);
+    u64::from(separator_length)
+        .checked_mul(separator_count)
+        .and_then(|separators| {
+            lengths
+                .map(u64::from)
+                .try_fold(separators, u64::checked_add)
+        })
+        .and_then(|total| u32::try_from(total).ok())
+        .ok_or_else(|| {
+            ImageError::Render {
+                reason: String::from("the stacked images are too large for a single image"),
+            }
+            .into()
+        })
+}
+
+/// Allocates the transparent RGBA buffer of a stacked image.
+///
+/// * `(width, height)`: The size of the stacked image.
+///
+/// # Returns
+/// * The buffer, or `Err` if it's too large to allocate.
+fn stacked_buffer(width: u32, height: u32) -> Result<Vec<u8>, ImgiiError> {
+    let len = u64::from(width)
+        .checked_mul(u64::from(height))
+        .and_then(|pixels| pixels.checked_mul(4))
+        .and_then(|len| usize::try_from(len).ok())
+        .ok_or_else(|| -> ImgiiError {
+            ImageError::Render {
+                reason: format!("a stacked image of {width}x{height} is too large to allocate"),
+            }
+            .into()
+        })?;
+    Ok(vec![0; len])
+}
+
+/// Pads an image with a color so its width and height are rounded up to a multiple of a value.
+/// The original image is placed at the top left of the padded image.
+///
+/// * `canvas`: The image to pad.
+/// * `multiple`: The multiple to round the dimensions up to.
+/// * `color`: The color of the padding.
+fn pad_to_multiple<P>(
+    canvas: ImageBuffer<P, Vec<P::Subpixel>>,
+    multiple: u32,
+    color: P,
+) -> ImageBuffer<P, Vec<P::Subpixel>>
+where
+    P: Pixel,
+{
+    let width = canvas.width().next_multiple_of(multiple);
+    let height = canvas.height().next_multiple_of(multiple);
+    if width == canvas.width() && height == canvas.height() {
+        // already the right size
+        return canvas;
+    }
+
+    let mut padded = ImageBuffer::from_pixel(width, height, color);
+    imageops::replace(&mut padded, &canvas, 0, 0);
+    padded
+}
+
+/// Darkens every nth row of an image, for a CRT scanline effect. Alpha is left untouched.
+///
+/// * `canvas`: The image to draw scanlines on.
+/// * `spacing`: The number of rows between each scanline.
+/// * `darkness`: How much to darken each scanline, where 255 is fully black.
+fn draw_scanlines<P>(canvas: &mut ImageBuffer<P, Vec<P::Subpixel>>, spacing: u32, darkness: u8)
+where
+    P: Pixel + Send + Sync,
+    P::Subpixel: Darken + Send + Sync,
+{
+    // only darken the color channels
+    let num_color_channels = usize::from(P::CHANNEL_COUNT).min(3);
+    enumerate_pixels_mut(canvas)
+        .filter(|(_, y, _)| y % spacing == 0)
+        .for_each(|(_, _, pixel)| {
+            for channel in pixel.channels_mut().iter_mut().take(num_color_channels) {
+                *channel = channel.darken(darkness);
+            }
+        });
+}
+
+/// Adds a glow over an image, for a bloom effect. The image is blurred and added back on top of
+/// itself, so bright pixels bleed light into their surroundings. Transparent pixels around glyphs
+/// take on the color of the glow, becoming partly opaque.
+///
+/// * `canvas`: The image to add the glow to.
+/// * `radius`: The standard deviation of the blur, in pixels. Must be positive.
+/// * `strength`: How much of the blurred image is added, where 1.0 adds all of it.
+fn draw_bloom<P>(canvas: &mut ImageBuffer<P, Vec<P::Subpixel>>, radius: f32, strength: f32)
+where
+    P: Pixel + Send + Sync,
+    P::Subpixel: Into<f32> + Clamp<f32> + Send + Sync,
+{
+    let max_value: f32 = P::Subpixel::DEFAULT_MAX_VALUE.into();
+    let num_color_channels = usize::from(P::CHANNEL_COUNT) - usize::from(P::HAS_ALPHA);
+    let alpha_of = |channels: &[P::Subpixel]| {
+        if P::HAS_ALPHA {
+            channels[num_color_channels].into() / max_value
+        } else {
+            1.0
+        }
+    };
+
+    // blur the premultiplied colors, so transparent pixels don't darken the glow
+    let mut premultiplied = canvas.clone();
+    if P::HAS_ALPHA {
+        for pixel in premultiplied.pixels_mut() {
+            let alpha = alpha_of(pixel.channels());
+            for channel in pixel.channels_mut().iter_mut().take(num_color_channels) {
+                *channel = Clamp::clamp((*channel).into() * alpha);
+            }
+        }
+    }
+    let glow = gaussian_blur_f32(&premultiplied, radius);
+
+    enumerate_pixels_mut(canvas).for_each(|(x, y, pixel)| {
+        let glow_channels = glow.get_pixel(x, y).channels();
+        let alpha = alpha_of(pixel.channels());
+        let new_alpha = (alpha + strength * alpha_of(glow_channels)).min(1.0);
+        if new_alpha <= 0.0 {
+            // no glow reaches this pixel
+            return;
+        }
+
+        let channels = pixel.channels_mut();
+        for (channel, glow_channel) in channels
+            .iter_mut()
+            .zip(glow_channels)
+            .take(num_color_channels)
+        {
+            let premultiplied = (*channel).into() * alpha + strength * (*glow_channel).into();
+            *channel = Clamp::clamp(premultiplied / new_alpha);
+        }
+        if P::HAS_ALPHA {
+            channels[num_color_channels] = Clamp::clamp(new_alpha * max_value);
+        }
+    });
+}
+
+/// A channel of a pixel that can be darkened, for drawing scanlines at any bit depth.
+pub(crate) trait Darken {
+    /// Darkens this channel, where a darkness of 255 is fully black.
+    ///
+    /// * `darkness`: How much to darken this channel.
+    fn darken(self, darkness: u8) -> Self;
+}
+
+impl Darken for u8 {
+    fn darken(self, darkness: u8) -> Self {
+        (u16::from(self) * u16::from(u8::MAX - darkness) / u16::from(u8::MAX)) as u8
+    }
+}
+
+impl Darken for u16 {
+    fn darken(self, darkness: u8) -> Self {
+        (u32::from(self) * u32::from(u8::MAX - darkness) / u32::from(u8::MAX)) as u16
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        conversion::converters::generic_converter::render_ascii_generic,
+        test_support::{ansi_cell, test_dir, test_options_builder},
+    };
+
+    /// Renders and stitches a small grid of colored glyphs.
+    ///
+    /// * `imgii_options`: The imgii options for rendering ASCII.
+    fn stitched_grid(imgii_options: &ImgiiOptions) -> InternalImage {
+        let ascii_text = [
+            [ansi_cell([255, 0, 0], '#'), ansi_cell([0, 255, 0], '@')].concat(),
+            [ansi_cell([0, 0, 255], ':'), ansi_cell([255, 255, 0], '.')].concat(),
+        ]
+        .join("\n");
+        let image = render_ascii_generic(imgii_options, ascii_text).unwrap();
+        AsciiImageWriter::from_2d_vec(image, imgii_options)
+            .unwrap()
+            .imagebuf
+            .as_buffer()
+            .clone()
+    }
+
+    #[test]
+    fn nearest_upscale_copies_each_pixel_into_a_block() {
+        let original = stitched_grid(&test_options_builder().build().unwrap());
+        let imgii_options = test_options_builder().scale_output(2.0).build().unwrap();
+        assert_eq!(imgii_options.resize_filter_output(), ResizeFilter::Nearest);
+        let upscaled = stitched_grid(&imgii_options);
+
+        assert_eq!(
+            upscaled.dimensions(),
+            (original.width() * 2, original.height() * 2)
+        );
+        for (x, y, pixel) in upscaled.enumerate_pixels() {
+            assert_eq!(pixel, original.get_pixel(x / 2, y / 2), "pixel ({x}, {y})");
+        }
+    }
+
+    #[test]
+    fn interrupted_atomic_writes_leave_no_partial_file() {
+        let dir = test_dir("interrupted_atomic_writes_leave_no_partial_file");
+        let imgii_options = test_options_builder().atomic_writes(true).build().unwrap();
+        // the write fails halfway through, like a crash or a full disk
+        let interrupted_write = |file_writer: &mut BufWriter<File>| {
+            file_writer.write_all(b"partial")?;
+            file_writer.flush()?;
+            Err::<(), _>(ImgiiError::Internal)
+        };
+
+        let new_file = dir.join("new.png");
+        assert!(
+            write_output_file(
+                new_file.to_str().unwrap(),
+                &imgii_options,
+                interrupted_write
+            )
+            .is_err()
+        );
+        assert!(!new_file.exists());
+
+        let existing_file = dir.join("existing.png");
+        std::fs::write(&existing_file, b"complete").unwrap();
+        assert!(
+            write_output_file(
+                existing_file.to_str().unwrap(),
+                &imgii_options,
+                interrupted_write
+            )
+            .is_err()
+        );
+        assert_eq!(std::fs::read(&existing_file).unwrap(), b"complete");
+
+        // and the temporary files are cleaned up
+        let mut files = std::fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect::<Vec<_>>();
+        files.sort();
+        assert_eq!(files, ["existing.png"]);
+    }
+
+    #[test]
+    fn subpixel_cells_leave_no_seams() {
+        // every cell has a background, so any seam between cells would show up as transparent
+        // pixels
+        let imgii_options = test_options_builder()
+            .background(true)
+            .match_source_resolution(true)
+            .subpixel_cells(true)
+            .build()
+            .unwrap();
+        // neither size is a multiple of the 2x2 grid
+        let imgii_options = imgii_options.for_source(101, 37);
+
+        let stitched = stitched_grid(&imgii_options);
+        assert_eq!(stitched.dimensions(), (101, 37));
+        for x in 0..stitched.width() {
+            assert!(
+                (0..stitched.height()).all(|y| stitched.get_pixel(x, y).0[3] > 0),
+                "column {x} has a seam"
+            );
+        }
+    }
+
+    #[test]
+    fn padding_keeps_its_color_over_a_continuous_background() {
+        let pad_color = Rgba([0, 255, 0, 64]);
+        let imgii_options = test_options_builder()
+            .background(true)
+            .background_color(Rgba([0, 0, 255, 128]))
+            .continuous_background(true)
+            .pad_to_multiple(7, pad_color)
+            .build()
+            .unwrap();
+        let (char_width, char_height) = calculate_char_dimensions(&imgii_options);
+        let (width, height) = (char_width * 2, char_height * 2);
+
+        let canvas = stitched_grid(&imgii_options);
+        assert_eq!(
+            canvas.dimensions(),
+            (width.next_multiple_of(7), height.next_multiple_of(7))
+        );
+        assert!(width % 7 != 0 || height % 7 != 0, "nothing was padded");
+        for (x, y, pixel) in canvas.enumerate_pixels() {
+            if x >= width || y >= height {
+                assert_eq!(*pixel, pad_color, "padding at ({x}, {y})");
+            }
+        }
+    }
+
+    #[test]
+    fn mixed_size_stacks_are_padded_with_transparency() {
+        const RED: Rgba<u8> = Rgba([255, 0, 0, 255]);
+        const GREEN: Rgba<u8> = Rgba([0, 255, 0, 255]);
+        const BLUE: Rgba<u8> = Rgba([0, 0, 255, 255]);
+        const CLEAR: Rgba<u8> = Rgba([0, 0, 0, 0]);
+        let images = [
+            InternalImage::from_pixel(2, 3, RED),
+            InternalImage::from_pixel(4, 1, GREEN),
+        ];
+
+        let stacked = stack_vertically(&images, Some((1, BLUE))).unwrap();
+        assert_eq!(stacked.dimensions(), (4, 5));
+        for (x, y, pixel) in stacked.enumerate_pixels() {
+            let expected = match (x, y) {
+                (0..2, 0..3) => RED,
+                (_, 0..3) => CLEAR,
+                (_, 3) => BLUE,
+                _ => GREEN,
+            };
+            assert_eq!(*pixel, expected, "vertical ({x}, {y})");
+        }
+
+        let stacked = stack_horizontally(&images, Some((1, BLUE))).unwrap();
+        assert_eq!(stacked.dimensions(), (7, 3));
+        for (x, y, pixel) in stacked.enumerate_pixels() {
+            let expected = match (x, y) {
+                (0..2, _) => RED,
+                (2, _) => BLUE,
+                (_, 0) => GREEN,
+                _ => CLEAR,
+            };
+            assert_eq!(*pixel, expected, "horizontal ({x}, {y})");
+        }
+    }
+
+    #[test]
+    fn empty_images_stack_without_panicking() {
+        let images = [
+            InternalImage::new(0, 2),
+            InternalImage::from_pixel(1, 1, Rgba([255, 0, 0, 255])),
+            InternalImage::new(3, 0),
+        ];
+
+        let stacked = stack_vertically(&images, None).unwrap();
+        assert_eq!(stacked.dimensions(), (3, 3));
+        assert_eq!(stacked.get_pixel(0, 2).0, [255, 0, 0, 255]);
+        let stacked = stack_horizontally(&images, None).unwrap();
+        assert_eq!(stacked.dimensions(), (4, 2));
+        assert_eq!(stacked.get_pixel(0, 0).0, [255, 0, 0, 255]);
+    }
+
+    #[test]
+    fn oversized_stacks_are_an_error() {
+        let images = [InternalImage::new(1, 1), InternalImage::new(1, 1)];
+        let separator = Some((u32::MAX, Rgba([0, 0, 0, 255])));
+
+        assert!(matches!(
+            stack_vertically(&images, separator),
+            Err(ImgiiError::Image(ImageError::Render { .. }))
+        ));
+        assert!(matches!(
+            stack_horizontally(&images, separator),
+            Err(ImgiiError::Image(ImageError::Render { .. }))
+        ));
+    }
+}
@@ -0,0 +1,75 @@
+//! Parallel iteration used while converting, backed by `rayon`. With the `wasm` feature, every
+//! parallel iterator falls back to a sequential one, so the global `rayon` thread pool is never
+//! touched.
+
+use image::{ImageBuffer, Pixel};
+
+#[cfg(not(feature = "wasm"))]
+pub(crate) use rayon::iter::{IntoParallelIterator, ParallelIterator};
+#[cfg(all(feature = "mmap", not(feature = "wasm")))]
+pub(crate) use rayon::{iter::IndexedParallelIterator, slice::ParallelSliceMut};
+
+/// Sequential stand-ins for the `rayon` traits, named the same so callers don't have to change.
+#[cfg(feature = "wasm")]
+mod sequential {
+    /// Stand-in for [`rayon::iter::IntoParallelIterator`], iterating sequentially.
+    pub(crate) trait IntoParallelIterator: IntoIterator + Sized {
+        /// Converts into a sequential iterator.
+        fn into_par_iter(self) -> Self::IntoIter {
+            self.into_iter()
+        }
+    }
+
+    impl<T: IntoIterator> IntoParallelIterator for T {}
+
+    /// Stand-in for [`rayon::slice::ParallelSliceMut`], iterating sequentially.
+    #[cfg(feature = "mmap")]
+    pub(crate) trait ParallelSliceMut<T> {
+        /// Iterates over mutable chunks of the slice sequentially.
+        fn par_chunks_mut(&mut self, chunk_size: usize) -> std::slice::ChunksMut<'_, T>;
+    }
+
+    #[cfg(feature = "mmap")]
+    impl<T> ParallelSliceMut<T> for [T] {
+        fn par_chunks_mut(&mut self, chunk_size: usize) -> std::slice::ChunksMut<'_, T> {
+            self.chunks_mut(chunk_size)
+        }
+    }
+
+    pub(crate) use std::iter::Iterator as ParallelIterator;
+}
+
+#[cfg(all(feature = "mmap", feature = "wasm"))]
+pub(crate) use sequential::ParallelSliceMut;
+#[cfg(feature = "wasm")]
+pub(crate) use sequential::{IntoParallelIterator, ParallelIterator};
+
+/// Iterates over every pixel of an image along with its coordinates, in parallel unless the
+/// `wasm` feature is enabled.
+///
+/// * `canvas`: The image to iterate over.
+#[cfg(not(feature = "wasm"))]
+pub(crate) fn enumerate_pixels_mut<P>(
+    canvas: &mut ImageBuffer<P, Vec<P::Subpixel>>,
+) -> impl ParallelIterator<Item = (u32, u32, &mut P)>
+where
+    P: Pixel + Send + Sync,
+    P::Subpixel: Send + Sync,
+{
+    canvas.par_enumerate_pixels_mut()
+}
+
+/// Iterates over every pixel of an image along with its coordinates, in parallel unless the
+/// `wasm` feature is enabled.
+///
+/// * `canvas`: The image to iterate over.
+#[cfg(feature = "wasm")]
+pub(crate) fn enumerate_pixels_mut<P>(
+    canvas: &mut ImageBuffer<P, Vec<P::Subpixel>>,
+) -> impl ParallelIterator<Item = (u32, u32, &mut P)>
+where
+    P: Pixel + Send + Sync,
+    P::Subpixel: Send + Sync,
+{
+    canvas.enumerate_pixels_mut()
+}
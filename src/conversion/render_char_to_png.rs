@@ -1,9 +1,15 @@
 //! Implementation for rendering a character as an image.
 
-use crate::{conversion::image_data::ImageData, options::ImgiiOptions};
-use ab_glyph::{FontRef, PxScale};
-use image::{ImageBuffer, Rgba};
-use imageproc::drawing::draw_text_mut;
+use crate::{
+    conversion::{image_data::ImageData, image_writer::canvas_background},
+    options::{ImgiiOptions, Jitter, SpaceHandling},
+};
+use ab_glyph::{Font, FontRef, PxScale};
+use image::{ImageBuffer, Rgba, imageops};
+use imageproc::{
+    drawing::draw_text_mut,
+    geometric_transformations::{Interpolation, Projection, warp},
+};
 
 /// Represents a colored string to write.
 /// All characters are contiguous and share the same color.
@@ -15,63 +21,397 @@ pub(crate) struct ColoredStr {
     pub(crate) blue: u8,
     pub(crate) green: u8,
     pub(crate) string: String,
+    /// The color the background of the cell is filled with as RGB, if any.
+    pub(crate) background: Option<[u8; 3]>,
 }
 
-const BACKGROUND_PIXEL: Rgba<u8> = Rgba([0, 0, 0, u8::MAX]);
+/// The character drawn in place of a character that the font draws nothing for.
+const FALLBACK_CHAR: char = '?';
+
+/// The offsets (x, y) to draw a glyph at for normal weight.
+const NORMAL_DRAW_OFFSETS: &[(i32, i32)] = &[(0, 0)];
+/// The offsets (x, y) to draw a glyph at to synthetically embolden it.
+const BOLD_DRAW_OFFSETS: &[(i32, i32)] = &[(0, 0), (1, 0)];
 
 /// Converts string data into a png.
-/// Uses `imageproc` to render text.
+/// Uses `imageproc` to render text, which draws each character as the glyph mapped to it by the
+/// font, with no shaping. Ligatures and contextual alternates are never applied.
 pub(crate) fn str_to_png(
     data: &ColoredStr,
     font: &FontRef<'_>,
     imgii_options: &ImgiiOptions,
 ) -> ImageData {
-    let font_size = imgii_options.font_size();
-    let (char_width, char_height) = calculate_char_dimensions(font_size);
     // create our image to work with
-    let mut image = if imgii_options.background() {
+    let mut image = create_cell(imgii_options, data.background);
+
+    if draws_nothing(font, &data.string) {
+        // the font has nothing to draw for this character, which would silently leave a hole in
+        // the image, so draw the fallback character instead
+        log::warn!(
+            "character {:?} has no glyph to draw, using {FALLBACK_CHAR:?} instead",
+            data.string
+        );
+        let fallback = ColoredStr {
+            string: String::from(FALLBACK_CHAR),
+            ..data.clone()
+        };
+        draw_glyph(&mut image, &fallback, font, imgii_options);
+    } else {
+        draw_glyph(&mut image, data, font, imgii_options);
+    }
+    if is_over_canvas_background(data, imgii_options) {
+        unpremultiply(&mut image);
+    }
+
+    ImageData::new(image)
+}
+
+/// Gets whether the font has nothing to draw for every character of a string, because it has no
+/// glyph for them or their glyphs have no outline. This is checked on the font rather than on the
+/// drawn cell, since a glyph drawn in the color of the background looks the same as no glyph.
+///
+/// * `font`: The font to draw with.
+/// * `string`: The string to draw.
+fn draws_nothing(font: &FontRef<'_>, string: &str) -> bool {
+    string.chars().all(|character| {
+        // glyph id 0 is the ".notdef" glyph, which fonts fall back to for characters they don't
+        // have
+        let glyph_id = font.glyph_id(character);
+        glyph_id.0 == 0 || font.outline(glyph_id).is_none()
+    })
+}
+
+/// Converts string data into a png, with the glyph slightly rotated and offset for a hand-drawn
+/// look. The rotation and offset are random, but are derived from the seed and cell position, so
+/// they are always the same for the same cell. The glyph is clipped to the bounds of the cell.
+///
+/// * `data`: The colored string to render.
+/// * `font`: The font to render with.
+/// * `imgii_options`: The imgii options for rendering ASCII.
+/// * `jitter`: How much to jitter the glyph.
+/// * `cell_position`: The (row, column) of this cell in the image.
+pub(crate) fn str_to_jittered_png(
+    data: &ColoredStr,
+    font: &FontRef<'_>,
+    imgii_options: &ImgiiOptions,
+    jitter: &Jitter,
+    cell_position: (usize, usize),
+) -> ImageData {
+    let (char_width, char_height) = calculate_char_dimensions(imgii_options);
+    // draw the glyph on its own layer, so the background isn't rotated with it
+    let mut glyph = ImageBuffer::new(char_width, char_height);
+    draw_glyph(&mut glyph, data, font, imgii_options);
+
+    // rotate about the center of the cell, then offset
+    let theta = jitter_value(jitter.seed(), cell_position, 0) * jitter.max_degrees().to_radians();
+    let max_offset = jitter.max_offset() as f32;
+    let offset_x = (jitter_value(jitter.seed(), cell_position, 1) * max_offset).round();
+    let offset_y = (jitter_value(jitter.seed(), cell_position, 2) * max_offset).round();
+    let (center_x, center_y) = (char_width as f32 / 2.0, char_height as f32 / 2.0);
+    let projection = Projection::translate(center_x + offset_x, center_y + offset_y)
+        * Projection::rotate(theta)
+        * Projection::translate(-center_x, -center_y);
+    // the warped image is the same size as the cell, which clips anything moved outside of it
+    let glyph = warp(
+        &glyph,
+        &projection,
+        Interpolation::Bilinear,
+        Rgba([0, 0, 0, 0]),
+    );
+
+    let mut image = create_cell(imgii_options, data.background);
+    imageops::overlay(&mut image, &glyph, 0, 0);
+    if is_over_canvas_background(data, imgii_options) {
+        unpremultiply(&mut image);
+    }
+    ImageData::new(image)
+}
+
+/// Creates an empty cell, filled with the cell background if it has one, otherwise with the
+/// background if it is drawn per cell.
+///
+/// * `imgii_options`: The imgii options for rendering ASCII.
+/// * `cell_background`: The color to fill this cell with as RGB, if any.
+pub(crate) fn create_cell(
+    imgii_options: &ImgiiOptions,
+    cell_background: Option<[u8; 3]>,
+) -> ImageBuffer<Rgba<u8>, Vec<u8>> {
+    let (char_width, char_height) = calculate_char_dimensions(imgii_options);
+    if let Some([red, green, blue]) = cell_background {
+        ImageBuffer::from_pixel(char_width, char_height, Rgba([red, green, blue, u8::MAX]))
+    } else if has_cell_background(imgii_options) {
         // create with background
-        ImageBuffer::from_pixel(char_width, char_height, BACKGROUND_PIXEL)
+        ImageBuffer::from_pixel(char_width, char_height, imgii_options.background_color())
     } else {
         ImageBuffer::new(char_width, char_height)
-    };
+    }
+}
+
+/// Draws the glyph for a colored string onto a cell.
+///
+/// * `image`: The cell to draw onto.
+/// * `data`: The colored string to draw.
+/// * `font`: The font to draw with.
+/// * `imgii_options`: The imgii options for rendering ASCII.
+fn draw_glyph(
+    image: &mut ImageBuffer<Rgba<u8>, Vec<u8>>,
+    data: &ColoredStr,
+    font: &FontRef<'_>,
+    imgii_options: &ImgiiOptions,
+) {
+    let font_size = imgii_options.font_size();
+    let glyph_size = font_size as f32 * emphasis_multiplier(data, imgii_options);
     let scale = PxScale {
-        x: font_size as f32,
-        y: font_size as f32,
+        x: glyph_size,
+        y: glyph_size,
     };
 
-    draw_text_mut(
-        &mut image,
-        Rgba([data.red, data.green, data.blue, u8::MAX]),
-        0,
-        0,
-        scale,
-        &font,
-        &data.string,
-    );
+    let draw_offsets = if imgii_options.bold() {
+        // drawing the glyph again, shifted over, thickens its strokes
+        BOLD_DRAW_OFFSETS
+    } else {
+        NORMAL_DRAW_OFFSETS
+    };
+    // center the glyph, for cells that are a different size than the glyph (like square cells or
+    // emphasized glyphs). Anything outside of the cell is clipped.
+    let glyph_size = glyph_size.round() as i32;
+    let center_offset_x = (image.width() as i32 - glyph_size / 2) / 2;
+    let center_offset_y = (image.height() as i32 - glyph_size) / 2;
+    if let Some(sdf_glyph_cache) = imgii_options.sdf_glyph_cache() {
+        let glyph = sdf_glyph_cache.glyph(font, imgii_options.font_hash(), &data.string);
+        for (x, y) in draw_offsets {
+            glyph.draw(
+                image,
+                Rgba([data.red, data.green, data.blue, u8::MAX]),
+                *x + center_offset_x,
+                *y + center_offset_y,
+                scale.x,
+            );
+        }
+        return;
+    }
 
-    ImageData::new(image)
+    for (x, y) in draw_offsets {
+        draw_text_mut(
+            image,
+            Rgba([data.red, data.green, data.blue, u8::MAX]),
+            *x + center_offset_x,
+            *y + center_offset_y,
+            scale,
+            &font,
+            &data.string,
+        );
+    }
 }
 
-/// Creates a transparent png in place of a character
+/// Gets the font size multiplier of a colored string, which is 1 unless it is emphasized.
+///
+/// * `data`: The colored string.
+/// * `imgii_options`: The imgii options, holding the emphasized characters.
+pub(crate) fn emphasis_multiplier(data: &ColoredStr, imgii_options: &ImgiiOptions) -> f32 {
+    let mut chars = data.string.chars();
+    match (chars.next(), chars.next()) {
+        // only single characters can be emphasized
+        (Some(character), None) => imgii_options
+            .emphasis()
+            .get(&character)
+            .copied()
+            .unwrap_or(1.0),
+        _ => 1.0,
+    }
+}
+
+/// Gets a pseudo-random value in the range [-1, 1] for a cell. Always returns the same value for
+/// the same inputs.
+///
+/// * `seed`: The seed for the random value.
+/// * `cell_position`: The (row, column) of the cell.
+/// * `salt`: Distinguishes multiple values for the same cell.
+fn jitter_value(seed: u64, (row, column): (usize, usize), salt: u64) -> f32 {
+    // mix everything together with splitmix64, which is plenty random for a visual effect
+    let mut z = seed
+        ^ (row as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15)
+        ^ (column as u64).wrapping_mul(0xC2B2_AE3D_27D4_EB4F)
+        ^ salt.wrapping_mul(0x1656_67B1_9E37_79F9);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^= z >> 31;
+
+    // use the top 24 bits, which fit exactly in an f32
+    ((z >> 40) as f32 / (1u64 << 24) as f32) * 2.0 - 1.0
+}
+
+/// Creates a transparent png in place of a character. How the png is filled depends on the
+/// space handling option.
 pub(crate) fn str_to_transparent_png(imgii_options: &ImgiiOptions) -> ImageData {
-    let (char_width, char_height) = calculate_char_dimensions(imgii_options.font_size());
-    let output = if imgii_options.background() {
-        // create image with background
-        ImageBuffer::from_pixel(char_width, char_height, BACKGROUND_PIXEL)
-    } else {
-        // empty image
-        ImageBuffer::new(char_width, char_height)
+    let (char_width, char_height) = calculate_char_dimensions(imgii_options);
+    let output = match imgii_options.space_handling() {
+        SpaceHandling::Background if has_cell_background(imgii_options) => {
+            // create image with background
+            ImageBuffer::from_pixel(char_width, char_height, imgii_options.background_color())
+        }
+        SpaceHandling::Background | SpaceHandling::Transparent => {
+            // empty image
+            ImageBuffer::new(char_width, char_height)
+        }
+        SpaceHandling::Custom(color) => ImageBuffer::from_pixel(char_width, char_height, color),
     };
 
     ImageData::new(output)
 }
 
+/// Gets whether the background is filled into each cell, rather than under the whole image once
+/// it's stitched together.
+///
+/// * `imgii_options`: The imgii options for rendering ASCII.
+fn has_cell_background(imgii_options: &ImgiiOptions) -> bool {
+    imgii_options.background() && canvas_background(imgii_options).is_none()
+}
+
+/// Gets whether a cell is drawn without a background, to be composited over the continuous
+/// background once it's stitched together.
+///
+/// * `data`: The colored string drawn in the cell.
+/// * `imgii_options`: The imgii options for rendering ASCII.
+fn is_over_canvas_background(data: &ColoredStr, imgii_options: &ImgiiOptions) -> bool {
+    data.background.is_none() && canvas_background(imgii_options).is_some()
+}
+
+/// Converts a glyph drawn onto a transparent cell to straight alpha. Glyphs are blended into the
+/// cell color and alpha alike, which leaves their anti-aliased edges premultiplied by their
+/// coverage, so they would darken when composited over a background.
+///
+/// * `image`: The cell to convert.
+fn unpremultiply(image: &mut ImageBuffer<Rgba<u8>, Vec<u8>>) {
+    for Rgba([red, green, blue, alpha]) in image.pixels_mut() {
+        if *alpha == 0 || *alpha == u8::MAX {
+            continue;
+        }
+        for channel in [red, green, blue] {
+            *channel = (u16::from(*channel) * u16::from(u8::MAX) / u16::from(*alpha))
+                .min(u16::from(u8::MAX)) as u8;
+        }
+    }
+}
+
 /// Calculates character dimensions are returns them
 ///
 /// # Returns
 /// (width, height) in a tuple
 #[inline]
-pub(crate) fn calculate_char_dimensions(font_size: u32) -> (u32, u32) {
-    (font_size / 2, font_size)
+pub(crate) fn calculate_char_dimensions(imgii_options: &ImgiiOptions) -> (u32, u32) {
+    let font_size = imgii_options.font_size();
+    if imgii_options.square_cells() {
+        (font_size, font_size)
+    } else {
+        // cells are half as wide as they are tall, which isn't a whole number for odd font sizes
+        let char_width = imgii_options
+            .cell_rounding()
+            .round(f64::from(font_size) / 2.0);
+        (char_width, font_size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{TEST_FONT, test_options_builder};
+
+    /// Creates a white colored string, with no cell background.
+    fn white(string: &str) -> ColoredStr {
+        ColoredStr {
+            red: u8::MAX,
+            green: u8::MAX,
+            blue: u8::MAX,
+            string: String::from(string),
+            background: None,
+        }
+    }
+
+    /// Sums the alpha of every pixel of a cell, which is how much of it the glyph covers.
+    fn coverage(image: &ImageData) -> u32 {
+        image
+            .as_buffer()
+            .pixels()
+            .map(|Rgba([_, _, _, alpha])| u32::from(*alpha))
+            .sum()
+    }
+
+    #[test]
+    fn bold_covers_more_of_the_cell() {
+        let font = FontRef::try_from_slice(TEST_FONT).unwrap();
+        let normal_options = test_options_builder().build().unwrap();
+        let bold_options = test_options_builder().bold(true).build().unwrap();
+
+        for string in ["l", "O", "#", "@"] {
+            let normal = coverage(&str_to_png(&white(string), &font, &normal_options));
+            let bold = coverage(&str_to_png(&white(string), &font, &bold_options));
+            assert!(
+                bold > normal,
+                "bold {string:?} covers {bold}, which isn't more than {normal}"
+            );
+        }
+    }
+
+    #[test]
+    fn glyphs_in_the_background_color_are_not_replaced() {
+        let font = FontRef::try_from_slice(TEST_FONT).unwrap();
+        let imgii_options = test_options_builder().build().unwrap();
+        let black_on_black = ColoredStr {
+            red: 0,
+            green: 0,
+            blue: 0,
+            string: String::from("#"),
+            background: Some([0, 0, 0]),
+        };
+
+        assert!(!draws_nothing(&font, &black_on_black.string));
+        // the glyph can hardly be seen, but it's still the glyph rather than the fallback
+        let fallback = ColoredStr {
+            string: String::from(FALLBACK_CHAR),
+            ..black_on_black.clone()
+        };
+        assert_ne!(
+            *str_to_png(&black_on_black, &font, &imgii_options).as_buffer(),
+            *str_to_png(&fallback, &font, &imgii_options).as_buffer()
+        );
+    }
+
+    #[test]
+    fn characters_without_a_glyph_draw_the_fallback() {
+        let font = FontRef::try_from_slice(TEST_FONT).unwrap();
+        let imgii_options = test_options_builder().build().unwrap();
+
+        // a private use character, which the font has no glyph for
+        let missing = white("\u{10FFFD}");
+        assert!(draws_nothing(&font, &missing.string));
+        assert_eq!(
+            *str_to_png(&missing, &font, &imgii_options).as_buffer(),
+            *str_to_png(&white(&String::from(FALLBACK_CHAR)), &font, &imgii_options).as_buffer()
+        );
+    }
+
+    #[test]
+    fn semi_transparent_backgrounds_keep_their_alpha() {
+        let font = FontRef::try_from_slice(TEST_FONT).unwrap();
+        let imgii_options = test_options_builder()
+            .background(true)
+            .background_color(Rgba([0, 0, 0, 128]))
+            .build()
+            .unwrap();
+
+        let space = str_to_transparent_png(&imgii_options);
+        assert!(space.as_buffer().pixels().all(|pixel| pixel.0[3] == 128));
+
+        // the glyph is blended over the background, which still shows around it
+        let glyph = str_to_png(&white("#"), &font, &imgii_options);
+        let alphas = glyph
+            .as_buffer()
+            .pixels()
+            .map(|pixel| pixel.0[3])
+            .collect::<Vec<_>>();
+        assert_eq!(alphas[0], 128);
+        assert!(alphas.iter().all(|alpha| *alpha >= 128));
+        assert!(alphas.iter().any(|alpha| *alpha > 128));
+    }
 }
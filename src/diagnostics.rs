@@ -0,0 +1,58 @@
+//! Contains diagnostics describing how the size of a converted image is worked out.
+
+use std::fmt::Display;
+
+/// Describes each step of working out the size of a converted image, from the source image to the
+/// final output image. Useful for finding out why an output image isn't the expected size.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConversionInfo {
+    /// The (width, height) of the source image, in pixels.
+    pub source_dimensions: (u32, u32),
+    /// The (width, height) of the ASCII grid that the source image is converted to, in cells.
+    pub grid_dimensions: (u32, u32),
+    /// The (width, height) of every cell, in pixels.
+    pub cell_dimensions: (u32, u32),
+    /// The (width, height) of the rendered image before post-processing, in pixels.
+    pub canvas_dimensions: (u32, u32),
+    /// The (width, height) of the final output image after scaling and padding, in pixels.
+    pub output_dimensions: (u32, u32),
+    /// The font size glyphs are rendered at.
+    pub font_size: u32,
+    /// Whether each cell is square, rather than twice as tall as it is wide.
+    pub square_cells: bool,
+    /// The characters the image is drawn with, from transparent to opaque. If there is a character
+    /// override, this is the override instead.
+    pub charset: Vec<String>,
+}
+
+impl Display for ConversionInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (source_width, source_height) = self.source_dimensions;
+        let (grid_width, grid_height) = self.grid_dimensions;
+        let (cell_width, cell_height) = self.cell_dimensions;
+        let (canvas_width, canvas_height) = self.canvas_dimensions;
+        let (output_width, output_height) = self.output_dimensions;
+        let cell_shape = if self.square_cells {
+            "square cells"
+        } else {
+            "half as wide as tall"
+        };
+
+        writeln!(f, "source image:  {source_width}x{source_height} pixels")?;
+        writeln!(f, "ASCII grid:    {grid_width}x{grid_height} cells")?;
+        writeln!(
+            f,
+            "cell size:     {cell_width}x{cell_height} pixels (font size {}, {cell_shape})",
+            self.font_size
+        )?;
+        writeln!(
+            f,
+            "canvas:        {canvas_width}x{canvas_height} pixels (grid x cell size)"
+        )?;
+        writeln!(
+            f,
+            "output image:  {output_width}x{output_height} pixels (after scaling and padding)"
+        )?;
+        write!(f, "charset:       {:?}", self.charset)
+    }
+}
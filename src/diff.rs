@@ -0,0 +1,143 @@
+//! Contains a visual diff of two rendered images, for checking how much a change affected them.
+
+use std::sync::atomic::{AtomicU8, AtomicU64, Ordering};
+
+use image::{ImageBuffer, Rgba};
+
+use crate::{
+    conversion::{
+        image_data::{ImageData, InternalImage},
+        parallel::{ParallelIterator, enumerate_pixels_mut},
+    },
+    error::{ImageError, ImgiiError},
+};
+
+/// The color of pixels that differ between the two images in a diff image.
+const CHANGED_COLOR: Rgba<u8> = Rgba([255, 0, 0, 255]);
+
+/// How much the brightness of unchanged pixels is dimmed in a diff image, so changed pixels stand
+/// out.
+const UNCHANGED_DIM: u8 = 4;
+
+/// Statistics about how two images differ.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DiffStats {
+    /// The number of pixels with any channel (including alpha) that differs.
+    differing_pixels: u64,
+    /// The largest difference of any channel of any pixel.
+    max_channel_delta: u8,
+}
+
+impl DiffStats {
+    /// Gets the number of pixels with any channel (including alpha) that differs.
+    #[must_use]
+    pub fn differing_pixels(&self) -> u64 {
+        self.differing_pixels
+    }
+
+    /// Gets the largest difference of any channel of any pixel, or 0 if the images are the same.
+    #[must_use]
+    pub fn max_channel_delta(&self) -> u8 {
+        self.max_channel_delta
+    }
+
+    /// Gets whether the images are exactly the same.
+    #[must_use]
+    pub fn is_identical(&self) -> bool {
+        self.differing_pixels == 0
+    }
+}
+
+/// Compares two images of the same size, such as two renders of the same input with different
+/// options, building a diff image and statistics about how they differ. Useful for checking that a
+/// change to a preset only affected a few pixels.
+///
+/// In the diff image, differing pixels are drawn in red, and every other pixel is drawn as a dimmed
+/// grayscale version of the first image.
+///
+/// * `a`: The first image.
+/// * `b`: The second image.
+///
+/// # Returns
+/// * The [`DiffStats`] and the diff image upon success, `Err` if the images aren't the same size.
+pub fn diff_images(a: &ImageData, b: &ImageData) -> Result<(DiffStats, ImageData), ImgiiError> {
+    let (a, b) = (a.as_buffer(), b.as_buffer());
+    if a.dimensions() != b.dimensions() {
+        return Err(ImageError::InvalidParameter {
+            parameter_name: String::from("b"),
+        }
+        .into());
+    }
+
+    let differing_pixels = AtomicU64::new(0);
+    let max_channel_delta = AtomicU8::new(0);
+    let mut canvas: InternalImage = ImageBuffer::new(a.width(), a.height());
+    enumerate_pixels_mut(&mut canvas).for_each(|(x, y, pixel)| {
+        let (a_pixel, b_pixel) = (a.get_pixel(x, y), b.get_pixel(x, y));
+        let delta = a_pixel
+            .0
+            .iter()
+            .zip(b_pixel.0)
+            .map(|(a_channel, b_channel)| a_channel.abs_diff(b_channel))
+            .max()
+            .unwrap_or(0);
+
+        *pixel = if delta > 0 {
+            differing_pixels.fetch_add(1, Ordering::Relaxed);
+            max_channel_delta.fetch_max(delta, Ordering::Relaxed);
+            CHANGED_COLOR
+        } else {
+            let [red, green, blue, alpha] = a_pixel.0;
+            let luma = (u16::from(red) * 3 + u16::from(green) * 6 + u16::from(blue)) / 10;
+            let dimmed = (luma / u16::from(UNCHANGED_DIM)) as u8;
+            Rgba([dimmed, dimmed, dimmed, alpha])
+        };
+    });
+
+    Ok((
+        DiffStats {
+            differing_pixels: differing_pixels.into_inner(),
+            max_channel_delta: max_channel_delta.into_inner(),
+        },
+        ImageData::new(canvas),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_the_differing_pixels() {
+        let a = InternalImage::from_pixel(4, 3, Rgba([100, 100, 100, 255]));
+        let mut b = a.clone();
+        b.put_pixel(0, 0, Rgba([110, 100, 100, 255]));
+        b.put_pixel(3, 2, Rgba([100, 100, 100, 200]));
+
+        let (stats, diff) = diff_images(&ImageData::new(a), &ImageData::new(b)).unwrap();
+        assert_eq!(stats.differing_pixels(), 2);
+        assert_eq!(stats.max_channel_delta(), 55);
+        assert!(!stats.is_identical());
+        let diff = diff.as_buffer();
+        assert_eq!(*diff.get_pixel(0, 0), CHANGED_COLOR);
+        assert_eq!(*diff.get_pixel(3, 2), CHANGED_COLOR);
+        assert_ne!(*diff.get_pixel(1, 1), CHANGED_COLOR);
+    }
+
+    #[test]
+    fn identical_images_have_no_differences() {
+        let a = ImageData::new(InternalImage::from_pixel(4, 3, Rgba([1, 2, 3, 4])));
+
+        let (stats, _) = diff_images(&a, &a).unwrap();
+        assert_eq!(stats, DiffStats::default());
+        assert!(stats.is_identical());
+    }
+
+    #[test]
+    fn mismatched_sizes_are_an_error() {
+        let a = ImageData::new(InternalImage::new(4, 3));
+        let b = ImageData::new(InternalImage::new(3, 4));
+
+        assert!(diff_images(&a, &b).is_err());
+    }
+}
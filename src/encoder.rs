@@ -0,0 +1,18 @@
+//! Contains custom encoders, for writing output images in formats imgii doesn't support natively.
+
+use std::{fmt::Debug, io::Write, sync::Arc};
+
+use crate::{conversion::image_data::ImageData, error::ImgiiError};
+
+/// Encodes a rendered image to a writer, such as the output file.
+pub type EncodeFn = dyn Fn(&ImageData, &mut dyn Write) -> Result<(), ImgiiError> + Send + Sync;
+
+/// A custom encoder held by the options, which can't be debug printed.
+#[derive(Clone)]
+pub(crate) struct CustomEncoder(pub(crate) Arc<EncodeFn>);
+
+impl Debug for CustomEncoder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("CustomEncoder")
+    }
+}
@@ -22,6 +22,9 @@ pub enum ImgiiError {
     /// Invalid argument error.
     #[error("invalid argument(s) provided")]
     InvalidArgument,
+    /// The conversion was cancelled with the cancel flag of the options.
+    #[error("the conversion was cancelled")]
+    Cancelled,
     /// Unknown, unspecified internal error.
     #[error("an internal error has occurred")]
     Internal,
@@ -31,6 +34,23 @@ pub enum ImgiiError {
 pub enum FontError {
     #[error("could not load font {font_name}")]
     FontLoad { font_name: String },
+    /// No installed font could be found for the requested font family.
+    #[error("could not find an installed monospace font in family {family}")]
+    FamilyNotFound {
+        /// The requested font family.
+        family: String,
+    },
+    /// No installed monospace fonts could be found.
+    #[error("there are no installed monospace fonts that could be found")]
+    NoneInstalled,
+    /// The font is missing a glyph that is required for rendering.
+    #[error("font {font_name} has no usable glyph for character {character:?}")]
+    MissingGlyph {
+        /// The name of the font.
+        font_name: String,
+        /// The character that has no glyph.
+        character: char,
+    },
 }
 
 /// ASCII text parsing error. Use this when parsing ASCII text and something goes wrong.
@@ -54,6 +74,22 @@ pub enum ParseError {
         /// The `std::num::ParseIntError` that was emitted upon failure to parse.
         err: std::num::ParseIntError,
     },
+    /// A line of the ASCII text is longer than the options allow.
+    #[error("row {row} is longer than the maximum of {max_line_chars} characters")]
+    LineTooLong {
+        /// The index of the row, starting from 0 as the top row.
+        row: usize,
+        /// The maximum number of characters in a line.
+        max_line_chars: usize,
+    },
+    /// Characters of the ASCII text weren't matched while parsing, so they would have been dropped.
+    #[error("row {row} has characters that could not be parsed ({dropped:?})")]
+    DroppedCharacters {
+        /// The index of the row, starting from 0 as the top row.
+        row: usize,
+        /// The characters that could not be parsed.
+        dropped: String,
+    },
 }
 
 /// Represents an error while creating an image.
@@ -68,6 +104,26 @@ pub enum ImageError {
         /// The row number of the image where this occurred.
         image_row_number: usize,
     },
+    /// The input file isn't in an image format that imgii can read.
+    #[error("input file {file_name} is not in a supported image format")]
+    UnsupportedInputType {
+        /// The name of the input file.
+        file_name: String,
+    },
+    /// The output file has an extension that imgii can't output.
+    #[error("output file {file_name} has an unsupported file extension")]
+    UnsupportedOutputType {
+        /// The name of the output file.
+        file_name: String,
+    },
+    /// Rendering the image would need more memory than the memory budget allows.
+    #[error("rendering needs {required} bytes, which exceeds the memory budget of {budget} bytes")]
+    ExceedsMemoryBudget {
+        /// The estimated number of bytes needed to render the image.
+        required: u64,
+        /// The memory budget, in bytes.
+        budget: u64,
+    },
     #[error("rendering failed because {reason}")]
     Render {
         /// The reason for the render error. Since this error is intended to handle various internals
@@ -76,6 +132,58 @@ pub enum ImageError {
     },
 }
 
+/// A broad category of an [`ImgiiError`], for mapping errors to responses (such as HTTP status
+/// codes) without matching on every variant.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorCategory {
+    /// The input was invalid, such as an undecodable image or invalid options (e.g. HTTP 400).
+    BadInput,
+    /// A file could not be found (e.g. HTTP 404).
+    NotFound,
+    /// Something went wrong inside imgii or the system it's running on (e.g. HTTP 500).
+    Internal,
+    /// The conversion was cancelled by the caller (e.g. HTTP 499).
+    Cancelled,
+}
+
+impl ImgiiError {
+    /// Gets the broad category of this error.
+    #[must_use]
+    pub fn category(&self) -> ErrorCategory {
+        match self {
+            ImgiiError::Font(_) | ImgiiError::Parse(_) | ImgiiError::InvalidArgument => {
+                ErrorCategory::BadInput
+            }
+            ImgiiError::Image(ImageError::Render { .. }) => ErrorCategory::Internal,
+            ImgiiError::Image(_) => ErrorCategory::BadInput,
+            ImgiiError::Io(err) => io_error_category(err),
+            ImgiiError::Other(err) => match err.downcast_ref::<image::ImageError>() {
+                // most other errors are passed up from the image crate
+                Some(image::ImageError::Decoding(_))
+                | Some(image::ImageError::Unsupported(_))
+                | Some(image::ImageError::Limits(_)) => ErrorCategory::BadInput,
+                Some(image::ImageError::IoError(err)) => io_error_category(err),
+                _ => ErrorCategory::Internal,
+            },
+            ImgiiError::Internal => ErrorCategory::Internal,
+            ImgiiError::Cancelled => ErrorCategory::Cancelled,
+        }
+    }
+}
+
+/// Gets the broad category of an I/O error.
+///
+/// * `err`: The I/O error.
+fn io_error_category(err: &std::io::Error) -> ErrorCategory {
+    match err.kind() {
+        std::io::ErrorKind::NotFound => ErrorCategory::NotFound,
+        std::io::ErrorKind::InvalidInput
+        | std::io::ErrorKind::InvalidData
+        | std::io::ErrorKind::UnexpectedEof => ErrorCategory::BadInput,
+        _ => ErrorCategory::Internal,
+    }
+}
+
 /*
  * NOTE: Implement any `From` traits here.
  */
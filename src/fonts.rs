@@ -1,8 +1,16 @@
 //! Contains helper functionality for handling fonts.
 
+use ab_glyph::{Font, FontRef};
+#[cfg(feature = "system-fonts")]
 use font_loader::system_fonts;
 
+use crate::error::FontError;
+
+/// Characters that a font must have glyphs for to be usable when rendering.
+const REQUIRED_GLYPHS: &[char] = &['M', ' '];
+
 /// Lists all of the fonts that are installed on the system.
+#[cfg(feature = "system-fonts")]
 pub fn list_fonts() -> Vec<String> {
     let mut property = system_fonts::FontPropertyBuilder::new().monospace().build();
     system_fonts::query_specific(&mut property)
@@ -14,6 +22,7 @@ pub fn list_fonts() -> Vec<String> {
 ///
 /// # Returns
 /// `Option<(Vec<u8>, i32)>` containing the bytes of the font, followed by the index of the font.
+#[cfg(feature = "system-fonts")]
 pub fn load_monospace_font(font_name: &str) -> Option<(Vec<u8>, i32)> {
     let property = system_fonts::FontPropertyBuilder::new()
         .monospace()
@@ -21,3 +30,34 @@ pub fn load_monospace_font(font_name: &str) -> Option<(Vec<u8>, i32)> {
         .build();
     system_fonts::get(&property)
 }
+
+/// Validates that the font can be loaded and has usable glyphs for basic characters. A font
+/// that fails this check would otherwise render blank cells everywhere.
+///
+/// * `font`: The loaded font bytes.
+/// * `font_name`: The name of the font, used for error reporting.
+pub fn validate_font(font: &[u8], font_name: &str) -> Result<(), FontError> {
+    let font = FontRef::try_from_slice(font).map_err(|_| FontError::FontLoad {
+        font_name: String::from(font_name),
+    })?;
+    validate_font_glyphs(&font, font_name)
+}
+
+/// Checks that an already loaded font has glyphs for every required character.
+///
+/// * `font`: The loaded font.
+/// * `font_name`: The name of the font, used for error reporting.
+pub(crate) fn validate_font_glyphs(font: &FontRef<'_>, font_name: &str) -> Result<(), FontError> {
+    // glyph id 0 is always the ".notdef" glyph, which is what fonts fall back to for characters
+    // they don't have
+    match REQUIRED_GLYPHS
+        .iter()
+        .find(|character| font.glyph_id(**character).0 == 0)
+    {
+        Some(character) => Err(FontError::MissingGlyph {
+            font_name: String::from(font_name),
+            character: *character,
+        }),
+        None => Ok(()),
+    }
+}
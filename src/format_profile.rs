@@ -0,0 +1,140 @@
+//! Contains the default options for each output format, which are applied under the options
+//! chosen by the user.
+
+use crate::{
+    image_types::OutputImageType,
+    options::{ImgiiOptionsBuilder, PngCompression},
+};
+
+/// The number of rendered frames that may wait to be encoded when a GIF's rendering and encoding
+/// overlap, in the GIF profile.
+const GIF_PROFILE_PIPELINE_DEPTH: usize = 2;
+
+/// The default options for an output format, holding the options to apply to an
+/// [`ImgiiOptionsBuilder`] that suit the format. Every option is optional, so a profile only
+/// changes the options it sets, and only sets options that differ from the builder's defaults.
+///
+/// Each field is named the same as its builder method (and its CLI flag, with dashes instead of
+/// underscores).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FormatProfile {
+    /// The number of rendered GIF frames that may wait to be encoded, overlapping rendering and
+    /// encoding.
+    pub gif_pipeline_depth: Option<usize>,
+    /// How hard an output PNG is compressed.
+    pub png_compression: Option<PngCompression>,
+}
+
+impl FormatProfile {
+    /// Gets the default options for an output format. These are the options that the CLI and
+    /// [`crate::convert`] apply for the format, so they can be looked at or copied.
+    ///
+    /// * `output_type`: The output format.
+    #[must_use]
+    pub fn for_output_type(output_type: OutputImageType) -> Self {
+        match output_type {
+            // GIFs are slow to encode, so render the next frames while encoding
+            // NOTE: there's no frame deduplication to turn on here, since imgii has no option for
+            // it. Frames are kept one to one with the source GIF so their delays stay exact.
+            OutputImageType::Gif => Self {
+                gif_pipeline_depth: Some(GIF_PROFILE_PIPELINE_DEPTH),
+                ..Self::default()
+            },
+            // ASCII art has large areas of flat color, which compress well
+            OutputImageType::Png => Self {
+                png_compression: Some(PngCompression::Best),
+                ..Self::default()
+            },
+            // nothing to tune for raw pixels or ASCII text
+            OutputImageType::Raw | OutputImageType::Ans => Self::default(),
+        }
+    }
+
+    /// Applies every option set in this profile to a builder.
+    ///
+    /// * `builder`: The builder to apply the profile to.
+    #[must_use]
+    pub fn apply<'a>(&self, builder: ImgiiOptionsBuilder<'a>) -> ImgiiOptionsBuilder<'a> {
+        self.apply_except(builder, |_| false)
+    }
+
+    /// Applies the options set in this profile to a builder, skipping any options that have
+    /// already been chosen by the user. Useful for letting CLI flags and presets win over the
+    /// profile.
+    ///
+    /// * `builder`: The builder to apply the profile to.
+    /// * `is_overridden`: Returns true for the name of each option that should be skipped.
+    #[must_use]
+    pub fn apply_except<'a>(
+        &self,
+        mut builder: ImgiiOptionsBuilder<'a>,
+        is_overridden: impl Fn(&str) -> bool,
+    ) -> ImgiiOptionsBuilder<'a> {
+        // only use the options set in the profile that haven't been overridden
+        let keep = |name: &str| !is_overridden(name);
+
+        if let Some(gif_pipeline_depth) = self
+            .gif_pipeline_depth
+            .filter(|_| keep("gif_pipeline_depth"))
+        {
+            builder = builder.gif_pipeline_depth(gif_pipeline_depth);
+        }
+        if let Some(png_compression) = self.png_compression.filter(|_| keep("png_compression")) {
+            builder = builder.png_compression(png_compression);
+        }
+
+        builder
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::test_options_builder;
+
+    #[test]
+    fn overridden_options_are_not_applied() {
+        let is_overridden = |name: &str| name == "gif_pipeline_depth" || name == "png_compression";
+
+        let imgii_options = FormatProfile::for_output_type(OutputImageType::Gif)
+            .apply_except(test_options_builder().gif_pipeline_depth(5), is_overridden)
+            .build()
+            .unwrap();
+        assert_eq!(imgii_options.gif_pipeline_depth(), Some(5));
+        let imgii_options = FormatProfile::for_output_type(OutputImageType::Png)
+            .apply_except(
+                test_options_builder().png_compression(PngCompression::Fast),
+                is_overridden,
+            )
+            .build()
+            .unwrap();
+        assert_eq!(imgii_options.png_compression(), PngCompression::Fast);
+
+        // the same options are applied when they haven't been chosen
+        let imgii_options = FormatProfile::for_output_type(OutputImageType::Png)
+            .apply_except(
+                test_options_builder().png_compression(PngCompression::Fast),
+                |_| false,
+            )
+            .build()
+            .unwrap();
+        assert_eq!(imgii_options.png_compression(), PngCompression::Best);
+    }
+
+    #[test]
+    fn profiles_change_the_default_options() {
+        let defaults = test_options_builder().build().unwrap().to_string();
+
+        for output_type in [OutputImageType::Gif, OutputImageType::Png] {
+            let profile = FormatProfile::for_output_type(output_type);
+            let imgii_options = profile.apply(test_options_builder()).build().unwrap();
+            assert_ne!(imgii_options.to_string(), defaults, "{output_type:?}");
+        }
+        for output_type in [OutputImageType::Raw, OutputImageType::Ans] {
+            assert_eq!(
+                FormatProfile::for_output_type(output_type),
+                FormatProfile::default()
+            );
+        }
+    }
+}
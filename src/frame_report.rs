@@ -0,0 +1,84 @@
+//! Contains a report of which frames of a GIF failed to convert, and why.
+
+use crate::error::ImgiiError;
+
+/// A frame of a GIF that failed to convert.
+#[derive(Debug)]
+pub struct FrameFailure {
+    /// The index of the frame in the input GIF.
+    pub(crate) index: usize,
+    /// Why the frame failed to convert.
+    pub(crate) error: ImgiiError,
+}
+
+impl FrameFailure {
+    /// Creates a new [`FrameFailure`].
+    ///
+    /// * `index`: The index of the frame in the input GIF.
+    /// * `error`: Why the frame failed to convert.
+    pub(crate) fn new(index: usize, error: ImgiiError) -> Self {
+        Self { index, error }
+    }
+
+    /// Gets the index of the frame in the input GIF, starting from 0.
+    #[must_use]
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// Gets why the frame failed to convert.
+    #[must_use]
+    pub fn error(&self) -> &ImgiiError {
+        &self.error
+    }
+}
+
+/// A summary of a best-effort GIF conversion, where frames that fail to convert are skipped.
+#[derive(Debug, Default)]
+pub struct GifConversionReport {
+    /// The number of frames that were converted. Frames dropped by `max_frames` or
+    /// `target_duration_ms` aren't counted.
+    pub(crate) frames_total: usize,
+    /// The number of frames that made it into the output GIF.
+    pub(crate) frames_rendered: usize,
+    /// The frames that failed to convert, in order.
+    pub(crate) failures: Vec<FrameFailure>,
+}
+
+impl GifConversionReport {
+    /// Adds the frames of another report to this one, such as the report of the next batch of
+    /// frames.
+    ///
+    /// * `other`: The report to add.
+    #[cfg(not(feature = "wasm"))]
+    pub(crate) fn merge(&mut self, other: GifConversionReport) {
+        self.frames_total += other.frames_total;
+        self.frames_rendered += other.frames_rendered;
+        self.failures.extend(other.failures);
+    }
+
+    /// Gets the number of frames that were converted. Frames dropped by `max_frames` or
+    /// `target_duration_ms` before converting aren't counted.
+    #[must_use]
+    pub fn frames_total(&self) -> usize {
+        self.frames_total
+    }
+
+    /// Gets the number of frames that made it into the output GIF.
+    #[must_use]
+    pub fn frames_rendered(&self) -> usize {
+        self.frames_rendered
+    }
+
+    /// Gets the frames that failed to convert, in order.
+    #[must_use]
+    pub fn failures(&self) -> &[FrameFailure] {
+        &self.failures
+    }
+
+    /// Gets whether every frame was converted.
+    #[must_use]
+    pub fn is_complete(&self) -> bool {
+        self.failures.is_empty()
+    }
+}
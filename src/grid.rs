@@ -0,0 +1,550 @@
+//! Parses colored ASCII text into a grid of cells, without rendering anything, so the grid can be
+//! used where rendering happens elsewhere (such as a canvas or WebGL).
+
+use regex::{Captures, Regex};
+
+use crate::{
+    error::{ImageError, ImgiiError, ParseError},
+    options::ZeroWidthHandling,
+};
+
+/// The zero-width characters that draw nothing at all, so cells holding only one of them are
+/// blank.
+const INVISIBLE_CHARS: &[char] = &[
+    '\u{200B}', // zero-width space
+    '\u{200C}', // zero-width non-joiner
+    '\u{200D}', // zero-width joiner
+    '\u{2060}', // word joiner
+    '\u{FEFF}', // zero-width no-break space
+];
+
+/// A single character of colored ASCII text.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Cell {
+    /// The character in this cell.
+    pub character: char,
+    /// The zero-width characters combined into this cell, such as combining accents, drawn
+    /// together with the character. Empty for most cells.
+    pub marks: String,
+    /// The red value of the character's color.
+    pub red: u8,
+    /// The green value of the character's color.
+    pub green: u8,
+    /// The blue value of the character's color.
+    pub blue: u8,
+    /// The color of the background of this cell as RGB, if it has one.
+    pub background: Option<[u8; 3]>,
+}
+
+impl Cell {
+    /// Creates an empty [`Cell`], holding a black space.
+    #[must_use]
+    pub fn blank() -> Self {
+        Self {
+            character: ' ',
+            marks: String::new(),
+            red: 0,
+            green: 0,
+            blue: 0,
+            background: None,
+        }
+    }
+
+    /// Gets whether this cell has nothing to draw, such as whitespace, a control character or an
+    /// invisible zero-width character (like a zero-width space).
+    #[must_use]
+    pub fn is_blank(&self) -> bool {
+        self.character.is_whitespace()
+            || self.character.is_control()
+            || INVISIBLE_CHARS.contains(&self.character)
+    }
+
+    /// Gets the string drawn in this cell, which is the character followed by any marks combined
+    /// into it. Invisible marks are left out, since fonts often draw a placeholder box for them.
+    #[must_use]
+    pub fn grapheme(&self) -> String {
+        std::iter::once(self.character)
+            .chain(
+                self.marks
+                    .chars()
+                    .filter(|mark| !INVISIBLE_CHARS.contains(mark)),
+            )
+            .collect()
+    }
+}
+
+/// A grid of cells parsed from colored ASCII text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CellGrid {
+    /// Every cell of the grid, row by row starting from the top left cell.
+    pub cells: Vec<Cell>,
+    /// The width of the grid, in cells.
+    pub width: usize,
+    /// The height of the grid, in cells.
+    pub height: usize,
+}
+
+#[cfg(feature = "serde")]
+impl CellGrid {
+    /// Serializes this grid as JSON, for renderers that draw the cells themselves (such as a
+    /// canvas or WebGL). The root holds the `width` and `height` of the grid and its `rows`,
+    /// starting from the top. Each cell holds its `char` (along with any marks combined into it)
+    /// and its color as `rgb`, along with its `background` as RGB if it has one.
+    ///
+    /// # Returns
+    /// * The JSON text, or `Err` if it can't be serialized.
+    pub fn to_json(&self) -> Result<String, ImgiiError> {
+        let rows = self
+            .cells
+            .chunks(self.width.max(1))
+            .map(|row| row.iter().map(JsonCell::from).collect())
+            .collect();
+        serde_json::to_string(&JsonGrid {
+            width: self.width,
+            height: self.height,
+            rows,
+        })
+        .map_err(|err| -> ImgiiError { anyhow::Error::new(err).into() })
+    }
+}
+
+/// A [`CellGrid`] laid out as rows, as it's written to JSON.
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize)]
+struct JsonGrid {
+    width: usize,
+    height: usize,
+    rows: Vec<Vec<JsonCell>>,
+}
+
+/// A [`Cell`] as it's written to JSON.
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize)]
+struct JsonCell {
+    #[serde(rename = "char")]
+    character: String,
+    rgb: [u8; 3],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    background: Option<[u8; 3]>,
+}
+
+#[cfg(feature = "serde")]
+impl From<&Cell> for JsonCell {
+    fn from(cell: &Cell) -> Self {
+        Self {
+            character: format!("{}{}", cell.character, cell.marks),
+            rgb: [cell.red, cell.green, cell.blue],
+            background: cell.background,
+        }
+    }
+}
+
+/// The 16 standard colors of the 256-color palette, as (red, green, blue). These match xterm.
+const STANDARD_COLORS: [(u8, u8, u8); 16] = [
+    (0, 0, 0),
+    (205, 0, 0),
+    (0, 205, 0),
+    (205, 205, 0),
+    (0, 0, 238),
+    (205, 0, 205),
+    (0, 205, 205),
+    (229, 229, 229),
+    (127, 127, 127),
+    (255, 0, 0),
+    (0, 255, 0),
+    (255, 255, 0),
+    (92, 92, 255),
+    (255, 0, 255),
+    (0, 255, 255),
+    (255, 255, 255),
+];
+
+/// Creates the regex for finding each colored character in a row of ASCII text. Tabs are also
+/// matched when they aren't colored, so they can be expanded.
+///
+/// Characters can be colored with either a 24-bit color (`\x1b[38;2;<R>;<G>;<B>m`) or a color
+/// from the 256-color palette (`\x1b[38;5;<index>m`), which some converters use for grayscale
+/// images. The color can be preceded by a 24-bit background color (`\x1b[48;2;<R>;<G>;<B>m`).
+///
+/// Zero-width characters (combining marks and invisible format characters) right after a colored
+/// character are matched along with it, as its marks. A colored zero-width character is matched
+/// on its own, so it can be combined into the cell before it.
+pub(crate) fn cell_regex() -> Result<Regex, ImgiiError> {
+    // we assume that there's only one character (and its marks) for each color
+    // NOTE: \u{1b} represents the \033 character
+    let pattern_str = concat!(
+        r"(?:",
+        '\u{1b}',
+        r"\[48;2;(?<bg_red>[0-9]+);(?<bg_green>[0-9]+);(?<bg_blue>[0-9]+)m)?",
+        '\u{1b}',
+        r"\[38;(?:2;(?<red>[0-9]+);(?<green>[0-9]+);(?<blue>[0-9]+)|5;(?<index>[0-9]+))m",
+        r"(?:(?<zero_width>[\p{Mn}\p{Me}\p{Cf}])|(?<str>.))(?<marks>[\p{Mn}\p{Me}\p{Cf}]*)|\t"
+    );
+    Ok(Regex::new(pattern_str).map_err(ParseError::from)?)
+}
+
+/// Gets the color at an index of the 256-color palette, as (red, green, blue).
+///
+/// * `index`: The index into the palette.
+fn palette_color(index: u8) -> (u8, u8, u8) {
+    // the levels of each channel in the color cube
+    let cube_level = |level: u8| if level == 0 { 0 } else { 55 + level * 40 };
+    match index {
+        0..=15 => STANDARD_COLORS[usize::from(index)],
+        // a 6x6x6 color cube
+        16..=231 => {
+            let index = index - 16;
+            (
+                cube_level(index / 36),
+                cube_level((index / 6) % 6),
+                cube_level(index % 6),
+            )
+        }
+        // a grayscale ramp, not including black or white
+        232..=255 => {
+            let gray = 8 + (index - 232) * 10;
+            (gray, gray, gray)
+        }
+    }
+}
+
+/// Finds the characters of a row of ASCII text that aren't matched by the regex from
+/// [`cell_regex`], so they would be dropped while parsing. Whitespace and escape sequences that
+/// don't color a character (such as resets) aren't counted.
+///
+/// * `re`: The regex from [`cell_regex`].
+/// * `line`: The ASCII text of this row.
+///
+/// # Returns
+/// * The dropped characters, in order, or `None` if nothing is dropped.
+pub(crate) fn find_dropped_chars(re: &Regex, line: &str) -> Option<String> {
+    // NOTE: \u{1b} represents the \033 character
+    let escape_sequence = concat!('\u{1b}', r"\[[0-9;]*m");
+    let Ok(escape_sequence) = Regex::new(escape_sequence) else {
+        // the pattern is fixed, so this can't happen
+        return None;
+    };
+
+    let mut dropped = String::new();
+    let mut unmatched_start = 0;
+    let unmatched_ends = re
+        .find_iter(line)
+        .map(|found| (found.start(), found.end()))
+        .chain(std::iter::once((line.len(), line.len())));
+    for (start, end) in unmatched_ends {
+        let unmatched = escape_sequence.replace_all(&line[unmatched_start..start], "");
+        dropped.extend(unmatched.chars().filter(|c| !c.is_whitespace()));
+        unmatched_start = end;
+    }
+
+    (!dropped.is_empty()).then_some(dropped)
+}
+
+/// Parses a single row of colored ASCII text into cells. Tabs are expanded to `tab_width` blank
+/// cells and carriage returns are ignored, so both `\n` and `\r\n` line endings are supported.
+///
+/// Zero-width characters, such as combining accents and zero-width joiners, are handled as chosen
+/// by `zero_width`. When they're combined, they're added to the marks of the cell before them,
+/// whether they're colored or not, so they never take up a cell of their own. A zero-width
+/// character with no cell before it in the row is combined into a blank cell instead.
+///
+/// Cells are parsed lazily as the iterator is advanced, so extremely long rows never have to be
+/// held as cells all at once.
+///
+/// * `re`: The regex from [`cell_regex`].
+/// * `line`: The ASCII text of this row.
+/// * `tab_width`: The number of cells each tab is expanded to.
+/// * `zero_width`: How zero-width characters are handled.
+pub(crate) fn parse_ascii_row<'r>(
+    re: &'r Regex,
+    line: &'r str,
+    tab_width: u32,
+    zero_width: ZeroWidthHandling,
+) -> impl Iterator<Item = Result<Cell, ParseError>> + 'r {
+    let mut captures = re.captures_iter(line).peekable();
+    std::iter::from_fn(move || {
+        let current = captures.next()?;
+        let mut parsed = parse_capture(&current, tab_width, zero_width);
+        if zero_width == ZeroWidthHandling::Combine
+            && let Ok(ParsedCell {
+                cell,
+                is_colored: true,
+                ..
+            }) = &mut parsed
+        {
+            // colored zero-width characters after this cell are part of it
+            while let Some(next) = captures.next_if(|next| next.name("zero_width").is_some()) {
+                for name in ["zero_width", "marks"] {
+                    cell.marks
+                        .push_str(next.name(name).map_or("", |m| m.as_str()));
+                }
+            }
+        }
+        Some(parsed)
+    })
+    .flat_map(|parsed| match parsed {
+        Ok(ParsedCell {
+            cell,
+            count,
+            separate_marks,
+            ..
+        }) => {
+            // in separate cells, each mark takes up a cell of its own, in the color of its cell
+            let mark_cells = separate_marks
+                .chars()
+                .map(|mark| {
+                    Ok(Cell {
+                        character: mark,
+                        ..cell.clone()
+                    })
+                })
+                .collect::<Vec<_>>();
+            std::iter::repeat_n(Ok(cell), count).chain(mark_cells)
+        }
+        Err(err) => std::iter::repeat_n(Err(err), 1).chain(Vec::new()),
+    })
+}
+
+/// A cell parsed from a single match of the regex from [`cell_regex`].
+struct ParsedCell {
+    /// The parsed cell.
+    cell: Cell,
+    /// The number of cells it takes up, such as the width of a tab.
+    count: usize,
+    /// Whether the cell was colored, rather than an uncolored tab, so zero-width characters can be
+    /// combined into it.
+    is_colored: bool,
+    /// The zero-width characters after the cell that each take up a cell of their own.
+    separate_marks: String,
+}
+
+/// Parses a single match of the regex from [`cell_regex`] into a cell.
+///
+/// * `captures`: The captures of the match.
+/// * `tab_width`: The number of cells each tab is expanded to.
+/// * `zero_width`: How zero-width characters are handled.
+fn parse_capture(
+    captures: &Captures,
+    tab_width: u32,
+    zero_width: ZeroWidthHandling,
+) -> Result<ParsedCell, ParseError> {
+    let blank = |count| ParsedCell {
+        cell: Cell::blank(),
+        count,
+        is_colored: false,
+        separate_marks: String::new(),
+    };
+    let marks = captures.name("marks").map_or("", |m| m.as_str());
+    let (the_str, marks) = match (captures.name("zero_width"), captures.name("str")) {
+        // a zero-width character with no cell before it to combine into, so give it a blank one
+        (Some(zero_width_char), _) if zero_width == ZeroWidthHandling::Combine => {
+            (" ", format!("{}{marks}", zero_width_char.as_str()))
+        }
+        (Some(zero_width_char), _) => (zero_width_char.as_str(), String::from(marks)),
+        (None, Some(the_str)) => (the_str.as_str(), String::from(marks)),
+        // an uncolored tab
+        (None, None) => return Ok(blank(tab_width as usize)),
+    };
+    let character = match the_str.chars().next() {
+        // a colored tab, which has no glyph to color
+        Some('\t') => return Ok(blank(tab_width as usize)),
+        // carriage returns from Windows line endings don't take up a cell
+        Some('\r') | None => return Ok(blank(0)),
+        Some(character) => character,
+    };
+
+    let parse_color = |value_name: &str, value: &str| {
+        value.parse::<u8>().map_err(|err| ParseError::ParseColor {
+            value_name: String::from(value_name),
+            the_str: String::from(the_str),
+            err,
+        })
+    };
+    let (red, green, blue) = match (
+        captures.name("red"),
+        captures.name("green"),
+        captures.name("blue"),
+    ) {
+        (Some(r), Some(g), Some(b)) => (
+            parse_color("red", r.as_str())?,
+            parse_color("green", g.as_str())?,
+            parse_color("blue", b.as_str())?,
+        ),
+        // otherwise, the character is colored from the 256-color palette
+        _ => {
+            let index = captures.name("index").map_or("", |m| m.as_str());
+            palette_color(parse_color("index", index)?)
+        }
+    };
+    let background = match (
+        captures.name("bg_red"),
+        captures.name("bg_green"),
+        captures.name("bg_blue"),
+    ) {
+        (Some(r), Some(g), Some(b)) => Some([
+            parse_color("background red", r.as_str())?,
+            parse_color("background green", g.as_str())?,
+            parse_color("background blue", b.as_str())?,
+        ]),
+        _ => None,
+    };
+    let (marks, separate_marks) = match zero_width {
+        ZeroWidthHandling::Combine => (marks, String::new()),
+        ZeroWidthHandling::SeparateCell => (String::new(), marks),
+    };
+    let cell = Cell {
+        character,
+        marks,
+        red,
+        green,
+        blue,
+        background,
+    };
+    Ok(ParsedCell {
+        cell,
+        count: 1,
+        is_colored: true,
+        separate_marks,
+    })
+}
+
+/// Parses colored ANSI-encoded ASCII text into a grid of cells, without rendering it. Tabs are
+/// expanded to `tab_width` blank cells and carriage returns are ignored.
+///
+/// * `ascii_text`: The ASCII text to parse.
+/// * `tab_width`: The number of cells each tab is expanded to.
+/// * `zero_width`: How zero-width characters, such as combining accents, are handled.
+///
+/// # Returns
+/// * The parsed [`CellGrid`], or `Err` if a color can't be parsed or the rows aren't all the same
+///   width.
+pub fn parse_ascii_grid(
+    ascii_text: &str,
+    tab_width: u32,
+    zero_width: ZeroWidthHandling,
+) -> Result<CellGrid, ImgiiError> {
+    let re = cell_regex()?;
+
+    let mut cells = Vec::new();
+    let mut width = None;
+    let mut height = 0;
+    for line in ascii_text.lines() {
+        let row_start = cells.len();
+        for cell in parse_ascii_row(&re, line, tab_width, zero_width) {
+            cells.push(cell?);
+        }
+        let row_width = cells.len() - row_start;
+        match width {
+            // every row must be the same width as the first
+            Some(width) if width != row_width => {
+                return Err(ImageError::Render {
+                    reason: format!(
+                        "width {width} is not equal to the current line width {row_width}"
+                    ),
+                }
+                .into());
+            }
+            Some(_) => {}
+            None => width = Some(row_width),
+        }
+        height += 1;
+    }
+
+    Ok(CellGrid {
+        cells,
+        width: width.unwrap_or(0),
+        height,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::ansi_cell;
+
+    /// Gets the characters of each row of a grid.
+    fn rows(grid: &CellGrid) -> Vec<String> {
+        grid.cells
+            .chunks(grid.width)
+            .map(|row| row.iter().map(|cell| cell.character).collect())
+            .collect()
+    }
+
+    #[test]
+    fn tabs_expand_to_tab_width_cells() {
+        let red = |character| ansi_cell([255, 0, 0], character);
+        // both uncolored and colored tabs are expanded
+        let ascii_text = format!(
+            "{}\t{}\n{}{}{}",
+            red('a'),
+            red('b'),
+            red('c'),
+            red('\t'),
+            red('d')
+        );
+
+        let grid = parse_ascii_grid(&ascii_text, 4, ZeroWidthHandling::Combine).unwrap();
+        assert_eq!((grid.width, grid.height), (6, 2));
+        assert_eq!(rows(&grid), ["a    b", "c    d"]);
+        assert!(grid.cells[1..5].iter().all(Cell::is_blank));
+    }
+
+    #[test]
+    fn windows_line_endings_leave_no_stray_cell() {
+        let red = |character| ansi_cell([255, 0, 0], character);
+        // the carriage return may also be colored, like every other character of the line
+        let ascii_text = format!(
+            "{}{}\r\n{}{}{}\r\n",
+            red('a'),
+            red('b'),
+            red('c'),
+            red('d'),
+            red('\r')
+        );
+
+        let grid = parse_ascii_grid(&ascii_text, 4, ZeroWidthHandling::Combine).unwrap();
+        assert_eq!((grid.width, grid.height), (2, 2));
+        assert_eq!(rows(&grid), ["ab", "cd"]);
+    }
+
+    #[test]
+    fn grayscale_palette_colors_are_parsed() {
+        // single-channel images may be colored from the grayscale ramp of the 256-color palette
+        let ascii_text = "\x1b[38;5;232m#\x1b[38;5;255m@\x1b[38;5;15m.";
+
+        let grid = parse_ascii_grid(ascii_text, 4, ZeroWidthHandling::Combine).unwrap();
+        assert_eq!((grid.width, grid.height), (3, 1));
+        let colors = grid
+            .cells
+            .iter()
+            .map(|cell| [cell.red, cell.green, cell.blue])
+            .collect::<Vec<_>>();
+        assert_eq!(colors, [[8; 3], [238; 3], [255; 3]]);
+    }
+
+    #[test]
+    fn zero_width_characters_combine_or_take_their_own_cell() {
+        let red = |character| ansi_cell([255, 0, 0], character);
+        // a combining accent inside the cell of its base character, then a colored zero-width space
+        let ascii_text = format!(
+            "{}{}\u{301}{}{}",
+            red('a'),
+            red('e'),
+            red('\u{200B}'),
+            red('b')
+        );
+
+        let grid = parse_ascii_grid(&ascii_text, 4, ZeroWidthHandling::Combine).unwrap();
+        assert_eq!((grid.width, grid.height), (3, 1));
+        assert_eq!(rows(&grid), ["aeb"]);
+        assert_eq!(grid.cells[1].marks, "\u{301}\u{200B}");
+        assert!(grid.cells[0].marks.is_empty() && grid.cells[2].marks.is_empty());
+
+        let grid = parse_ascii_grid(&ascii_text, 4, ZeroWidthHandling::SeparateCell).unwrap();
+        assert_eq!((grid.width, grid.height), (5, 1));
+        assert_eq!(rows(&grid), ["ae\u{301}\u{200B}b"]);
+        assert!(grid.cells.iter().all(|cell| cell.marks.is_empty()));
+        // the accent takes the color of the cell it was in
+        assert_eq!(grid.cells[2].red, 255);
+    }
+}
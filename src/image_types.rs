@@ -1,19 +1,27 @@
 //! Contains helpers for handling supported output image types.
 
+use image::ImageFormat;
+
 /// Holds the image types that imgii can output.
 /// Each value holds an index into the `IMAGE_STR_TYPES` array.
 #[derive(Debug, Clone, Copy)]
 pub enum OutputImageType {
     Png,
     Gif,
+    /// Raw pixel bytes, with no header. Channel order is chosen by the options.
+    Raw,
+    /// Colored ASCII text, escape sequences included, which can be rendered later.
+    Ans,
 }
 
 // image type string defines
 const IMG_TYPE_PNG: &str = ".png";
 const IMG_TYPE_GIF: &str = ".gif";
+const IMG_TYPE_RAW: &str = ".raw";
+const IMG_TYPE_ANS: &str = ".ans";
 
 /// All image types stored in an array, for iterating through all image types.
-pub const IMG_TYPES_ARRAY: &[&str] = &[IMG_TYPE_PNG, IMG_TYPE_GIF];
+pub const IMG_TYPES_ARRAY: &[&str] = &[IMG_TYPE_PNG, IMG_TYPE_GIF, IMG_TYPE_RAW, IMG_TYPE_ANS];
 
 impl OutputImageType {
     /// Converts a string slice to an `OutputImageType`.
@@ -23,6 +31,8 @@ impl OutputImageType {
         match output_image_type_str {
             IMG_TYPE_PNG => Some(OutputImageType::Png),
             IMG_TYPE_GIF => Some(OutputImageType::Gif),
+            IMG_TYPE_RAW => Some(OutputImageType::Raw),
+            IMG_TYPE_ANS => Some(OutputImageType::Ans),
             _ => None,
         }
     }
@@ -48,10 +58,23 @@ impl OutputImageType {
         match *self {
             OutputImageType::Png => IMG_TYPE_PNG,
             OutputImageType::Gif => IMG_TYPE_GIF,
+            OutputImageType::Raw => IMG_TYPE_RAW,
+            OutputImageType::Ans => IMG_TYPE_ANS,
         }
     }
 }
 
+/// Information about an input image, read from its header without decoding the whole image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ImageInfo {
+    /// The format of the image.
+    pub format: ImageFormat,
+    /// The width of the image, in pixels.
+    pub width: u32,
+    /// The height of the image, in pixels.
+    pub height: u32,
+}
+
 /// Holds whether the program should convert a batch of inputs or just a single.
 #[derive(Debug, Clone)]
 pub enum ImageBatchType {
@@ -0,0 +1,45 @@
+//! Contains a rendered image described as its unique cells and a grid of indices into them.
+
+use std::{collections::HashMap, sync::Arc};
+
+use crate::conversion::{converters::generic_converter::Imgii2dImage, image_data::ImageData};
+
+/// A rendered image, stored as each distinct rendered cell once, along with which cell goes where.
+#[derive(Debug, Clone)]
+pub struct IndexedGrid {
+    /// Every distinct rendered cell, in the order they first appear in the image.
+    pub unique_cells: Vec<ImageData>,
+    /// The index into `unique_cells` of every cell in the image, as rows starting from the top of
+    /// the image.
+    pub indices: Vec<Vec<usize>>,
+}
+
+/// Builds an indexed grid from a rendered image. Cells are considered the same if they share the
+/// same rendered image, so cells are only deduplicated if they came from the glyph cache.
+///
+/// * `the_image`: The rendered image.
+pub(crate) fn build_indexed_grid(the_image: &Imgii2dImage) -> IndexedGrid {
+    let mut unique_cells = Vec::new();
+    // the index of each cell we've seen, by the address of its shared image
+    let mut seen_cells: HashMap<*const ImageData, usize> = HashMap::new();
+
+    let indices = the_image
+        .image_2d
+        .chunks(the_image.width.max(1))
+        .map(|row| {
+            row.iter()
+                .map(|cell| {
+                    *seen_cells.entry(Arc::as_ptr(cell)).or_insert_with(|| {
+                        unique_cells.push(ImageData::clone(cell));
+                        unique_cells.len() - 1
+                    })
+                })
+                .collect()
+        })
+        .collect();
+
+    IndexedGrid {
+        unique_cells,
+        indices,
+    }
+}
@@ -1,40 +1,276 @@
 //! Imgii is a library for converting images to ASCII and rendering as different image types. For
 //! example, it can take a PNG input and convert it into ASCII, render it, and save it.
 
-pub(crate) mod conversion;
+pub mod ascii_source;
+pub mod batch_report;
+pub mod cell_export;
+pub mod conversion;
+pub mod diagnostics;
+pub mod diff;
+pub mod encoder;
 pub mod error;
 pub mod fonts;
+pub mod format_profile;
+pub mod frame_report;
+pub mod grid;
 pub mod image_types;
+pub mod indexed_grid;
 pub mod options;
+#[cfg(feature = "serde")]
+pub mod preset;
+pub mod progress;
+pub mod sdf_glyph_cache;
+pub mod sprite_sheet;
+pub mod stats;
+#[cfg(test)]
+mod test_support;
+pub mod timings;
 
-use std::{fs::File, io::BufWriter};
+use std::{
+    borrow::Cow,
+    io::{Cursor, Write},
+    path::Path,
+    time::{Duration, Instant},
+};
+
+use image::{Delay, DynamicImage, Frame, ImageFormat, Rgba};
 
-use image::{Frame, codecs::gif::GifEncoder};
-use rayon::iter::{IntoParallelIterator, ParallelIterator};
+#[cfg(not(feature = "wasm"))]
+use std::sync::mpsc::{Receiver, SyncSender};
 
+#[cfg(not(feature = "wasm"))]
+use crate::conversion::converters::gif_converter::{
+    NonRenderedFramePart, drop_excess_frames, frames_to_ascii,
+};
+#[cfg(feature = "mmap")]
+use crate::conversion::image_writer::write_2d_vec_to_mmap;
 use crate::{
+    batch_report::{BatchFailure, BatchReport},
+    cell_export::{CellManifest, write_cells},
     conversion::{
         converters::{
-            gif_converter::read_as_deconstructed_rendered_gif_vec,
-            png_converter::parse_ascii_to_2d_png_vec,
+            generic_converter::{Imgii2dImage, render_ascii_generic},
+            gif_converter::{
+                FrameMetadata, RenderedFramePart, build_contact_sheet, build_css_sprite,
+                deconstructed_gif_to_ascii, quantize_to_centiseconds, read_deconstructed_gif,
+                read_gif_repeat, render_deconstructed_ascii,
+                render_deconstructed_ascii_with_progress, save_gif, save_gif_with_repeat,
+                split_frame_failures,
+            },
+            png_converter::{
+                image_to_ascii, open_image, open_image_with_icc_profile, parse_ascii_to_2d_png_vec,
+                read_ansi_input, read_image_info, save_png, save_with_encoder, stream_ascii_as_png,
+            },
+            typewriter_converter::render_typewriter_frames,
+        },
+        image_data::InternalImage,
+        image_writer::{
+            AsciiImageWriter, luma_from_2d_vec, luma16_from_2d_vec, output_dimensions,
+            prepare_output_dir, rgba16_from_2d_vec, rgba16_to_rgb16, stack_horizontally,
+            stack_vertically, write_if_changed, write_output_file,
         },
-        image_writer::AsciiImageWriter,
+        parallel::{IntoParallelIterator, ParallelIterator},
+        render_char_to_png::calculate_char_dimensions,
     },
-    error::ImgiiError,
-    options::ImgiiOptions,
+    diagnostics::ConversionInfo,
+    error::{ImageError, ImgiiError},
+    frame_report::{FrameFailure, GifConversionReport},
+    grid::parse_ascii_grid,
+    image_types::ImageInfo,
+    indexed_grid::{IndexedGrid, build_indexed_grid},
+    options::{BitDepth, ImgiiOptions, ImgiiOptionsBuilder, PngAlpha, TypewriterOptions},
+    progress::{ProgressStage, ProgressTracker},
+    sprite_sheet::{SpriteSheetIndex, build_sprite_sheet},
+    stats::RenderStats,
+    timings::ConversionTimings,
 };
+#[cfg(feature = "system-fonts")]
+use crate::{
+    error::FontError,
+    fonts::list_fonts,
+    format_profile::FormatProfile,
+    image_types::OutputImageType,
+    options::{Charset, from_enum},
+};
+
+/// Converts an image into an ASCII image with default options, inferring the output type from the
+/// extension of the output file name (see [`image_types::OutputImageType`]). The simplest way to use imgii.
+///
+/// Renders with the minimal charset at a font size of 16 with no background, using the first
+/// installed monospace font, along with the defaults of the output format (see
+/// [`FormatProfile::for_output_type`]). For anything else, build [`ImgiiOptions`] and use the
+/// conversion function for the output type, such as [`convert_to_ascii_png`].
+///
+/// # Params
+/// - `input_file_name` - The input file name.
+/// - `output_file_name` - The output file name. Must end in one of
+///   [`image_types::IMG_TYPES_ARRAY`].
+///
+/// # Returns
+/// - `Err` upon error, such as when the output file extension isn't supported, `Ok(())`
+///   otherwise.
+///
+/// # Example
+///
+/// ```no_run
+/// imgii::convert("the_input_image.jpg", "the_output_image.png").unwrap();
+/// ```
+#[cfg(feature = "system-fonts")]
+pub fn convert(input_file_name: &str, output_file_name: &str) -> Result<(), ImgiiError> {
+    let Some(output_type) = OutputImageType::from_file_name(output_file_name) else {
+        return Err(ImageError::UnsupportedOutputType {
+            file_name: String::from(output_file_name),
+        }
+        .into());
+    };
+    let Some(font_family) = list_fonts().into_iter().next() else {
+        return Err(FontError::NoneInstalled.into());
+    };
+
+    let imgii_options = FormatProfile::for_output_type(output_type)
+        .apply(ImgiiOptionsBuilder::new())
+        .charset(from_enum(Charset::Minimal))
+        .font_family(&font_family)
+        .build()?;
+    match output_type {
+        OutputImageType::Png => {
+            convert_to_ascii_png(input_file_name, output_file_name, &imgii_options)
+        }
+        OutputImageType::Gif => {
+            convert_to_ascii_gif(input_file_name, output_file_name, &imgii_options)
+        }
+        OutputImageType::Raw => {
+            convert_to_ascii_raw(input_file_name, output_file_name, &imgii_options)
+        }
+        OutputImageType::Ans => {
+            convert_to_ansi_file(input_file_name, output_file_name, &imgii_options)
+        }
+    }
+    .map(|_| ())
+}
+
+/// Checks that an input file is an image that imgii can convert, without decoding the whole image.
+/// Only the header of the image is read, so this is fast enough to check every input of a batch
+/// before starting a long conversion.
+///
+/// NOTE: the rest of the image isn't decoded, so an image with a valid header but corrupt data
+/// can still fail to convert.
+///
+/// # Params
+/// - `input_file_name` - The input file name.
+///
+/// # Returns
+/// - The [`ImageInfo`] with the format and dimensions of the image upon success, `Err` if the
+///   file can't be read or isn't a supported image.
+pub fn validate_input(input_file_name: &str) -> Result<ImageInfo, ImgiiError> {
+    read_image_info(input_file_name)
+}
+
+/// Works out every step of sizing the output of converting an image, without rendering anything.
+/// The input image is converted to ASCII text to find the size of the grid, but no glyphs are
+/// drawn, so this is a cheap way to find out why an output image isn't the expected size.
+///
+/// # Params
+/// - `input_file_name` - The input file name.
+/// - `imgii_options` - The `imgii` render options
+///
+/// # Returns
+/// - The [`ConversionInfo`] describing the size at each step upon success, `Err` otherwise.
+pub fn conversion_info(
+    input_file_name: &str,
+    imgii_options: &ImgiiOptions,
+) -> Result<ConversionInfo, ImgiiError> {
+    let loaded_img = open_image(input_file_name)?;
+    let imgii_options = &*imgii_options.for_source(loaded_img.width(), loaded_img.height());
+    let ascii_text = image_to_ascii(&loaded_img, imgii_options)?;
+    let grid = parse_ascii_grid(
+        &ascii_text,
+        imgii_options.tab_width(),
+        imgii_options.zero_width_handling(),
+    )?;
+
+    let (cell_width, cell_height) = calculate_char_dimensions(imgii_options);
+    // a fixed grid crops and pads the parsed grid to its own size
+    let grid_dimensions = imgii_options
+        .fixed_grid()
+        .unwrap_or((grid.width as u32, grid.height as u32));
+    let canvas_dimensions = (
+        cell_width * grid_dimensions.0,
+        cell_height * grid_dimensions.1,
+    );
+
+    let rascii_options = imgii_options.rascii_options();
+    let charset = match &rascii_options.char_override {
+        Some(char_override) => char_override.clone(),
+        None => rascii_options
+            .charset
+            .iter()
+            .map(|the_str| String::from(*the_str))
+            .collect(),
+    };
+
+    Ok(ConversionInfo {
+        source_dimensions: (loaded_img.width(), loaded_img.height()),
+        grid_dimensions,
+        cell_dimensions: (cell_width, cell_height),
+        canvas_dimensions,
+        output_dimensions: output_dimensions(
+            canvas_dimensions.0,
+            canvas_dimensions.1,
+            imgii_options,
+        ),
+        font_size: imgii_options.font_size(),
+        square_cells: imgii_options.square_cells(),
+        charset,
+    })
+}
+
+/// Creates options that render an input image to fill as much of a target resolution as they can,
+/// picking the grid size and font size with [`ImgiiOptionsBuilder::fit_to_resolution`]. Only the
+/// header of the input image is read. The options are returned so they can be inspected before
+/// rendering.
+///
+/// # Params
+/// - `input_file_name` - The input file name.
+/// - `target_width` - The width to fill, in pixels.
+/// - `target_height` - The height to fill, in pixels.
+/// - `imgii_options_builder` - The builder holding every other option. If it has a width, the
+///   width is kept and only the font size is picked.
+///
+/// # Returns
+/// - The built options upon success, `Err` if the input can't be read or the options are invalid.
+pub fn options_for_resolution<'a>(
+    input_file_name: &str,
+    target_width: u32,
+    target_height: u32,
+    imgii_options_builder: ImgiiOptionsBuilder<'a>,
+) -> Result<ImgiiOptions<'a>, ImgiiError> {
+    let image_info = read_image_info(input_file_name)?;
+    imgii_options_builder
+        .fit_to_resolution(
+            (image_info.width, image_info.height),
+            (target_width, target_height),
+        )
+        .build()
+}
 
 /// Converts an image (such as a PNG or JPEG) into an ASCII PNG.
 /// It does this by first converting the image into colored ASCII text,
 /// then renders the ASCII text as an image.
 ///
+/// NOTE: the output format is chosen by the extension of the output file name, so other formats
+/// supported by the `image` crate can be written too. JPEGs have no alpha channel, so they need
+/// `png_alpha` set to strip or flatten it, and are always written without chroma subsampling
+/// (4:4:4), which keeps the colored edges of glyphs crisp at the cost of a larger file.
+///
 /// # Params
 /// - `input_file_name` - The input file name.
 /// - `output_file_name` - The output file name.
 /// - `imgii_options` - The `imgii` render options
 ///
 /// # Returns
-/// - `Err(())` upon error, `Ok(())` otherwise.
+/// - `Err(())` upon error, otherwise `Ok` holding the [`ConversionTimings`] if timings are enabled
+///   in the options.
 ///
 /// # Example
 ///
@@ -78,22 +314,306 @@ pub fn convert_to_ascii_png(
     input_file_name: &str,
     output_file_name: &str,
     imgii_options: &ImgiiOptions,
+) -> Result<Option<ConversionTimings>, ImgiiError> {
+    let mut timings = ConversionTimings::default();
+    let AsciiInput {
+        ascii_text,
+        imgii_options,
+        icc_profile,
+        is_ansi,
+    } = read_ascii_input(input_file_name, imgii_options, &mut timings)?;
+    let imgii_options = &*imgii_options;
+
+    let stage_start = Instant::now();
+    let lines = render_ascii_generic(imgii_options, ascii_text)?;
+    save_rendered_png(
+        lines,
+        output_file_name,
+        icc_profile,
+        // there's no source image to name if the input was already ASCII
+        (!is_ansi).then_some(input_file_name),
+        imgii_options,
+        stage_start,
+        &mut timings,
+    )?;
+
+    Ok(imgii_options.timings().then_some(timings))
+}
+
+/// Converts a batch of images into ASCII PNGs, like [`convert_to_ascii_png`] for each one. The
+/// images are converted in parallel, and an image that fails to convert doesn't stop the rest of
+/// the batch. Useful for converting arbitrary lists of files, such as from a glob or a manifest.
+///
+/// # Params
+/// - `inputs` - The input file name of each image, followed by its output file name.
+/// - `imgii_options` - The `imgii` render options, used for every image.
+///
+/// # Returns
+/// - The [`BatchReport`] of which images failed to convert, and why.
+pub fn convert_batch(
+    inputs: impl IntoIterator<Item = (String, String)>,
+    imgii_options: &ImgiiOptions,
+) -> BatchReport {
+    let inputs: Vec<_> = inputs.into_iter().collect();
+    let total = inputs.len();
+    let failures = inputs
+        .into_par_iter()
+        .filter_map(|(input_file_name, output_file_name)| {
+            convert_to_ascii_png(&input_file_name, &output_file_name, imgii_options)
+                .err()
+                .map(|error| BatchFailure {
+                    input_file_name,
+                    output_file_name,
+                    error,
+                })
+        })
+        .collect();
+
+    BatchReport { total, failures }
+}
+
+/// An input file converted into colored ASCII text, ready to be rendered.
+struct AsciiInput<'o, 'a> {
+    /// The colored ASCII text to render.
+    ascii_text: String,
+    /// The imgii options to render with, holding the size of the source image if it's matched.
+    imgii_options: Cow<'o, ImgiiOptions<'a>>,
+    /// The ICC profile of the source image, if it's preserved.
+    icc_profile: Option<Vec<u8>>,
+    /// Whether the input was already colored ASCII text, rather than an image.
+    is_ansi: bool,
+}
+
+/// Reads an input file and converts it into colored ASCII text, which every conversion of a single
+/// image into a rendered image starts with. Inputs that are already colored ASCII text are read as
+/// they are if `ansi_input` is enabled, otherwise the image is decoded and converted.
+///
+/// * `input_file_name`: The input file name.
+/// * `imgii_options`: The imgii options.
+/// * `timings`: The timings to record the decode and ASCII stages in.
+///
+/// # Returns
+/// * The [`AsciiInput`] to render, or `Err` if the conversion was cancelled or the input couldn't
+///   be read.
+fn read_ascii_input<'o, 'a>(
+    input_file_name: &str,
+    imgii_options: &'o ImgiiOptions<'a>,
+    timings: &mut ConversionTimings,
+) -> Result<AsciiInput<'o, 'a>, ImgiiError> {
+    // don't start converting if we've already been cancelled, such as in a batch
+    imgii_options.check_cancelled()?;
+    if imgii_options.ansi_input()
+        && let Some(ascii_text) = read_ansi_input(input_file_name)?
+    {
+        // the input is already ASCII, so there's nothing to convert
+        return Ok(AsciiInput {
+            ascii_text,
+            imgii_options: Cow::Borrowed(imgii_options),
+            icc_profile: None,
+            is_ansi: true,
+        });
+    }
+
+    let stage_start = Instant::now();
+    let (loaded_img, icc_profile) = if imgii_options.preserve_icc_profile() {
+        open_image_with_icc_profile(input_file_name)?
+    } else {
+        (open_image(input_file_name)?, None)
+    };
+    let imgii_options = imgii_options.for_source(loaded_img.width(), loaded_img.height());
+    timings.decode = stage_start.elapsed();
+
+    let stage_start = Instant::now();
+    let ascii_text = image_to_ascii(&loaded_img, &imgii_options)?;
+    timings.ascii = stage_start.elapsed();
+
+    Ok(AsciiInput {
+        ascii_text,
+        imgii_options,
+        icc_profile,
+        is_ansi: false,
+    })
+}
+
+/// Stitches rendered ASCII into the output image and saves it, with the custom encoder if one is
+/// set, otherwise as a PNG.
+///
+/// * `lines`: The rendered cells of the ASCII text.
+/// * `output_file_name`: The output file name.
+/// * `icc_profile`: The ICC profile to embed in the PNG, if any.
+/// * `source_file_name`: The file name of the source image, embedded as metadata if enabled.
+/// * `imgii_options`: The imgii options.
+/// * `render_start`: When rendering the ASCII text started, for timing the render stage.
+/// * `timings`: The timings to record the render and encode stages in.
+fn save_rendered_png(
+    lines: Imgii2dImage,
+    output_file_name: &str,
+    icc_profile: Option<Vec<u8>>,
+    source_file_name: Option<&str>,
+    imgii_options: &ImgiiOptions,
+    render_start: Instant,
+    timings: &mut ConversionTimings,
 ) -> Result<(), ImgiiError> {
-    let lines = parse_ascii_to_2d_png_vec(input_file_name, imgii_options)?;
-    let final_image_writer = AsciiImageWriter::from_2d_vec(lines)?;
+    if let Some(encoder) = imgii_options.encoder() {
+        // the custom encoder decides how the image is written, so always give it RGBA
+        let final_image_writer = AsciiImageWriter::from_2d_vec(lines, imgii_options)?;
+        timings.render = render_start.elapsed();
+
+        let stage_start = Instant::now();
+        save_with_encoder(
+            &final_image_writer.imagebuf,
+            output_file_name,
+            encoder,
+            imgii_options,
+        )?;
+        timings.encode = stage_start.elapsed();
+
+        return Ok(());
+    }
+    let output_image = render_png_image(lines, imgii_options)?;
+    timings.render = render_start.elapsed();
 
     // write the image
-    final_image_writer
-        .imagebuf
-        .as_buffer()
-        .save(&output_file_name)
-        .map_err(|err| -> ImgiiError { anyhow::Error::new(err).into() })?;
+    let stage_start = Instant::now();
+    save_png(
+        &output_image,
+        output_file_name,
+        icc_profile,
+        source_file_name,
+        imgii_options,
+    )?;
+    timings.encode = stage_start.elapsed();
+
     Ok(())
 }
 
-/// Converts a GIF into an ASCII GIF.
+/// Converts an image (such as a PNG or JPEG) into an ASCII image split into two PNGs for
+/// compositing: the color of the render flattened onto black, and a grayscale alpha matte where
+/// opaque pixels are white and transparent pixels are black. Useful for VFX pipelines that expect
+/// the color and the matte separately.
+///
+/// NOTE: the matte is the alpha channel of the render, so it's fully white if the background is
+/// enabled with an opaque background color. `png_alpha` and `bit_depth` are ignored, since both
+/// outputs are always 8-bit images without an alpha channel.
+///
+/// # Params
+/// - `input_file_name` - The input file name.
+/// - `rgb_output_file_name` - The output file name of the color image.
+/// - `matte_output_file_name` - The output file name of the alpha matte.
+/// - `imgii_options` - The `imgii` render options
+///
+/// # Returns
+/// - `Err(())` upon error, `Ok(())` otherwise.
+pub fn convert_to_ascii_rgb_and_matte(
+    input_file_name: &str,
+    rgb_output_file_name: &str,
+    matte_output_file_name: &str,
+    imgii_options: &ImgiiOptions,
+) -> Result<(), ImgiiError> {
+    imgii_options.check_cancelled()?;
+    let loaded_img = open_image(input_file_name)?;
+    let imgii_options = &*imgii_options.for_source(loaded_img.width(), loaded_img.height());
+    let ascii_text = image_to_ascii(&loaded_img, imgii_options)?;
+    let lines = render_ascii_generic(imgii_options, ascii_text)?;
+    let render = AsciiImageWriter::from_2d_vec(lines, imgii_options)?.imagebuf;
+
+    save_png(
+        &DynamicImage::ImageRgb8(render.to_rgb(true)?),
+        rgb_output_file_name,
+        None,
+        Some(input_file_name),
+        imgii_options,
+    )?;
+    save_png(
+        &DynamicImage::ImageLuma8(render.to_alpha_matte()),
+        matte_output_file_name,
+        None,
+        Some(input_file_name),
+        imgii_options,
+    )
+}
+
+/// Converts an encoded image (such as a PNG or JPEG) held in memory into an ASCII PNG, returning
+/// the encoded PNG bytes. Nothing is read from or written to the file system, which makes this
+/// usable from WASM. With the `wasm` feature, the global `rayon` thread pool is never used.
+///
+/// # Params
+/// - `input_bytes` - The encoded input image.
+/// - `imgii_options` - The `imgii` render options
+///
+/// # Returns
+/// - The encoded output PNG upon success, `Err` otherwise.
+pub fn convert_bytes_to_ascii_png(
+    input_bytes: &[u8],
+    imgii_options: &ImgiiOptions,
+) -> Result<Vec<u8>, ImgiiError> {
+    let to_imgii_err = |err: image::ImageError| -> ImgiiError { anyhow::Error::new(err).into() };
+
+    let loaded_img = image::load_from_memory(input_bytes).map_err(to_imgii_err)?;
+    let imgii_options = &*imgii_options.for_source(loaded_img.width(), loaded_img.height());
+    let ascii_text = image_to_ascii(&loaded_img, imgii_options)?;
+    let lines = render_ascii_generic(imgii_options, ascii_text)?;
+
+    let mut output_bytes = Vec::new();
+    render_png_image(lines, imgii_options)?
+        .write_to(&mut Cursor::new(&mut output_bytes), ImageFormat::Png)
+        .map_err(to_imgii_err)?;
+    Ok(output_bytes)
+}
+
+/// Stitches rendered ASCII into the image to encode as a PNG, using the fewest channels and the
+/// bit depth it needs.
+///
+/// * `lines`: The rendered ASCII.
+/// * `imgii_options`: The imgii options, holding the bit depth and how to handle alpha.
+fn render_png_image(
+    lines: Imgii2dImage,
+    imgii_options: &ImgiiOptions,
+) -> Result<DynamicImage, ImgiiError> {
+    // there's no color or transparency in opaque grayscale, so only keep one channel
+    let opaque_grayscale = imgii_options.renders_opaque_grayscale();
+    Ok(match imgii_options.bit_depth() {
+        BitDepth::Eight if opaque_grayscale => {
+            DynamicImage::ImageLuma8(luma_from_2d_vec(&lines, imgii_options)?)
+        }
+        BitDepth::Eight => to_png_image(
+            AsciiImageWriter::from_2d_vec(lines, imgii_options)?,
+            imgii_options.png_alpha(),
+        )?,
+        BitDepth::Sixteen if opaque_grayscale => {
+            DynamicImage::ImageLuma16(luma16_from_2d_vec(&lines, imgii_options)?)
+        }
+        BitDepth::Sixteen => {
+            let canvas = rgba16_from_2d_vec(&lines, imgii_options)?;
+            match imgii_options.png_alpha() {
+                PngAlpha::Keep => DynamicImage::ImageRgba16(canvas),
+                PngAlpha::Strip => DynamicImage::ImageRgb16(rgba16_to_rgb16(&canvas, false)?),
+                PngAlpha::Flatten => DynamicImage::ImageRgb16(rgba16_to_rgb16(&canvas, true)?),
+            }
+        }
+    })
+}
+
+/// Converts a rendered image into the image to encode as a PNG, handling its alpha channel.
+///
+/// * `image_writer`: The rendered image.
+/// * `png_alpha`: How to handle the alpha channel.
+fn to_png_image(
+    image_writer: AsciiImageWriter,
+    png_alpha: PngAlpha,
+) -> Result<DynamicImage, ImgiiError> {
+    Ok(match png_alpha {
+        PngAlpha::Keep => DynamicImage::ImageRgba8(image_writer.imagebuf.into()),
+        PngAlpha::Strip => DynamicImage::ImageRgb8(image_writer.imagebuf.to_rgb(false)?),
+        PngAlpha::Flatten => DynamicImage::ImageRgb8(image_writer.imagebuf.to_rgb(true)?),
+    })
+}
+
+/// Converts an image (such as a PNG or JPEG) into raw ASCII image bytes.
 /// It does this by first converting the image into colored ASCII text,
-/// then renders the ASCII text as an image.
+/// then renders the ASCII text as an image and writes its pixels with no header, using the
+/// channel order from the options. With `ansi_input`, inputs that are already colored ASCII text
+/// are rendered as they are, like [`convert_to_ascii_png`].
 ///
 /// # Params
 /// - `input_file_name` - The input file name.
@@ -101,106 +621,1559 @@ pub fn convert_to_ascii_png(
 /// - `imgii_options` - The `imgii` render options
 ///
 /// # Returns
-/// - `Err(())` upon error, `Ok(())` otherwise.
+/// - `Err(())` upon error, otherwise `Ok` holding the [`ConversionTimings`] if timings are enabled
+///   in the options.
+pub fn convert_to_ascii_raw(
+    input_file_name: &str,
+    output_file_name: &str,
+    imgii_options: &ImgiiOptions,
+) -> Result<Option<ConversionTimings>, ImgiiError> {
+    let mut timings = ConversionTimings::default();
+    let AsciiInput {
+        ascii_text,
+        imgii_options,
+        ..
+    } = read_ascii_input(input_file_name, imgii_options, &mut timings)?;
+    let imgii_options = &*imgii_options;
+
+    let stage_start = Instant::now();
+    imgii_options.check_cancelled()?;
+    let lines = render_ascii_generic(imgii_options, ascii_text)?;
+    let final_image_writer = AsciiImageWriter::from_2d_vec(lines, imgii_options)?;
+    timings.render = stage_start.elapsed();
+    // rendering is the slowest part, so don't write anything if we were cancelled during it
+    imgii_options.check_cancelled()?;
+
+    // write the raw bytes
+    let stage_start = Instant::now();
+    write_output_file(output_file_name, imgii_options, |file_writer| {
+        Ok(file_writer.write_all(
+            &final_image_writer
+                .imagebuf
+                .to_channel_ordered_bytes(imgii_options.channel_order()),
+        )?)
+    })?;
+    timings.encode = stage_start.elapsed();
+
+    Ok(imgii_options.timings().then_some(timings))
+}
+
+/// Converts an image (such as a PNG or JPEG) into raw ASCII image bytes, like
+/// [`convert_to_ascii_raw`], but writes the pixels straight into a memory-mapped output file
+/// instead of building the whole image in memory first. Useful for multi-gigabyte renders.
 ///
-/// # Example
+/// The output is a headerless dump of the image's pixels, row by row starting from the top left
+/// pixel, with 4 bytes per pixel in the channel order from the options. The width of the image
+/// is the width of the ASCII grid multiplied by the cell width, and the height is the height of
+/// the ASCII grid multiplied by the cell height. Post-processing (scanlines, scaling, matching the
+/// source resolution and padding) isn't applied, and the memory budget isn't checked since the
+/// image is never held in memory.
 ///
-/// ```no_run
-/// // Simple example showing off basic usage.
+/// # Params
+/// - `input_file_name` - The input file name.
+/// - `output_file_name` - The output file name.
+/// - `imgii_options` - The `imgii` render options
 ///
-/// use imgii::{
-///     convert_to_ascii_gif,
-///     options::{Charset, ImgiiOptionsBuilder, RasciiOptions, from_enum},
-/// };
+/// # Returns
+/// - `Err(())` upon error, otherwise `Ok` holding the [`ConversionTimings`] if timings are enabled
+///   in the options.
+#[cfg(feature = "mmap")]
+pub fn convert_to_ascii_raw_mmap(
+    input_file_name: &str,
+    output_file_name: &str,
+    imgii_options: &ImgiiOptions,
+) -> Result<Option<ConversionTimings>, ImgiiError> {
+    let mut timings = ConversionTimings::default();
+    let AsciiInput {
+        ascii_text,
+        imgii_options,
+        ..
+    } = read_ascii_input(input_file_name, imgii_options, &mut timings)?;
+    let imgii_options = &*imgii_options;
+
+    let stage_start = Instant::now();
+    imgii_options.check_cancelled()?;
+    let lines = render_ascii_generic(imgii_options, ascii_text)?;
+    timings.render = stage_start.elapsed();
+    // don't create the (possibly huge) output file if we were cancelled while rendering
+    imgii_options.check_cancelled()?;
+
+    // stitch the image straight into the output file
+    let stage_start = Instant::now();
+    write_2d_vec_to_mmap(&lines, output_file_name, imgii_options)?;
+    timings.encode = stage_start.elapsed();
+
+    Ok(imgii_options.timings().then_some(timings))
+}
+
+/// Converts an image (such as a PNG or JPEG) into colored ASCII text and saves it as an ANSI-art
+/// file (usually ending in `.ans`), without rendering it. The file holds exactly the ASCII text
+/// that would be rendered, escape sequences included, so it can be shown by other ANSI viewers or
+/// rendered later with [`convert_ansi_to_ascii_png`].
 ///
-/// # fn main() {
-/// let input_file_name = "the_input_image.gif";
-/// let output_file_name = "the_output_image.gif";
+/// # Params
+/// - `input_file_name` - The input file name.
+/// - `output_file_name` - The output file name.
+/// - `imgii_options` - The `imgii` render options
 ///
-/// // imgii options (for converting image to ASCII image)
-/// let imgii_options = ImgiiOptionsBuilder::new()
-///     .background(true) // set black background behind GIF
-///     .width(50) // keeps the aspect ratio but is 50 pixels wide
-///     .build()
-///     .unwrap();
+/// # Returns
+/// - `Err` upon error, otherwise `Ok` holding the [`ConversionTimings`] if timings are enabled in
+///   the options. Nothing is rendered, so the render time is always zero.
+pub fn convert_to_ansi_file(
+    input_file_name: &str,
+    output_file_name: &str,
+    imgii_options: &ImgiiOptions,
+) -> Result<Option<ConversionTimings>, ImgiiError> {
+    imgii_options.check_cancelled()?;
+    let mut timings = ConversionTimings::default();
+
+    let stage_start = Instant::now();
+    let loaded_img = open_image(input_file_name)?;
+    let imgii_options = &*imgii_options.for_source(loaded_img.width(), loaded_img.height());
+    timings.decode = stage_start.elapsed();
+
+    let stage_start = Instant::now();
+    let ascii_text = image_to_ascii(&loaded_img, imgii_options)?;
+    timings.ascii = stage_start.elapsed();
+
+    let stage_start = Instant::now();
+    if imgii_options.skip_if_unchanged() {
+        write_if_changed(output_file_name, ascii_text.as_bytes(), imgii_options)?;
+    } else {
+        write_output_file(output_file_name, imgii_options, |file_writer| {
+            Ok(file_writer.write_all(ascii_text.as_bytes())?)
+        })?;
+    }
+    timings.encode = stage_start.elapsed();
+
+    Ok(imgii_options.timings().then_some(timings))
+}
+
+/// Converts an image (such as a PNG or JPEG) into colored ASCII text and saves the parsed grid of
+/// cells as JSON, without rendering it. Useful for web clients that draw the grid themselves,
+/// with their own fonts and effects. See [`grid::CellGrid::to_json`] for the layout of the JSON.
 ///
-/// // perform the conversion
-/// match convert_to_ascii_gif(
-///     input_file_name,
-///     output_file_name,
-///     &imgii_options,
-/// ) {
-///     Ok(_) => {
-///         // GIF was successfully saved
-///         /* ... */
-///     }
-///     Err(_) => {
-///         // failed, could not save GIF
-///         /* ... */
-///     }
-/// };
-/// # }
-/// ```
-pub fn convert_to_ascii_gif(
+/// NOTE: only the options used when converting the image to ASCII text (like the width and the
+/// cell color mode) apply. Render options, such as `grayscale`, are left to the renderer.
+///
+/// # Params
+/// - `input_file_name` - The input file name.
+/// - `output_file_name` - The output file name.
+/// - `imgii_options` - The `imgii` render options
+///
+/// # Returns
+/// - `Err` upon error, otherwise `Ok` holding the [`ConversionTimings`] if timings are enabled in
+///   the options. Nothing is rendered, so the render time is always zero.
+#[cfg(feature = "serde")]
+pub fn convert_to_grid_json(
     input_file_name: &str,
     output_file_name: &str,
     imgii_options: &ImgiiOptions,
-) -> Result<(), ImgiiError> {
-    let raw_frames = read_as_deconstructed_rendered_gif_vec(input_file_name, imgii_options)?;
+) -> Result<Option<ConversionTimings>, ImgiiError> {
+    imgii_options.check_cancelled()?;
+    let mut timings = ConversionTimings::default();
 
-    // create an image writer for each frame
-    let image_writers = raw_frames
-        .into_par_iter()
-        // filter out failed frames
-        .filter_map(|frame_part| frame_part)
-        .map(|frame_part| {
-            let (image_data, frame_metadata) = frame_part.into_frame_data();
-            (AsciiImageWriter::from_2d_vec(image_data), frame_metadata)
-        })
-        .collect::<Vec<_>>();
+    let stage_start = Instant::now();
+    let loaded_img = open_image(input_file_name)?;
+    let imgii_options = &*imgii_options.for_source(loaded_img.width(), loaded_img.height());
+    timings.decode = stage_start.elapsed();
 
-    let frames: Vec<Frame> = image_writers
-        .into_par_iter()
-        .filter_map(|(writer, frame_metadata)| match writer {
-            // let's just get rid of errors and try our best with what we've got
-            // NOTE: this will discard frames with errors.
-            Ok(writer) => Some((writer, frame_metadata)),
-            Err(_) => None,
-        })
-        .map(|(image_writer, frame_metadata)| {
-            // basically, we want to put the image data and the frame data back into a frame, so we
-            // can then use the image crate to build a new GIF from the new image!
-            Frame::from_parts(
-                image_writer.imagebuf.into(), // converts into its inner held type
-                frame_metadata.left(),
-                frame_metadata.top(),
-                frame_metadata.delay(),
-            )
-        })
-        .collect();
+    let stage_start = Instant::now();
+    let ascii_text = image_to_ascii(&loaded_img, imgii_options)?;
+    let grid_json = parse_ascii_grid(
+        &ascii_text,
+        imgii_options.tab_width(),
+        imgii_options.zero_width_handling(),
+    )?
+    .to_json()?;
+    timings.ascii = stage_start.elapsed();
+
+    let stage_start = Instant::now();
+    if imgii_options.skip_if_unchanged() {
+        write_if_changed(output_file_name, grid_json.as_bytes(), imgii_options)?;
+    } else {
+        write_output_file(output_file_name, imgii_options, |file_writer| {
+            Ok(file_writer.write_all(grid_json.as_bytes())?)
+        })?;
+    }
+    timings.encode = stage_start.elapsed();
 
-    let out_file = File::create(output_file_name)?;
-    let file_writer = BufWriter::new(out_file);
+    Ok(imgii_options.timings().then_some(timings))
+}
+
+/// Renders colored ASCII text, such as the contents of a file saved by [`convert_to_ansi_file`],
+/// into an ASCII PNG. The text goes through the same rendering as [`convert_to_ascii_png`], so
+/// rendering the ANSI-art file of an image with the same options gives the same PNG as converting
+/// the image directly.
+///
+/// NOTE: the options used when converting the image to ASCII text (like the width and the cell
+/// color mode) are already baked into the text. There's no source image, so `match_source_resolution`
+/// has no effect.
+///
+/// # Params
+/// - `ascii_text` - The colored ASCII text to render.
+/// - `output_file_name` - The output file name.
+/// - `imgii_options` - The `imgii` render options
+///
+/// # Returns
+/// - `Err` upon error, otherwise `Ok` holding the [`ConversionTimings`] if timings are enabled in
+///   the options. Nothing is decoded or converted to ASCII, so those times are always zero.
+pub fn convert_ansi_to_ascii_png(
+    ascii_text: &str,
+    output_file_name: &str,
+    imgii_options: &ImgiiOptions,
+) -> Result<Option<ConversionTimings>, ImgiiError> {
+    imgii_options.check_cancelled()?;
+    let mut timings = ConversionTimings::default();
+
+    let stage_start = Instant::now();
+    let lines = render_ascii_generic(imgii_options, ascii_text.to_owned())?;
+    save_rendered_png(
+        lines,
+        output_file_name,
+        None,
+        None,
+        imgii_options,
+        stage_start,
+        &mut timings,
+    )?;
 
-    let mut gif_encoder = GifEncoder::new(file_writer);
+    Ok(imgii_options.timings().then_some(timings))
+}
+
+/// Renders already converted ASCII text into a PNG, writing each grid row to `writer` as soon as
+/// it has been rendered rather than waiting for the whole image. Useful for very large images,
+/// where the latency and memory of building the whole image first matter.
+///
+/// At most one grid row of rendered pixels is held in memory at once, along with the glyph cache
+/// (unless it's disabled). Because the whole image is never built, the options that change the
+/// stitched image (`pad_to_multiple`, `bloom`, `scanlines`, `scale_output`, `fixed_grid` and
+/// `opacity_mask`) are not applied. The PNG is always written with an alpha channel, so options
+/// asking for an indexed PNG (`png_palette` or `optimize_size`) or one without an alpha channel
+/// (`png_alpha`) are an error. `bit_depth`, `png_compression` and `embed_metadata` are honored.
+///
+/// # Params
+/// - `ascii_text` - The colored ASCII text to render, as produced by `RASCII`.
+/// - `writer` - The writer to write the PNG to. It is flushed after every grid row.
+/// - `imgii_options` - The `imgii` render options
+///
+/// # Returns
+/// - `Err` upon error, otherwise `Ok`.
+pub fn stream_ascii_to_png<W: Write>(
+    ascii_text: &str,
+    writer: W,
+    imgii_options: &ImgiiOptions,
+) -> Result<(), ImgiiError> {
+    stream_ascii_as_png(ascii_text, writer, imgii_options)
+}
 
-    // TODO: allow user to choose number of repeats?
-    let err = gif_encoder.set_repeat(image::codecs::gif::Repeat::Infinite);
-    if let Err(err) = err {
-        // repeat couldn't be set properly
-        let err = anyhow::Error::new(err);
-        return Err(err.into());
+/// Converts an image (such as a PNG or JPEG) into a small ASCII image, as a fast preview of the full
+/// conversion. The image goes through the same pipeline as [`convert_to_ascii_png`], but the
+/// configured width and height are replaced with a grid of at most `max_dim` characters in each
+/// direction, keeping the proportions of the image.
+///
+/// # Params
+/// - `input_file_name` - The input file name.
+/// - `max_dim` - The maximum width and height of the grid, in characters. Must be non-zero.
+/// - `imgii_options` - The `imgii` render options
+///
+/// # Returns
+/// - The rendered thumbnail upon success, `Err` otherwise.
+pub fn render_thumbnail(
+    input_file_name: &str,
+    max_dim: u32,
+    imgii_options: &ImgiiOptions,
+) -> Result<DynamicImage, ImgiiError> {
+    if max_dim == 0 {
+        return Err(ImgiiError::InvalidArgument);
+    }
+    let loaded_img = open_image(input_file_name)?;
+    if loaded_img.width() == 0 || loaded_img.height() == 0 {
+        return Err(ImgiiError::InvalidArgument);
     }
 
-    // FUTURE: the longest part of the GIF creation process is encoding...is there any way to speed
-    // it up?
+    // the width of the grid compared to its height, accounting for cells being taller than they
+    // are wide
+    let (char_width, char_height) = calculate_char_dimensions(imgii_options);
+    let grid_ratio = (f64::from(loaded_img.width()) * f64::from(char_height))
+        / (f64::from(loaded_img.height()) * f64::from(char_width));
+    let (width, height) = if grid_ratio >= 1.0 {
+        (max_dim, ((f64::from(max_dim) / grid_ratio) as u32).max(1))
+    } else {
+        (((f64::from(max_dim) * grid_ratio) as u32).max(1), max_dim)
+    };
 
-    // encode the frames
-    match gif_encoder.encode_frames(frames) {
-        Err(err) => {
-            let err = anyhow::Error::new(err);
-            Err(err.into())
+    let thumbnail_options = imgii_options.with_grid_size(width, height);
+    let ascii_text = image_to_ascii(&loaded_img, &thumbnail_options)?;
+    let lines = render_ascii_generic(&thumbnail_options, ascii_text)?;
+    let final_image_writer = AsciiImageWriter::from_2d_vec(lines, &thumbnail_options)?;
+    Ok(DynamicImage::ImageRgba8(final_image_writer.imagebuf.into()))
+}
+
+/// Converts an image (such as a PNG or JPEG) into an ASCII image, returning both the decoded source
+/// image and the rendered ASCII image, such as for showing them side by side. The source is only
+/// decoded once, so it's exactly the image that was converted to ASCII (before any preprocessing
+/// from the options, such as `edge_detect`).
+///
+/// The rendered image is the same image [`convert_to_ascii_png`] would save, following the bit
+/// depth and alpha handling in the options.
+///
+/// # Params
+/// - `input_file_name` - The input file name.
+/// - `imgii_options` - The `imgii` render options, holding the RASCII options for converting to
+///   ASCII.
+///
+/// # Returns
+/// - The decoded source image, followed by the rendered ASCII image upon success, `Err` otherwise.
+pub fn convert_with_original(
+    input_file_name: &str,
+    imgii_options: &ImgiiOptions,
+) -> Result<(DynamicImage, DynamicImage), ImgiiError> {
+    imgii_options.check_cancelled()?;
+    let loaded_img = open_image(input_file_name)?;
+    let imgii_options = &*imgii_options.for_source(loaded_img.width(), loaded_img.height());
+    let ascii_text = image_to_ascii(&loaded_img, imgii_options)?;
+    let lines = render_ascii_generic(imgii_options, ascii_text)?;
+    let rendered_img = render_png_image(lines, imgii_options)?;
+
+    Ok((loaded_img, rendered_img))
+}
+
+/// Converts an image (such as a PNG or JPEG) into ASCII and renders it, returning statistics
+/// about the rendered cells instead of saving the image. Useful for finding which glyphs a font
+/// subset needs to contain.
+///
+/// # Params
+/// - `input_file_name` - The input file name.
+/// - `imgii_options` - The `imgii` render options
+///
+/// # Returns
+/// - The [`RenderStats`] for the rendered image upon success, `Err` otherwise.
+pub fn render_stats(
+    input_file_name: &str,
+    imgii_options: &ImgiiOptions,
+) -> Result<RenderStats, ImgiiError> {
+    let lines = parse_ascii_to_2d_png_vec(input_file_name, imgii_options)?;
+    Ok(lines.stats)
+}
+
+/// Converts an image (such as a PNG or JPEG) into ASCII and renders it, saving each unique
+/// rendered glyph cell packed into a sprite sheet image instead of the full image. Useful for
+/// front-ends that re-composite the ASCII image themselves.
+///
+/// Whitespace cells are never included. If there are no cells to lay out, such as when the glyph
+/// cache is disabled or the image is entirely whitespace, [`ImgiiError::InvalidArgument`] is
+/// returned and nothing is saved.
+///
+/// # Params
+/// - `input_file_name` - The input file name.
+/// - `output_file_name` - The output file name of the sprite sheet image.
+/// - `imgii_options` - The `imgii` render options
+///
+/// # Returns
+/// - The [`SpriteSheetIndex`] describing where each cell is in the sprite sheet upon success,
+///   `Err` otherwise. With the `serde` feature, this can be saved as JSON.
+pub fn write_glyph_sprite_sheet(
+    input_file_name: &str,
+    output_file_name: &str,
+    imgii_options: &ImgiiOptions,
+) -> Result<SpriteSheetIndex, ImgiiError> {
+    let lines = parse_ascii_to_2d_png_vec(input_file_name, imgii_options)?;
+    let (cell_width, cell_height) = calculate_char_dimensions(imgii_options);
+    let (sprite_sheet, index) = build_sprite_sheet(&lines.glyph_cache, cell_width, cell_height)?;
+
+    prepare_output_dir(output_file_name, imgii_options)?;
+    sprite_sheet
+        .save(output_file_name)
+        .map_err(|err| -> ImgiiError { anyhow::Error::new(err).into() })?;
+    Ok(index)
+}
+
+/// Converts an image (such as a PNG or JPEG) into ASCII and renders it, writing each unique
+/// rendered cell to its own PNG in `output_dir` instead of the full image. Each file is named by
+/// the color and string of its cell, such as `ff8000_a.png`, followed by its cell background if
+/// it has one, such as `ff8000_a_bg102030.png`. Useful for tiling and CSS sprite
+/// workflows.
+///
+/// With the `serde` feature, the [`CellManifest`] is also saved to `manifest.json` in `output_dir`.
+///
+/// NOTE: only cells from the glyph cache are exported, so whitespace cells are never exported and
+/// nothing is exported if the glyph cache is disabled.
+///
+/// # Params
+/// - `input_file_name` - The input file name.
+/// - `output_dir` - The directory to write the cells to. Created if it doesn't exist.
+/// - `imgii_options` - The `imgii` render options
+///
+/// # Returns
+/// - The [`CellManifest`] mapping each cell of the image to its file upon success, `Err`
+///   otherwise.
+pub fn export_cells(
+    input_file_name: &str,
+    output_dir: &str,
+    imgii_options: &ImgiiOptions,
+) -> Result<CellManifest, ImgiiError> {
+    let lines = parse_ascii_to_2d_png_vec(input_file_name, imgii_options)?;
+    let (cell_width, cell_height) = calculate_char_dimensions(imgii_options);
+    let output_dir = Path::new(output_dir);
+    let manifest = write_cells(&lines, output_dir, cell_width, cell_height)?;
+
+    #[cfg(feature = "serde")]
+    manifest.save_json(&output_dir.join("manifest.json").to_string_lossy())?;
+    Ok(manifest)
+}
+
+/// Converts an image (such as a PNG or JPEG) into ASCII and renders it, returning each distinct
+/// rendered cell once along with a grid of indices into them, instead of the full image. Useful
+/// for layout tools that place the cells themselves.
+///
+/// NOTE: cells are only shared when they come from the glyph cache, so disabling the glyph cache
+/// or enabling jitter makes most cells unique.
+///
+/// # Params
+/// - `input_file_name` - The input file name.
+/// - `imgii_options` - The `imgii` render options
+///
+/// # Returns
+/// - The [`IndexedGrid`] for the rendered image upon success, `Err` otherwise.
+pub fn render_indexed_grid(
+    input_file_name: &str,
+    imgii_options: &ImgiiOptions,
+) -> Result<IndexedGrid, ImgiiError> {
+    let lines = parse_ascii_to_2d_png_vec(input_file_name, imgii_options)?;
+    Ok(build_indexed_grid(&lines))
+}
+
+/// Converts a GIF into an ASCII GIF.
+/// It does this by first converting the image into colored ASCII text,
+/// then renders the ASCII text as an image.
+///
+/// Frame delays are rounded to the nearest centisecond, which is the precision of GIF delays, so
+/// output timing is centisecond-precise.
+///
+/// NOTE: with the `wasm` feature, frames are converted one at a time, so large GIFs can be slow.
+///
+/// # Params
+/// - `input_file_name` - The input file name.
+/// - `output_file_name` - The output file name.
+/// - `imgii_options` - The `imgii` render options
+///
+/// # Returns
+/// - `Err(())` upon error, otherwise `Ok` holding the [`ConversionTimings`] if timings are enabled
+///   in the options.
+///
+/// # Example
+///
+/// ```no_run
+/// // Simple example showing off basic usage.
+///
+/// use imgii::{
+///     convert_to_ascii_gif,
+///     options::{Charset, ImgiiOptionsBuilder, RasciiOptions, from_enum},
+/// };
+///
+/// # fn main() {
+/// let input_file_name = "the_input_image.gif";
+/// let output_file_name = "the_output_image.gif";
+///
+/// // imgii options (for converting image to ASCII image)
+/// let imgii_options = ImgiiOptionsBuilder::new()
+///     .background(true) // set black background behind GIF
+///     .width(50) // keeps the aspect ratio but is 50 pixels wide
+///     .build()
+///     .unwrap();
+///
+/// // perform the conversion
+/// match convert_to_ascii_gif(
+///     input_file_name,
+///     output_file_name,
+///     &imgii_options,
+/// ) {
+///     Ok(_) => {
+///         // GIF was successfully saved
+///         /* ... */
+///     }
+///     Err(_) => {
+///         // failed, could not save GIF
+///         /* ... */
+///     }
+/// };
+/// # }
+/// ```
+pub fn convert_to_ascii_gif(
+    input_file_name: &str,
+    output_file_name: &str,
+    imgii_options: &ImgiiOptions,
+) -> Result<Option<ConversionTimings>, ImgiiError> {
+    let (timings, _) = convert_gif(
+        input_file_name,
+        output_file_name,
+        imgii_options,
+        gif::Repeat::Infinite,
+    )?;
+    Ok(imgii_options.timings().then_some(timings))
+}
+
+/// Restyles a GIF, converting it into an ASCII GIF with a different charset, font or colors while
+/// keeping its timing and how many times it loops. This works like [`convert_to_ascii_gif`], but
+/// the loop count is read from the input GIF instead of always looping forever.
+///
+/// Every frame is normalized to the logical screen size of the input GIF before converting, so
+/// the proportions of the GIF are kept. To keep its exact size in pixels, enable
+/// `match_source_resolution` in the options.
+///
+/// NOTE: performs a best-effort conversion, frames that fail to convert are skipped. GIFs without
+/// a loop count are played once, like the input.
+///
+/// # Params
+/// - `input_file_name` - The input file name.
+/// - `output_file_name` - The output file name.
+/// - `imgii_options` - The `imgii` render options, holding the new style.
+///
+/// # Returns
+/// - `Err(())` upon error, otherwise `Ok` holding the [`ConversionTimings`] if timings are enabled
+///   in the options.
+pub fn restyle_gif(
+    input_file_name: &str,
+    output_file_name: &str,
+    imgii_options: &ImgiiOptions,
+) -> Result<Option<ConversionTimings>, ImgiiError> {
+    let repeat = read_gif_repeat(input_file_name)?;
+    let (timings, _) = convert_gif(input_file_name, output_file_name, imgii_options, repeat)?;
+    Ok(imgii_options.timings().then_some(timings))
+}
+
+/// Converts a GIF into ASCII frames in the background, sending each finished frame over a channel
+/// as soon as it's rendered, in the order of the source GIF. Useful for showing frames in a live UI
+/// before the whole GIF is converted, or for encoding them some other way.
+///
+/// The conversion runs on a background thread, converting and rendering frames in parallel on the
+/// rayon thread pool in batches. Every frame of a batch is sent before the next batch starts.
+/// Dropping the receiver stops the conversion after the current batch, and so does setting the
+/// cancel flag of the options, which sends [`ImgiiError::Cancelled`].
+///
+/// NOTE: performs a best-effort conversion, so a frame that fails to convert is sent as an `Err`
+/// and the frames after it are still sent. If the GIF can't be read at all, a single `Err` is sent.
+/// The channel holds at most one batch of frames, so the conversion waits for frames to be
+/// received rather than piling them up in memory.
+///
+/// # Params
+/// - `input_file_name` - The input file name.
+/// - `imgii_options` - The `imgii` render options
+///
+/// # Returns
+/// - The receiving end of the channel, which is closed once every frame has been sent.
+#[cfg(not(feature = "wasm"))]
+pub fn convert_to_ascii_gif_streaming(
+    input_file_name: &str,
+    imgii_options: &ImgiiOptions<'static>,
+) -> Receiver<Result<(DynamicImage, Delay), ImgiiError>> {
+    // render as many frames at once as we have threads to render them with
+    let batch_size = rayon::current_num_threads().max(1);
+    let (sender, receiver) = std::sync::mpsc::sync_channel(batch_size);
+    let input_file_name = input_file_name.to_string();
+    let imgii_options = imgii_options.clone();
+    // NOTE: not spawned on the rayon thread pool, since waiting for frames to be received would
+    // block one of the threads rendering them
+    std::thread::spawn(move || {
+        if let Err(err) = stream_gif_frames(&input_file_name, &imgii_options, batch_size, &sender) {
+            // the receiver may already be gone, in which case nobody needs the error
+            let _ = sender.send(Err(err));
+        }
+    });
+    receiver
+}
+
+/// Converts a GIF into ASCII frames, sending each frame in order as soon as its batch has been
+/// rendered. Each batch is converted to ASCII and rendered only once the batch before it has been
+/// sent, and nothing more is converted once the receiver is dropped.
+///
+/// * `input_file_name`: The input file name.
+/// * `imgii_options`: The imgii render options.
+/// * `batch_size`: The number of frames to convert at once.
+/// * `sender`: The sender to send each frame, or the error converting it, over.
+#[cfg(not(feature = "wasm"))]
+fn stream_gif_frames(
+    input_file_name: &str,
+    imgii_options: &ImgiiOptions,
+    batch_size: usize,
+    sender: &SyncSender<Result<(DynamicImage, Delay), ImgiiError>>,
+) -> Result<(), ImgiiError> {
+    let deconstructed_gif = read_deconstructed_gif(input_file_name)?;
+    // every frame is normalized to the size of the GIF, so any frame gives us the source size
+    let imgii_options = &*match deconstructed_gif.first() {
+        Some((image, _)) => imgii_options.for_source(image.width(), image.height()),
+        None => Cow::Borrowed(imgii_options),
+    };
+
+    let mut frames = drop_excess_frames(deconstructed_gif, imgii_options).into_iter();
+    loop {
+        // don't start on another batch if we've been cancelled since the last one
+        imgii_options.check_cancelled()?;
+        let batch: Vec<_> = frames.by_ref().take(batch_size).collect();
+        if batch.is_empty() {
+            return Ok(());
+        }
+
+        let ascii_text = frames_to_ascii(batch, imgii_options);
+        let raw_frames = render_deconstructed_ascii(ascii_text, imgii_options);
+        imgii_options.check_cancelled()?;
+        for frame in stitch_rendered_frames(raw_frames, imgii_options) {
+            let frame = frame
+                .map(|frame| {
+                    let delay = frame.delay();
+                    (DynamicImage::ImageRgba8(frame.into_buffer()), delay)
+                })
+                .map_err(|failure| failure.error);
+            if sender.send(frame).is_err() {
+                // nobody is listening anymore, so stop converting
+                return Ok(());
+            }
         }
-        _ => Ok(()),
+    }
+}
+
+/// Converts a GIF into an ASCII GIF like [`convert_to_ascii_gif`], but reports exactly which
+/// frames failed to convert and why, instead of only logging a warning for each of them. Useful for
+/// alerting on partial successes.
+///
+/// NOTE: performs a best-effort conversion, frames that fail to convert are skipped. The GIF is
+/// still saved if some frames fail, so check [`GifConversionReport::is_complete`].
+///
+/// # Params
+/// - `input_file_name` - The input file name.
+/// - `output_file_name` - The output file name.
+/// - `imgii_options` - The `imgii` render options
+///
+/// # Returns
+/// - The [`GifConversionReport`] of the conversion upon success, `Err` if the GIF couldn't be
+///   read or saved.
+pub fn convert_to_ascii_gif_with_report(
+    input_file_name: &str,
+    output_file_name: &str,
+    imgii_options: &ImgiiOptions,
+) -> Result<GifConversionReport, ImgiiError> {
+    let (_, report) = convert_gif(
+        input_file_name,
+        output_file_name,
+        imgii_options,
+        gif::Repeat::Infinite,
+    )?;
+    Ok(report)
+}
+
+/// Converts a GIF into an ASCII GIF, timing each stage and reporting the frames that failed.
+///
+/// * `input_file_name`: The input file name.
+/// * `output_file_name`: The output file name.
+/// * `imgii_options`: The imgii render options.
+/// * `repeat`: How many times the output GIF repeats.
+fn convert_gif(
+    input_file_name: &str,
+    output_file_name: &str,
+    imgii_options: &ImgiiOptions,
+    repeat: gif::Repeat,
+) -> Result<(ConversionTimings, GifConversionReport), ImgiiError> {
+    let mut timings = ConversionTimings::default();
+
+    let stage_start = Instant::now();
+    let deconstructed_gif = read_deconstructed_gif(input_file_name)?;
+    // every frame is normalized to the size of the GIF, so any frame gives us the source size
+    let imgii_options = &*match deconstructed_gif.first() {
+        Some((image, _)) => imgii_options.for_source(image.width(), image.height()),
+        None => Cow::Borrowed(imgii_options),
+    };
+    timings.decode = stage_start.elapsed();
+
+    let stage_start = Instant::now();
+    let ascii_text = deconstructed_gif_to_ascii(deconstructed_gif, imgii_options);
+    timings.ascii = stage_start.elapsed();
+
+    #[cfg(not(feature = "wasm"))]
+    if let Some(depth) = imgii_options.gif_pipeline_depth() {
+        let report = render_and_save_gif_pipelined(
+            ascii_text,
+            output_file_name,
+            imgii_options,
+            depth,
+            repeat,
+            &mut timings,
+        )?;
+        return Ok((timings, report));
+    }
+
+    let stage_start = Instant::now();
+    let render_progress =
+        ProgressTracker::new(imgii_options, ProgressStage::Render, ascii_text.len());
+    let raw_frames = render_deconstructed_ascii_with_progress(
+        ascii_text,
+        imgii_options,
+        render_progress.as_ref(),
+    );
+    let (frames, report) = split_frame_failures(stitch_rendered_frames(raw_frames, imgii_options));
+    imgii_options.check_cancelled()?;
+    timings.render = stage_start.elapsed();
+
+    let stage_start = Instant::now();
+    let encode_progress = ProgressTracker::new(imgii_options, ProgressStage::Encode, frames.len());
+    save_gif_with_repeat(
+        frames,
+        output_file_name,
+        imgii_options,
+        repeat,
+        encode_progress.as_ref(),
+    )?;
+    timings.encode = stage_start.elapsed();
+
+    Ok((timings, report))
+}
+
+/// Renders the ASCII text of each frame and encodes the frames as a GIF at the same time. Frames
+/// are rendered in parallel in batches and sent in order to a single encoder thread through a
+/// bounded channel, so encoding overlaps rendering.
+///
+/// * `ascii_text`: The ASCII text of each frame, with its frame metadata.
+/// * `output_file_name`: The output file name.
+/// * `imgii_options`: The imgii render options.
+/// * `depth`: The number of rendered frames that may wait to be encoded.
+/// * `repeat`: How many times the output GIF repeats.
+/// * `timings`: The timings to record the render and encode stages in.
+#[cfg(not(feature = "wasm"))]
+fn render_and_save_gif_pipelined(
+    ascii_text: Vec<Result<NonRenderedFramePart, FrameFailure>>,
+    output_file_name: &str,
+    imgii_options: &ImgiiOptions,
+    depth: usize,
+    repeat: gif::Repeat,
+    timings: &mut ConversionTimings,
+) -> Result<GifConversionReport, ImgiiError> {
+    let stage_start = Instant::now();
+    // render as many frames at once as we have threads to render them with
+    let batch_size = rayon::current_num_threads().max(1);
+    let render_progress =
+        ProgressTracker::new(imgii_options, ProgressStage::Render, ascii_text.len());
+    // frames that fail to render are taken away from the encode total as each batch finishes
+    let encode_progress =
+        ProgressTracker::new(imgii_options, ProgressStage::Encode, ascii_text.len());
+
+    std::thread::scope(|scope| {
+        let (sender, receiver) = std::sync::mpsc::sync_channel::<Frame>(depth);
+        let encode_progress = encode_progress.as_ref();
+        let encoder = scope.spawn(move || {
+            save_gif_with_repeat(
+                receiver,
+                output_file_name,
+                imgii_options,
+                repeat,
+                encode_progress,
+            )
+        });
+
+        let mut report = GifConversionReport::default();
+        let mut ascii_text = ascii_text.into_iter();
+        let render_result = loop {
+            let batch: Vec<_> = ascii_text.by_ref().take(batch_size).collect();
+            if batch.is_empty() {
+                break Ok(());
+            }
+
+            let raw_frames = render_deconstructed_ascii_with_progress(
+                batch,
+                imgii_options,
+                render_progress.as_ref(),
+            );
+            let (frames, batch_report) =
+                split_frame_failures(stitch_rendered_frames(raw_frames, imgii_options));
+            if let Some(encode_progress) = encode_progress {
+                encode_progress.frames_dropped(batch_report.failures.len());
+            }
+            report.merge(batch_report);
+            if let Err(err) = imgii_options.check_cancelled() {
+                break Err(err);
+            }
+            // the encoder only stops receiving once it has failed, which is reported below
+            if frames
+                .into_iter()
+                .try_for_each(|frame| sender.send(frame))
+                .is_err()
+            {
+                break Ok(());
+            }
+        };
+        timings.render = stage_start.elapsed();
+
+        // let the encoder finish the GIF
+        drop(sender);
+        let encode_start = Instant::now();
+        let encode_result = encoder.join().unwrap_or_else(|_| {
+            Err(ImageError::Render {
+                reason: String::from("the GIF encoder thread panicked"),
+            }
+            .into())
+        });
+        timings.encode = encode_start.elapsed();
+
+        render_result?;
+        encode_result?;
+        Ok(report)
+    })
+}
+
+/// Converts already decoded frames into an ASCII GIF. Each frame is converted into colored ASCII
+/// text in parallel, then rendered and encoded, just like [`convert_to_ascii_gif`]. Useful when
+/// frames are decoded or processed outside of imgii, such as video frames.
+///
+/// NOTE: performs a best-effort conversion, frames that fail to convert are skipped.
+///
+/// # Params
+/// - `frames` - The frames to convert, each followed by its delay.
+/// - `output_file_name` - The output file name.
+/// - `imgii_options` - The `imgii` render options
+///
+/// # Returns
+/// - `Err(())` upon error, `Ok(())` otherwise.
+pub fn frames_to_ascii_gif(
+    frames: Vec<(DynamicImage, Delay)>,
+    output_file_name: &str,
+    imgii_options: &ImgiiOptions,
+) -> Result<(), ImgiiError> {
+    let deconstructed_frames = frames
+        .into_iter()
+        .enumerate()
+        .map(|(index, (image, delay))| (image, FrameMetadata::new(index, 0, 0, delay)))
+        .collect();
+    let ascii_text = deconstructed_gif_to_ascii(deconstructed_frames, imgii_options);
+    let raw_frames = render_deconstructed_ascii(ascii_text, imgii_options);
+    let (frames, _) = split_frame_failures(stitch_rendered_frames(raw_frames, imgii_options));
+    imgii_options.check_cancelled()?;
+    save_gif(frames, output_file_name, imgii_options)
+}
+
+/// Converts a GIF into a film strip PNG, with every ASCII frame stacked vertically in order. Useful
+/// for showing every frame of an animation at once, such as in documentation.
+///
+/// NOTE: performs a best-effort conversion, frames that fail to convert are skipped. Frames
+/// narrower than the widest frame are padded on the right with transparent pixels.
+///
+/// # Params
+/// - `input_file_name` - The input file name.
+/// - `output_file_name` - The output file name.
+/// - `imgii_options` - The `imgii` render options
+/// - `separator` - The height in pixels and the color of a line drawn between each frame, if any.
+///
+/// # Returns
+/// - `Err(())` upon error, `Ok(())` otherwise.
+pub fn convert_gif_to_filmstrip_png(
+    input_file_name: &str,
+    output_file_name: &str,
+    imgii_options: &ImgiiOptions,
+    separator: Option<(u32, Rgba<u8>)>,
+) -> Result<(), ImgiiError> {
+    let frames = render_gif_frames_to_stack(input_file_name, imgii_options)?;
+    let filmstrip = stack_vertically(&frames, separator)?;
+    save_png(
+        &DynamicImage::ImageRgba8(filmstrip),
+        output_file_name,
+        None,
+        Some(input_file_name),
+        imgii_options,
+    )
+}
+
+/// Converts a GIF into a horizontal film strip PNG, with every ASCII frame placed side by side in
+/// order, from left to right.
+///
+/// NOTE: performs a best-effort conversion, frames that fail to convert are skipped. Frames
+/// shorter than the tallest frame are padded at the bottom with transparent pixels.
+///
+/// # Params
+/// - `input_file_name` - The input file name.
+/// - `output_file_name` - The output file name.
+/// - `imgii_options` - The `imgii` render options
+/// - `separator` - The width in pixels and the color of a line drawn between each frame, if any.
+///
+/// # Returns
+/// - `Err(())` upon error, `Ok(())` otherwise.
+pub fn convert_gif_to_horizontal_filmstrip_png(
+    input_file_name: &str,
+    output_file_name: &str,
+    imgii_options: &ImgiiOptions,
+    separator: Option<(u32, Rgba<u8>)>,
+) -> Result<(), ImgiiError> {
+    let frames = render_gif_frames_to_stack(input_file_name, imgii_options)?;
+    let filmstrip = stack_horizontally(&frames, separator)?;
+    save_png(
+        &DynamicImage::ImageRgba8(filmstrip),
+        output_file_name,
+        None,
+        Some(input_file_name),
+        imgii_options,
+    )
+}
+
+/// Renders every frame of a GIF to be stacked into a single image, skipping frames that fail to
+/// convert. Fails if no frames could be converted, since there is nothing to stack.
+///
+/// * `input_file_name`: The input file name.
+/// * `imgii_options`: The imgii render options.
+fn render_gif_frames_to_stack(
+    input_file_name: &str,
+    imgii_options: &ImgiiOptions,
+) -> Result<Vec<InternalImage>, ImgiiError> {
+    let deconstructed_gif = read_deconstructed_gif(input_file_name)?;
+    let ascii_text = deconstructed_gif_to_ascii(deconstructed_gif, imgii_options);
+    let raw_frames = render_deconstructed_ascii(ascii_text, imgii_options);
+    let (frames, _) = split_frame_failures(stitch_rendered_frames(raw_frames, imgii_options));
+    imgii_options.check_cancelled()?;
+    let frames = frames
+        .into_iter()
+        .map(|frame| frame.into_buffer())
+        .collect::<Vec<_>>();
+    if frames.is_empty() {
+        // no frames to stack
+        return Err(ImgiiError::InvalidArgument);
+    }
+    Ok(frames)
+}
+
+/// Converts a GIF into a contact sheet PNG, with every ASCII frame laid out in a grid and its
+/// frame index labeled beneath it. Useful for checking every frame of a converted GIF at once.
+///
+/// NOTE: performs a best-effort conversion, frames that fail to convert are skipped, but every
+/// label is the index of the frame in the input GIF. Empty space, such as the rest of the last row,
+/// is filled with the background color if the background is enabled, otherwise it's transparent.
+///
+/// # Params
+/// - `input_file_name` - The input file name.
+/// - `output_file_name` - The output file name.
+/// - `columns` - The number of frames in each row of the grid.
+/// - `imgii_options` - The `imgii` render options, whose font is also used for the labels.
+///
+/// # Returns
+/// - `Err(())` upon error, `Ok(())` otherwise.
+pub fn gif_contact_sheet(
+    input_file_name: &str,
+    output_file_name: &str,
+    columns: u32,
+    imgii_options: &ImgiiOptions,
+) -> Result<(), ImgiiError> {
+    if columns == 0 {
+        // can't lay out frames without any columns
+        return Err(ImgiiError::InvalidArgument);
+    }
+
+    let deconstructed_gif = read_deconstructed_gif(input_file_name)?;
+    let ascii_text = deconstructed_gif_to_ascii(deconstructed_gif, imgii_options);
+    let (rendered_frames, _) =
+        split_frame_failures(render_deconstructed_ascii(ascii_text, imgii_options));
+    let (frames, _) = split_frame_failures(
+        rendered_frames
+            .into_par_iter()
+            .map(|frame_part| {
+                let (image_data, frame_metadata) = frame_part.into_frame_data();
+                let index = frame_metadata.index();
+                AsciiImageWriter::from_2d_vec(image_data, imgii_options)
+                    .map(|image_writer| (index, image_writer.imagebuf.into()))
+                    .map_err(|err| FrameFailure::new(index, err))
+            })
+            .collect(),
+    );
+    imgii_options.check_cancelled()?;
+    if frames.is_empty() {
+        // no frames to lay out
+        return Err(ImgiiError::InvalidArgument);
+    }
+
+    let contact_sheet = build_contact_sheet(&frames, columns, imgii_options)?;
+    save_png(
+        &DynamicImage::ImageRgba8(contact_sheet),
+        output_file_name,
+        None,
+        Some(input_file_name),
+        imgii_options,
+    )
+}
+
+/// Converts a GIF into a CSS sprite animation, so the ASCII animation can be played on the web
+/// without JavaScript or a GIF. Every ASCII frame is packed into a single atlas PNG, and a CSS file
+/// is generated that animates an element of the class `class_name` through the frames with
+/// `@keyframes` named `<class_name>-frames`, keeping the delay of each frame.
+///
+/// The atlas is a single row of frames, left to right in order, each in a slot the size of the
+/// largest frame. Frame `i` is at x = `i * frame_width`, so it's shown with
+/// `background-position: -<i * frame_width>px 0`. The CSS references the atlas by its file name,
+/// so it must be served from the same directory as the CSS, otherwise the `url()` in the CSS
+/// needs to be adjusted. Frames without a delay are shown for 100ms, like browsers do for GIFs.
+///
+/// NOTE: performs a best-effort conversion, frames that fail to convert are skipped.
+///
+/// # Params
+/// - `input_file_name` - The input file name.
+/// - `output_png_file_name` - The output file name of the atlas PNG.
+/// - `output_css_file_name` - The output file name of the CSS.
+/// - `class_name` - The CSS class of the animated element. Must be a valid CSS identifier made of
+///   ASCII letters, digits, `-` and `_`, not starting with a digit.
+/// - `imgii_options` - The `imgii` render options
+///
+/// # Returns
+/// - `Err(())` upon error, `Ok(())` otherwise.
+pub fn convert_gif_to_css_sprite(
+    input_file_name: &str,
+    output_png_file_name: &str,
+    output_css_file_name: &str,
+    class_name: &str,
+    imgii_options: &ImgiiOptions,
+) -> Result<(), ImgiiError> {
+    let is_valid_class_name = class_name
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        && class_name
+            .chars()
+            .next()
+            .is_some_and(|c| !c.is_ascii_digit());
+    if !is_valid_class_name {
+        return Err(ImageError::InvalidParameter {
+            parameter_name: String::from("class_name"),
+        }
+        .into());
+    }
+
+    let deconstructed_gif = read_deconstructed_gif(input_file_name)?;
+    let ascii_text = deconstructed_gif_to_ascii(deconstructed_gif, imgii_options);
+    let raw_frames = render_deconstructed_ascii(ascii_text, imgii_options);
+    let (frames, _) = split_frame_failures(stitch_rendered_frames(raw_frames, imgii_options));
+    imgii_options.check_cancelled()?;
+    let frames = frames
+        .into_iter()
+        .map(|frame| {
+            let delay = Duration::from(frame.delay());
+            (frame.into_buffer(), delay)
+        })
+        .collect::<Vec<_>>();
+    if frames.is_empty() {
+        // no frames to pack
+        return Err(ImgiiError::InvalidArgument);
+    }
+
+    let atlas_url = Path::new(output_png_file_name).file_name().map_or_else(
+        || output_png_file_name.into(),
+        |name| name.to_string_lossy(),
+    );
+    let (atlas, css) = build_css_sprite(&frames, &atlas_url, class_name);
+    save_png(
+        &DynamicImage::ImageRgba8(atlas),
+        output_png_file_name,
+        None,
+        Some(input_file_name),
+        imgii_options,
+    )?;
+    if imgii_options.skip_if_unchanged() {
+        write_if_changed(output_css_file_name, css.as_bytes(), imgii_options)
+    } else {
+        write_output_file(output_css_file_name, imgii_options, |file_writer| {
+            Ok(file_writer.write_all(css.as_bytes())?)
+        })
+    }
+}
+
+/// Converts an image (such as a PNG or JPEG) into an ASCII GIF that zooms in, rendering the same
+/// image at each grid size in order. Useful for a "resolution increasing" effect.
+///
+/// NOTE: frames are centered and padded with transparent pixels to the size of the largest frame,
+/// rather than stretched, so the glyphs stay the same size in every frame.
+///
+/// # Params
+/// - `input_file_name` - The input file name.
+/// - `output_file_name` - The output file name.
+/// - `imgii_options` - The `imgii` render options
+/// - `sizes` - The (width, height) of the ASCII grid for each frame, in characters.
+/// - `delay_ms` - The delay of each frame, in milliseconds.
+///
+/// # Returns
+/// - `Err(())` upon error, `Ok(())` otherwise.
+pub fn zoom_gif(
+    input_file_name: &str,
+    output_file_name: &str,
+    imgii_options: &ImgiiOptions,
+    sizes: Vec<(u32, u32)>,
+    delay_ms: u32,
+) -> Result<(), ImgiiError> {
+    if sizes.is_empty() {
+        // no frames to render
+        return Err(ImgiiError::InvalidArgument);
+    }
+    let loaded_img = open_image(input_file_name)?;
+
+    // render the image at every size
+    let rendered_frames = sizes
+        .into_par_iter()
+        .map(|(width, height)| {
+            let frame_options = imgii_options.with_grid_size(width, height);
+            let ascii_text = image_to_ascii(&loaded_img, &frame_options)?;
+            let lines = render_ascii_generic(&frame_options, ascii_text)?;
+            let image_writer = AsciiImageWriter::from_2d_vec(lines, &frame_options)?;
+            Ok(image::RgbaImage::from(image_writer.imagebuf))
+        })
+        .collect::<Result<Vec<_>, ImgiiError>>()?;
+
+    // pad every frame to the largest frame, keeping it centered
+    let canvas_width = rendered_frames.iter().map(|frame| frame.width()).max();
+    let canvas_height = rendered_frames.iter().map(|frame| frame.height()).max();
+    let (Some(canvas_width), Some(canvas_height)) = (canvas_width, canvas_height) else {
+        return Err(ImgiiError::InvalidArgument);
+    };
+    let delay = quantize_to_centiseconds(Delay::from_numer_denom_ms(delay_ms, 1));
+    let frames = rendered_frames
+        .into_par_iter()
+        .map(|frame| {
+            let mut canvas = image::RgbaImage::new(canvas_width, canvas_height);
+            image::imageops::replace(
+                &mut canvas,
+                &frame,
+                i64::from((canvas_width - frame.width()) / 2),
+                i64::from((canvas_height - frame.height()) / 2),
+            );
+            Frame::from_parts(canvas, 0, 0, delay)
+        })
+        .collect::<Vec<_>>();
+
+    save_gif(frames, output_file_name, imgii_options)
+}
+
+/// Converts two images (such as PNGs or JPEGs) into an ASCII GIF that cross-dissolves from the
+/// first to the second, fading the color and alpha of each cell from the first image to the
+/// second. Useful for transitions.
+///
+/// NOTE: both images must convert to ASCII grids of the same dimensions (and render to the same
+/// size), so that their cells line up. Setting both the width and the height of the grid in the
+/// options guarantees this, otherwise the images need the same aspect ratio.
+///
+/// # Params
+/// - `input_a_file_name` - The input file name of the image to dissolve from.
+/// - `input_b_file_name` - The input file name of the image to dissolve to.
+/// - `steps` - The number of frames, including the first and second image. Must be non-zero.
+/// - `delay_ms` - The delay of each frame, in milliseconds.
+/// - `output_file_name` - The output file name.
+/// - `imgii_options` - The `imgii` render options
+///
+/// # Returns
+/// - `Err` upon error, such as when the images convert to grids of different dimensions, `Ok(())`
+///   otherwise.
+pub fn dissolve_gif(
+    input_a_file_name: &str,
+    input_b_file_name: &str,
+    steps: u32,
+    delay_ms: u32,
+    output_file_name: &str,
+    imgii_options: &ImgiiOptions,
+) -> Result<(), ImgiiError> {
+    if steps == 0 {
+        // no frames to render
+        return Err(ImgiiError::InvalidArgument);
+    }
+
+    // render both images, keeping the dimensions of their grids
+    let render = |input_file_name: &str| -> Result<_, ImgiiError> {
+        let loaded_img = open_image(input_file_name)?;
+        let imgii_options = &*imgii_options.for_source(loaded_img.width(), loaded_img.height());
+        let ascii_text = image_to_ascii(&loaded_img, imgii_options)?;
+        let lines = render_ascii_generic(imgii_options, ascii_text)?;
+        let grid_dimensions = (lines.width, lines.height);
+        let image_writer = AsciiImageWriter::from_2d_vec(lines, imgii_options)?;
+        Ok((
+            grid_dimensions,
+            image::RgbaImage::from(image_writer.imagebuf),
+        ))
+    };
+    let (grid_a, image_a) = render(input_a_file_name)?;
+    let (grid_b, image_b) = render(input_b_file_name)?;
+    if grid_a != grid_b || image_a.dimensions() != image_b.dimensions() {
+        // the cells of the images wouldn't line up
+        return Err(ImageError::InvalidParameter {
+            parameter_name: String::from("input_b_file_name"),
+        }
+        .into());
+    }
+
+    let delay = quantize_to_centiseconds(Delay::from_numer_denom_ms(delay_ms, 1));
+    let frames = (0..steps)
+        .into_par_iter()
+        .map(|step| {
+            // the first frame is the first image, and the last frame is the second image
+            let amount = if steps == 1 {
+                0.0
+            } else {
+                step as f32 / (steps - 1) as f32
+            };
+            Frame::from_parts(dissolve(&image_a, &image_b, amount), 0, 0, delay)
+        })
+        .collect::<Vec<_>>();
+
+    save_gif(frames, output_file_name, imgii_options)
+}
+
+/// Blends two images of the same size, fading from the first to the second. Colors are blended
+/// premultiplied by alpha, so transparent pixels don't darken the edges of glyphs as they fade.
+///
+/// * `image_a`: The image to fade from.
+/// * `image_b`: The image to fade to.
+/// * `amount`: How far to fade, from 0 (the first image) to 1 (the second image).
+fn dissolve(
+    image_a: &image::RgbaImage,
+    image_b: &image::RgbaImage,
+    amount: f32,
+) -> image::RgbaImage {
+    image::RgbaImage::from_fn(image_a.width(), image_a.height(), |x, y| {
+        let (Rgba(a), Rgba(b)) = (image_a.get_pixel(x, y), image_b.get_pixel(x, y));
+        let lerp = |from: f32, to: f32| from + (to - from) * amount;
+
+        let alpha = lerp(f32::from(a[3]), f32::from(b[3]));
+        if alpha <= 0.0 {
+            return Rgba([0, 0, 0, 0]);
+        }
+        let mut pixel = [0, 0, 0, alpha.round() as u8];
+        for channel in 0..3 {
+            let premultiplied = lerp(
+                f32::from(a[channel]) * f32::from(a[3]),
+                f32::from(b[channel]) * f32::from(b[3]),
+            );
+            pixel[channel] = (premultiplied / alpha).round().clamp(0.0, 255.0) as u8;
+        }
+        Rgba(pixel)
+    })
+}
+
+/// Stitches rendered frames back together into frames of a GIF.
+///
+/// NOTE: frames that failed to render are passed through, and frames that fail to stitch are
+/// returned as a [`FrameFailure`] too.
+///
+/// * `raw_frames`: The rendered frames.
+/// * `imgii_options`: The imgii options for rendering ASCII.
+fn stitch_rendered_frames(
+    raw_frames: Vec<Result<RenderedFramePart, FrameFailure>>,
+    imgii_options: &ImgiiOptions,
+) -> Vec<Result<Frame, FrameFailure>> {
+    raw_frames
+        .into_par_iter()
+        .map(|frame_part| {
+            // frames that already failed are passed through, so they can be reported
+            let (image_data, frame_metadata) = frame_part?.into_frame_data();
+            let image_writer = AsciiImageWriter::from_2d_vec(image_data, imgii_options)
+                .map_err(|err| FrameFailure::new(frame_metadata.index(), err))?;
+
+            // basically, we want to put the image data and the frame data back into a frame, so we
+            // can then use the image crate to build a new GIF from the new image!
+            Ok(Frame::from_parts(
+                image_writer.imagebuf.into(), // converts into its inner held type
+                frame_metadata.left(),
+                frame_metadata.top(),
+                quantize_to_centiseconds(frame_metadata.delay()),
+            ))
+        })
+        .collect()
+}
+
+/// Converts an image (such as a PNG or JPEG) into an ASCII GIF that reveals the ASCII cell-by-cell,
+/// like a typewriter. The ASCII is only rendered once, then each frame reveals more of it.
+///
+/// # Params
+/// - `input_file_name` - The input file name.
+/// - `output_file_name` - The output file name.
+/// - `imgii_options` - The `imgii` render options
+/// - `typewriter_options` - The typewriter animation options
+///
+/// # Returns
+/// - `Err(())` upon error, `Ok(())` otherwise.
+pub fn convert_to_typewriter_gif(
+    input_file_name: &str,
+    output_file_name: &str,
+    imgii_options: &ImgiiOptions,
+    typewriter_options: &TypewriterOptions,
+) -> Result<(), ImgiiError> {
+    let lines = parse_ascii_to_2d_png_vec(input_file_name, imgii_options)?;
+    let frames = render_typewriter_frames(&lines, imgii_options, typewriter_options)?;
+    save_gif(frames, output_file_name, imgii_options)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    };
+
+    use super::*;
+    #[cfg(not(feature = "wasm"))]
+    use crate::test_support::{TestFrame, write_test_gif};
+    use crate::test_support::{ansi_cell, test_dir, test_options_builder};
+
+    /// Writes a 3x2 ANSI-art file.
+    ///
+    /// * `path`: The path to write the file to.
+    fn write_ansi_file(path: &Path) {
+        let row = [
+            ansi_cell([255, 0, 0], '#'),
+            ansi_cell([0, 255, 0], '@'),
+            ansi_cell([0, 0, 255], ':'),
+        ]
+        .concat();
+        std::fs::write(path, [row.clone(), row].join("\n")).unwrap();
+    }
+
+    #[test]
+    fn raw_conversion_renders_ansi_input() {
+        let dir = test_dir("raw_conversion_renders_ansi_input");
+        let input = dir.join("grid.ans");
+        write_ansi_file(&input);
+        let output = dir.join("grid.raw");
+        let imgii_options = test_options_builder().ansi_input(true).build().unwrap();
+
+        convert_to_ascii_raw(
+            input.to_str().unwrap(),
+            output.to_str().unwrap(),
+            &imgii_options,
+        )
+        .unwrap();
+        let (char_width, char_height) = calculate_char_dimensions(&imgii_options);
+        let expected_len = 3 * char_width * 2 * char_height * 4;
+        assert_eq!(
+            std::fs::metadata(&output).unwrap().len(),
+            u64::from(expected_len)
+        );
+    }
+
+    #[test]
+    fn cancelled_raw_conversions_write_nothing() {
+        let dir = test_dir("cancelled_raw_conversions_write_nothing");
+        let input = dir.join("grid.ans");
+        write_ansi_file(&input);
+        let output = dir.join("grid.raw");
+        let cancel_flag = Arc::new(AtomicBool::new(false));
+        let imgii_options = test_options_builder()
+            .ansi_input(true)
+            .cancel_flag(Arc::clone(&cancel_flag))
+            .build()
+            .unwrap();
+        cancel_flag.store(true, Ordering::Relaxed);
+
+        let result = convert_to_ascii_raw(
+            input.to_str().unwrap(),
+            output.to_str().unwrap(),
+            &imgii_options,
+        );
+        assert!(matches!(result, Err(ImgiiError::Cancelled)));
+        #[cfg(feature = "mmap")]
+        {
+            let result = convert_to_ascii_raw_mmap(
+                input.to_str().unwrap(),
+                output.to_str().unwrap(),
+                &imgii_options,
+            );
+            assert!(matches!(result, Err(ImgiiError::Cancelled)));
+        }
+        assert!(!output.exists());
+    }
+
+    /// Writes a small gradient image, to convert.
+    ///
+    /// * `path`: The path to write the image to.
+    fn write_test_image(path: &Path) {
+        image::RgbImage::from_fn(32, 16, |x, y| {
+            image::Rgb([(x * 8) as u8, (y * 16) as u8, 96])
+        })
+        .save(path)
+        .unwrap();
+    }
+
+    #[test]
+    fn ansi_files_round_trip_to_the_same_png() {
+        let dir = test_dir("ansi_files_round_trip_to_the_same_png");
+        let input = dir.join("gradient.png");
+        write_test_image(&input);
+        let imgii_options = test_options_builder().width(16).build().unwrap();
+
+        let direct = dir.join("direct.png");
+        convert_to_ascii_png(
+            input.to_str().unwrap(),
+            direct.to_str().unwrap(),
+            &imgii_options,
+        )
+        .unwrap();
+        let ansi_file = dir.join("gradient.ans");
+        convert_to_ansi_file(
+            input.to_str().unwrap(),
+            ansi_file.to_str().unwrap(),
+            &imgii_options,
+        )
+        .unwrap();
+        let round_trip = dir.join("round_trip.png");
+        convert_ansi_to_ascii_png(
+            &std::fs::read_to_string(&ansi_file).unwrap(),
+            round_trip.to_str().unwrap(),
+            &imgii_options,
+        )
+        .unwrap();
+
+        let direct = image::open(&direct).unwrap().to_rgba8();
+        let round_trip = image::open(&round_trip).unwrap().to_rgba8();
+        assert_eq!(direct.dimensions(), round_trip.dimensions());
+        // not assert_eq!, so a failure doesn't print every pixel
+        assert!(direct == round_trip);
+    }
+
+    #[test]
+    fn missing_output_directories_are_created_only_when_enabled() {
+        let dir = test_dir("missing_output_directories_are_created_only_when_enabled");
+        let input = dir.join("grid.ans");
+        write_ansi_file(&input);
+        let output = dir.join("nonexistent").join("sub").join("out.png");
+
+        let result = convert_to_ascii_png(
+            input.to_str().unwrap(),
+            output.to_str().unwrap(),
+            &test_options_builder().ansi_input(true).build().unwrap(),
+        );
+        assert!(matches!(result, Err(ImgiiError::Io(_))));
+        assert!(!dir.join("nonexistent").exists());
+
+        convert_to_ascii_png(
+            input.to_str().unwrap(),
+            output.to_str().unwrap(),
+            &test_options_builder()
+                .ansi_input(true)
+                .create_dirs(true)
+                .build()
+                .unwrap(),
+        )
+        .unwrap();
+        assert!(output.parent().unwrap().is_dir());
+        assert!(image::open(&output).is_ok());
+    }
+
+    #[test]
+    fn grayscale_inputs_render_a_whole_image() {
+        let dir = test_dir("grayscale_inputs_render_a_whole_image");
+        let input = dir.join("gray.png");
+        image::GrayImage::from_fn(32, 16, |x, _| image::Luma([(x * 8) as u8]))
+            .save(&input)
+            .unwrap();
+        let output = dir.join("gray_ascii.png");
+        let imgii_options = test_options_builder().width(16).height(4).build().unwrap();
+
+        convert_to_ascii_png(
+            input.to_str().unwrap(),
+            output.to_str().unwrap(),
+            &imgii_options,
+        )
+        .unwrap();
+        let rendered = image::open(&output).unwrap().to_rgba8();
+        let (char_width, char_height) = calculate_char_dimensions(&imgii_options);
+        assert_eq!(rendered.dimensions(), (16 * char_width, 4 * char_height));
+        // every glyph is a shade of gray, and something was drawn
+        assert!(rendered.pixels().any(|pixel| pixel.0[3] > 0));
+        assert!(
+            rendered
+                .pixels()
+                .filter(|pixel| pixel.0[3] == u8::MAX)
+                .all(|Rgba([red, green, blue, _])| red == green && green == blue)
+        );
+    }
+
+    #[test]
+    fn matte_is_the_alpha_of_the_render() {
+        let dir = test_dir("matte_is_the_alpha_of_the_render");
+        let input = dir.join("gradient.png");
+        write_test_image(&input);
+        let imgii_options = test_options_builder().width(16).build().unwrap();
+
+        let render = dir.join("render.png");
+        convert_to_ascii_png(
+            input.to_str().unwrap(),
+            render.to_str().unwrap(),
+            &imgii_options,
+        )
+        .unwrap();
+        let (rgb, matte) = (dir.join("rgb.png"), dir.join("matte.png"));
+        convert_to_ascii_rgb_and_matte(
+            input.to_str().unwrap(),
+            rgb.to_str().unwrap(),
+            matte.to_str().unwrap(),
+            &imgii_options,
+        )
+        .unwrap();
+
+        let render = image::open(&render).unwrap().to_rgba8();
+        let matte = image::open(&matte).unwrap().to_luma8();
+        assert_eq!(render.dimensions(), matte.dimensions());
+        assert!(render.pixels().any(|pixel| pixel.0[3] > 0));
+        assert!(
+            render
+                .pixels()
+                .zip(matte.pixels())
+                .all(|(Rgba([_, _, _, alpha]), image::Luma([matte]))| alpha == matte)
+        );
+    }
+
+    /// Writes a 4x4 GIF of five solid frames, each with a longer delay than the one before it.
+    ///
+    /// * `path`: The path to write the GIF to.
+    #[cfg(not(feature = "wasm"))]
+    fn write_five_frame_gif(path: &Path) {
+        let frames: Vec<_> = (1..=5)
+            .map(|i| TestFrame {
+                left: 0,
+                top: 0,
+                width: 4,
+                height: 4,
+                color: [i * 50, 0, 0, 255],
+                delay: u16::from(i) * 10,
+            })
+            .collect();
+        write_test_gif(path, (4, 4), &frames);
+    }
+
+    #[test]
+    #[cfg(not(feature = "wasm"))]
+    fn streamed_frames_arrive_in_order() {
+        let dir = test_dir("streamed_frames_arrive_in_order");
+        let input = dir.join("five_frames.gif");
+        write_five_frame_gif(&input);
+        let imgii_options = test_options_builder().width(4).height(2).build().unwrap();
+
+        let receiver = convert_to_ascii_gif_streaming(input.to_str().unwrap(), &imgii_options);
+        // the channel closes once every frame has been sent
+        let delays: Vec<_> = receiver
+            .iter()
+            .map(|frame| {
+                let (image, delay) = frame.unwrap();
+                let (char_width, char_height) = calculate_char_dimensions(&imgii_options);
+                assert_eq!(image.width(), 4 * char_width);
+                assert_eq!(image.height(), 2 * char_height);
+                let (numer, denom) = delay.numer_denom_ms();
+                numer / denom
+            })
+            .collect();
+        assert_eq!(delays, [100, 200, 300, 400, 500]);
+    }
+
+    #[test]
+    #[cfg(not(feature = "wasm"))]
+    fn cancelled_streams_send_only_the_cancellation() {
+        let dir = test_dir("cancelled_streams_send_only_the_cancellation");
+        let input = dir.join("five_frames.gif");
+        write_five_frame_gif(&input);
+        let cancel_flag = Arc::new(AtomicBool::new(true));
+        let imgii_options = test_options_builder()
+            .width(4)
+            .height(2)
+            .cancel_flag(cancel_flag)
+            .build()
+            .unwrap();
+
+        let receiver = convert_to_ascii_gif_streaming(input.to_str().unwrap(), &imgii_options);
+        let frames: Vec<_> = receiver.iter().collect();
+        assert_eq!(frames.len(), 1);
+        assert!(matches!(frames[0], Err(ImgiiError::Cancelled)));
     }
 }
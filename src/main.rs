@@ -1,22 +1,41 @@
 //! The main file for the `imgii` CLI tool.
 
-use clap::Parser;
 use clap::builder as clap_builder;
 use clap::builder::styling as clap_styling;
+use clap::parser::ValueSource;
+use clap::{CommandFactory, FromArgMatches, Parser};
+use image::Rgba;
 use imgii::error::FontError;
 use imgii::error::ImgiiError;
 use imgii::fonts::list_fonts;
 use imgii::fonts::load_monospace_font;
+use imgii::fonts::validate_font;
+#[cfg(feature = "serde")]
+use imgii::preset::Preset;
+use imgii::timings::ConversionTimings;
 use rayon::iter::{IntoParallelIterator, ParallelIterator};
-use std::{sync::Arc, time::Instant};
+use std::{
+    path::{Path, PathBuf},
+    sync::{
+        Arc,
+        atomic::{AtomicU32, Ordering},
+    },
+    thread,
+    time::Instant,
+};
 
 use imgii::{
-    convert_to_ascii_gif, convert_to_ascii_png,
+    conversion_info, convert_batch, convert_to_ansi_file, convert_to_ascii_gif,
+    convert_to_ascii_png, convert_to_ascii_raw, convert_to_typewriter_gif,
+    format_profile::FormatProfile,
     image_types::{IMG_TYPES_ARRAY, ImageBatchType, OutputImageType},
     options::{
-        Charset, ImgiiOptions, ImgiiOptionsBuilder, convert_string_to_str_vec, from_enum,
-        to_charset_enum,
+        BitDepth, CellColorMode, CellRounding, ChannelOrder, Charset, GifDisposal,
+        GradientDirection, GradientSpec, ImgiiOptions, ImgiiOptionsBuilder, PngAlpha, ResizeFilter,
+        RevealOrder, SpaceHandling, TypewriterOptions, ZeroWidthHandling,
+        convert_string_to_str_vec, from_enum, to_charset_enum,
     },
+    progress::{Progress, ProgressStage},
 };
 
 #[derive(Debug, Parser)]
@@ -28,7 +47,8 @@ struct Args {
     /// input image index.
     ///
     /// Example: "input_image%d.png"
-    input_filename: String,
+    #[arg(required_unless_present = "manifest")]
+    input_filename: Option<String>,
 
     /// Path to the output image
     ///
@@ -36,17 +56,24 @@ struct Args {
     /// input image index (will use the same index as the original image).
     ///
     /// Example: "output_image%d.png"
-    output_filename: String,
+    #[arg(required_unless_present_any = ["info", "manifest"])]
+    output_filename: Option<String>,
+
+    /// Path to a manifest file listing a batch of images to convert to PNGs, instead of a single
+    /// input and output. Each line holds an input path and an output path, separated by a tab.
+    /// Empty lines and lines starting with '#' are skipped.
+    #[arg(long, conflicts_with_all = ["input_filename", "output_filename", "final_image_index"])]
+    manifest: Option<PathBuf>,
 
     /// Width (in characters) of the output image. To retain the image's original aspect ratio,
-    /// only set this value.
+    /// only set this value. If the height is also set, the aspect ratio is not preserved.
     ///
     /// Defaults to 128 if width and height are not specified.
     #[arg(short, long)]
     width: Option<u32>,
 
     /// Height (in characters) of the output image, if not specified, it will be calculated to keep
-    /// the aspect ratio.
+    /// the aspect ratio. If the width is also set, the aspect ratio is not preserved.
     #[arg(short = 'H', long)]
     height: Option<u32>,
 
@@ -65,12 +92,320 @@ struct Args {
     #[arg(short, long)]
     invert: bool,
 
-    /// Sets a black background behind the image.
+    /// Sets a background behind the image, black unless --background-color is set.
     ///
     /// No background by default.
     #[arg(short, long)]
     background: bool,
 
+    /// Disables the glyph cache, rendering every character fresh. Useful for profiling worst-case
+    /// render performance.
+    #[arg(long)]
+    disable_glyph_cache: bool,
+
+    /// The channel order of raw (.raw) output pixels.
+    /// Has no effect on other output types.
+    #[arg(long, value_enum, default_value = "rgba")]
+    channel_order: CliChannelOrder,
+
+    /// Pads the output image so its width and height are a multiple of this many pixels. Useful
+    /// when piping output frames into video encoders that require even dimensions.
+    #[arg(long)]
+    pad_to_multiple: Option<u32>,
+
+    /// Fades out cells by a grayscale mask image stretched over the grid. Cells under black are
+    /// transparent and cells under white are drawn as usual.
+    #[arg(long)]
+    opacity_mask: Option<PathBuf>,
+
+    /// Renders a grid of exactly this many cells, given as <WIDTH>x<HEIGHT>, cropping and padding
+    /// the ASCII text to fit.
+    ///
+    /// Example: "80x25"
+    #[arg(long, value_parser = parse_grid_size)]
+    fixed_grid: Option<(u32, u32)>,
+
+    /// The color of the background set by --background, as a hex color (RRGGBB or RRGGBBAA). A
+    /// semi-transparent color keeps the image see-through.
+    ///
+    /// Defaults to black.
+    #[arg(long, value_parser = parse_hex_color, default_value = "000000")]
+    background_color: Rgba<u8>,
+
+    /// The color of the padding added by --pad-to-multiple, as a hex color (RRGGBB or RRGGBBAA).
+    ///
+    /// Defaults to black.
+    #[arg(long, value_parser = parse_hex_color, default_value = "000000")]
+    pad_color: Rgba<u8>,
+
+    /// Renders glyphs with a heavier weight by synthetically emboldening them. Useful for fonts
+    /// without a bold variant.
+    #[arg(long)]
+    bold: bool,
+
+    /// Renders every glyph in grayscale. With --background, PNGs are saved as grayscale images,
+    /// using less memory.
+    #[arg(long)]
+    grayscale: bool,
+
+    /// Skips writing the output file if it already holds exactly the rendered image, keeping its
+    /// modification time. Useful for incremental builds.
+    #[arg(long)]
+    skip_if_unchanged: bool,
+
+    /// Renders the lightest character of the charset like whitespace, so only the denser
+    /// characters are drawn. With --invert, the see-through parts of the image are flipped.
+    #[arg(long)]
+    lightest_transparent: bool,
+
+    /// Resizes the output image to exactly the size of the input image, so it can be overlaid on
+    /// the input image. Glyphs may be stretched if the grid has a different aspect ratio.
+    #[arg(long)]
+    match_source_resolution: bool,
+
+    /// Fails the conversion if any character of the ASCII text can't be parsed, instead of
+    /// silently dropping it.
+    #[arg(long)]
+    strict_parse: bool,
+
+    /// Fills the background of each cell with the average color of the part of the input image it
+    /// covers, drawing the glyph on top, for a dense "colored block and character" look.
+    #[arg(long)]
+    cell_bg_from_source: bool,
+
+    /// Embeds the input file name, charset, font and font size as text metadata in output PNGs.
+    #[arg(long)]
+    embed_metadata: bool,
+
+    /// Writes each output file to a temporary file first, then renames it over the output file, so
+    /// an interrupted run never leaves a truncated output file behind.
+    #[arg(long)]
+    atomic_writes: bool,
+
+    /// Creates any missing directories in the output file path, instead of failing.
+    #[arg(long)]
+    create_dirs: bool,
+
+    /// Renders inputs that are already colored ASCII text (such as ".ans" files) directly, instead
+    /// of converting them to ASCII first. Only applies to PNG output.
+    #[arg(long)]
+    ansi_input: bool,
+
+    /// Fills the background once across the whole image, under every glyph, rather than into each
+    /// cell. Avoids seams between cells when the background color isn't fully opaque.
+    #[arg(long)]
+    continuous_background: bool,
+
+    /// Writes the smallest PNGs possible, such as for icons: indexed color with a minimal palette,
+    /// the best compression and no metadata or ICC profile.
+    #[arg(long)]
+    optimize_size: bool,
+
+    /// Prints how the size of the output image is worked out, from the input image to the ASCII
+    /// grid to the final image, then exits without converting anything.
+    #[arg(long)]
+    info: bool,
+
+    /// The maximum playback duration of an output GIF, in milliseconds. Frames are uniformly
+    /// dropped from longer GIFs until they fit.
+    #[arg(long)]
+    target_duration_ms: Option<u32>,
+
+    /// The maximum number of frames of an output GIF. Evenly spaced frames are kept from GIFs
+    /// with more frames, keeping the total duration the same.
+    #[arg(long)]
+    max_frames: Option<usize>,
+
+    /// Encodes GIF frames while later frames are still rendering, letting up to this many rendered
+    /// frames wait to be encoded. Hides rendering time behind encoding for long GIFs.
+    #[arg(long)]
+    gif_pipeline_depth: Option<usize>,
+
+    /// How whitespace cells are rendered. "background" fills them with the background if it's
+    /// enabled, "transparent" always leaves them transparent, and "custom" fills them with
+    /// --space-color.
+    #[arg(long, value_enum, default_value = "background")]
+    space_handling: CliSpaceHandling,
+
+    /// The color to fill whitespace cells with when --space-handling is "custom", as a hex color
+    /// (RRGGBB or RRGGBBAA).
+    ///
+    /// Defaults to black.
+    #[arg(long, value_parser = parse_hex_color, default_value = "000000")]
+    space_color: Rgba<u8>,
+
+    /// Renders a static image as a GIF that reveals the ASCII cell-by-cell, like a typewriter.
+    /// Specifies the number of cells revealed by each frame.
+    #[arg(long)]
+    typewriter: Option<usize>,
+
+    /// The delay (in milliseconds) of each frame of a --typewriter GIF.
+    #[arg(long, default_value_t = 50)]
+    typewriter_delay_ms: u32,
+
+    /// Reveals cells of a --typewriter GIF column-by-column, instead of row-by-row.
+    #[arg(long)]
+    typewriter_column_major: bool,
+
+    /// Adds a soft glow over the output image. Specifies how far the glow spreads, in pixels.
+    #[arg(long)]
+    bloom: Option<f32>,
+
+    /// How bright the glow added by --bloom is, where 1.0 adds the whole blurred image.
+    #[arg(long, default_value_t = 0.6)]
+    bloom_strength: f32,
+
+    /// Renders every glyph in a single phosphor color with a subtle glow over a black background,
+    /// for a classic monochrome terminal look. Given as a hex color (RRGGBB), such as "33ff66" for
+    /// green or "ffb000" for amber. Other flags can still fine-tune the look.
+    #[arg(long, value_parser = parse_hex_color)]
+    phosphor: Option<Rgba<u8>>,
+
+    /// Overlays horizontal scanlines for a CRT look. Specifies the number of rows between each
+    /// scanline.
+    #[arg(long)]
+    scanlines: Option<u32>,
+
+    /// How much to darken each scanline drawn by --scanlines, from 0 (not at all) to 255 (fully
+    /// black).
+    #[arg(long, default_value_t = 96)]
+    scanline_darkness: u8,
+
+    /// How the alpha channel is handled when writing a PNG. "keep" writes an RGBA PNG, "strip"
+    /// writes an RGB PNG (failing if the image has transparency), and "flatten" writes an RGB PNG
+    /// after flattening transparency onto black.
+    #[arg(long, value_enum, default_value = "keep")]
+    png_alpha: CliPngAlpha,
+
+    /// The bit depth of each channel of an output PNG. "sixteen" avoids banding in smooth
+    /// gradients, such as when scaling the output.
+    #[arg(long, value_enum, default_value = "eight")]
+    bit_depth: CliBitDepth,
+
+    /// How each frame of an output GIF is disposed of before the next frame is drawn. "background"
+    /// avoids ghosting when frames are transparent.
+    #[arg(long, value_enum, default_value = "background")]
+    gif_disposal: CliGifDisposal,
+
+    /// Reserves a color as the transparent color key of an output GIF, as a hex color (RRGGBB),
+    /// for tools that find transparency by color. Glyphs of this color are shifted slightly.
+    #[arg(long, value_parser = parse_hex_color)]
+    gif_transparent_key: Option<Rgba<u8>>,
+
+    /// The number of transparent cells each tab in the ASCII text is expanded to.
+    #[arg(long, default_value_t = 4)]
+    tab_width: u32,
+
+    /// Renders each character in a square cell, instead of a cell twice as tall as it is wide. The
+    /// output keeps the proportions of the input image.
+    #[arg(long)]
+    square_cells: bool,
+
+    /// How the width of a cell is rounded when it isn't a whole number of pixels, such as with an
+    /// odd font size.
+    #[arg(long, value_enum, default_value = "floor")]
+    cell_rounding: CliCellRounding,
+
+    /// Colors every glyph along a gradient by the position of its cell, overriding the colors of
+    /// the input image. Given as hex colors (RRGGBB) separated by commas, from the first row (or
+    /// column) to the last.
+    ///
+    /// Example: "ff0000,00ff00,0000ff"
+    #[arg(long, value_parser = parse_hex_color, value_delimiter = ',')]
+    gradient: Vec<Rgba<u8>>,
+
+    /// The direction of the gradient from --gradient.
+    #[arg(long, value_enum, default_value = "rows")]
+    gradient_direction: CliGradientDirection,
+
+    /// How zero-width characters in ASCII input, such as combining accents, are placed in the
+    /// grid. "combine" draws them together with the character before them, while "separate-cell"
+    /// gives each one a cell of its own.
+    #[arg(long, value_enum, default_value = "combine")]
+    zero_width: CliZeroWidthHandling,
+
+    /// Runs edge detection on the input image before converting it to ASCII, for a line-art look.
+    /// Works best with --background.
+    #[arg(long)]
+    edge_detect: bool,
+
+    /// Posterizes the input image before converting it to ASCII, reducing each color channel to
+    /// this many levels (at least 2), for a cleaner, poster-like look.
+    #[arg(long, value_parser = clap::value_parser!(u8).range(2..))]
+    posterize: Option<u8>,
+
+    /// Path to a render preset file (TOML or JSON). Flags passed on the command line win over the
+    /// values in the preset.
+    #[cfg(feature = "serde")]
+    #[arg(long)]
+    preset: Option<String>,
+
+    /// Draws a character at a multiple of the font size so it stands out, given as
+    /// <CHARACTER>=<MULTIPLIER>. Can be passed multiple times.
+    ///
+    /// Example: "@=2.0"
+    #[arg(long, value_parser = parse_emphasis)]
+    emphasis: Vec<(char, f32)>,
+
+    /// How the color of each cell is chosen. "sampled" uses the color RASCII samples, "average"
+    /// uses the average color of the part of the image the cell covers, and "center" uses the
+    /// exact color of the image at the center of the cell.
+    #[arg(long, value_enum, default_value = "sampled")]
+    cell_color_mode: CliCellColorMode,
+
+    /// Scales the final output image by this factor.
+    #[arg(long)]
+    scale_output: Option<f32>,
+
+    /// The filter used when scaling the final output image with --scale-output. "nearest" keeps
+    /// glyphs sharp when upscaling.
+    #[arg(long, value_enum, default_value = "nearest")]
+    resize_filter_output: CliResizeFilter,
+
+    /// The maximum number of bytes a rendered image may take up. Rendering a larger image fails
+    /// instead of running out of memory.
+    #[arg(long)]
+    memory_budget: Option<u64>,
+
+    /// Randomly rotates each glyph by up to this many degrees for a hand-drawn look. Makes
+    /// rendering much slower, since glyphs can't be reused.
+    #[arg(long)]
+    jitter_degrees: Option<f32>,
+
+    /// Randomly offsets each glyph by up to this many pixels for a hand-drawn look. Makes
+    /// rendering much slower, since glyphs can't be reused.
+    #[arg(long)]
+    jitter_offset: Option<u32>,
+
+    /// The seed for --jitter-degrees and --jitter-offset. The same seed always produces the same
+    /// output.
+    #[arg(long, default_value_t = 0)]
+    jitter_seed: u64,
+
+    /// Only renders the glyph of every nth cell, leaving the rest empty, for a dotted look.
+    #[arg(long)]
+    sparsity: Option<u32>,
+
+    /// Embeds the ICC profile of the input image in the output PNG. Without this, wide-gamut
+    /// inputs render with shifted colors.
+    #[arg(long)]
+    preserve_icc_profile: bool,
+
+    /// Logs how long each stage of the conversion took.
+    #[arg(long)]
+    timings: bool,
+
+    /// Draws a progress bar with the estimated time remaining while rendering and encoding a GIF.
+    #[arg(long)]
+    progress: bool,
+
+    /// The maximum number of images in a batch converted at once. Each image still renders in
+    /// parallel, so a small number can be faster than converting every image at once.
+    ///
+    /// Converts every image at once if not set.
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+    batch_concurrency: Option<u32>,
+
     /// Allows for converting multiple images. Specifies the final input image index.
     /// Index starts at 1.
     final_image_index: Option<u32>,
@@ -86,9 +421,233 @@ struct Args {
     char_override: Option<String>,
 }
 
+/// CLI representation of [`ChannelOrder`].
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+enum CliChannelOrder {
+    Rgba,
+    Bgra,
+    Argb,
+}
+
+impl From<CliChannelOrder> for ChannelOrder {
+    fn from(value: CliChannelOrder) -> Self {
+        match value {
+            CliChannelOrder::Rgba => ChannelOrder::Rgba,
+            CliChannelOrder::Bgra => ChannelOrder::Bgra,
+            CliChannelOrder::Argb => ChannelOrder::Argb,
+        }
+    }
+}
+
+/// CLI representation of [`SpaceHandling`].
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+enum CliSpaceHandling {
+    Background,
+    Transparent,
+    Custom,
+}
+
+/// CLI representation of [`PngAlpha`].
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+enum CliPngAlpha {
+    Keep,
+    Strip,
+    Flatten,
+}
+
+impl From<CliPngAlpha> for PngAlpha {
+    fn from(value: CliPngAlpha) -> Self {
+        match value {
+            CliPngAlpha::Keep => PngAlpha::Keep,
+            CliPngAlpha::Strip => PngAlpha::Strip,
+            CliPngAlpha::Flatten => PngAlpha::Flatten,
+        }
+    }
+}
+
+/// CLI representation of [`BitDepth`].
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+enum CliBitDepth {
+    Eight,
+    Sixteen,
+}
+
+impl From<CliBitDepth> for BitDepth {
+    fn from(value: CliBitDepth) -> Self {
+        match value {
+            CliBitDepth::Eight => BitDepth::Eight,
+            CliBitDepth::Sixteen => BitDepth::Sixteen,
+        }
+    }
+}
+
+/// CLI representation of [`CellRounding`].
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+enum CliCellRounding {
+    Floor,
+    Nearest,
+    Ceil,
+}
+
+impl From<CliCellRounding> for CellRounding {
+    fn from(value: CliCellRounding) -> Self {
+        match value {
+            CliCellRounding::Floor => CellRounding::Floor,
+            CliCellRounding::Nearest => CellRounding::Nearest,
+            CliCellRounding::Ceil => CellRounding::Ceil,
+        }
+    }
+}
+
+/// CLI representation of [`GradientDirection`].
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+enum CliGradientDirection {
+    Rows,
+    Columns,
+}
+
+impl From<CliGradientDirection> for GradientDirection {
+    fn from(value: CliGradientDirection) -> Self {
+        match value {
+            CliGradientDirection::Rows => GradientDirection::Rows,
+            CliGradientDirection::Columns => GradientDirection::Columns,
+        }
+    }
+}
+
+/// CLI representation of [`ZeroWidthHandling`].
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+enum CliZeroWidthHandling {
+    Combine,
+    SeparateCell,
+}
+
+impl From<CliZeroWidthHandling> for ZeroWidthHandling {
+    fn from(value: CliZeroWidthHandling) -> Self {
+        match value {
+            CliZeroWidthHandling::Combine => ZeroWidthHandling::Combine,
+            CliZeroWidthHandling::SeparateCell => ZeroWidthHandling::SeparateCell,
+        }
+    }
+}
+
+/// CLI representation of [`CellColorMode`].
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+enum CliCellColorMode {
+    Sampled,
+    Average,
+    Center,
+}
+
+impl From<CliCellColorMode> for CellColorMode {
+    fn from(value: CliCellColorMode) -> Self {
+        match value {
+            CliCellColorMode::Sampled => CellColorMode::Sampled,
+            CliCellColorMode::Average => CellColorMode::Average,
+            CliCellColorMode::Center => CellColorMode::Center,
+        }
+    }
+}
+
+/// CLI representation of [`ResizeFilter`].
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+enum CliResizeFilter {
+    Nearest,
+    Triangle,
+    CatmullRom,
+    Lanczos3,
+}
+
+impl From<CliResizeFilter> for ResizeFilter {
+    fn from(value: CliResizeFilter) -> Self {
+        match value {
+            CliResizeFilter::Nearest => ResizeFilter::Nearest,
+            CliResizeFilter::Triangle => ResizeFilter::Triangle,
+            CliResizeFilter::CatmullRom => ResizeFilter::CatmullRom,
+            CliResizeFilter::Lanczos3 => ResizeFilter::Lanczos3,
+        }
+    }
+}
+
+/// CLI representation of [`GifDisposal`].
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+enum CliGifDisposal {
+    Keep,
+    Background,
+    Previous,
+}
+
+impl From<CliGifDisposal> for GifDisposal {
+    fn from(value: CliGifDisposal) -> Self {
+        match value {
+            CliGifDisposal::Keep => GifDisposal::Keep,
+            CliGifDisposal::Background => GifDisposal::Background,
+            CliGifDisposal::Previous => GifDisposal::Previous,
+        }
+    }
+}
+
+/// Parses a hex color string (RRGGBB or RRGGBBAA, optionally starting with a '#') into a color.
+///
+/// * `color_str`: The hex color string.
+fn parse_hex_color(color_str: &str) -> Result<Rgba<u8>, String> {
+    let hex = color_str.strip_prefix('#').unwrap_or(color_str);
+    if !(hex.len() == 6 || hex.len() == 8) || !hex.is_ascii() {
+        return Err(format!(
+            "expected a hex color (RRGGBB or RRGGBBAA), got {color_str}"
+        ));
+    }
+
+    // parse each pair of hex digits as a channel, defaulting to opaque when there's no alpha
+    let mut channels = [u8::MAX; 4];
+    for (i, channel) in channels.iter_mut().take(hex.len() / 2).enumerate() {
+        *channel = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16)
+            .map_err(|err| format!("could not parse hex color {color_str} ({err})"))?;
+    }
+    Ok(Rgba(channels))
+}
+
+/// Parses an emphasized character and its font size multiplier, separated by an '='.
+///
+/// * `emphasis_str`: The emphasis string, such as "@=2.0".
+fn parse_emphasis(emphasis_str: &str) -> Result<(char, f32), String> {
+    let mut chars = emphasis_str.chars();
+    let (Some(character), Some('=')) = (chars.next(), chars.next()) else {
+        return Err(format!(
+            "expected <CHARACTER>=<MULTIPLIER>, got {emphasis_str}"
+        ));
+    };
+    let multiplier = chars
+        .as_str()
+        .parse::<f32>()
+        .map_err(|err| format!("could not parse multiplier of {emphasis_str} ({err})"))?;
+    Ok((character, multiplier))
+}
+
+/// Parses the size of a grid of cells, as a width and height separated by an 'x'.
+///
+/// * `size_str`: The size string, such as "80x25".
+fn parse_grid_size(size_str: &str) -> Result<(u32, u32), String> {
+    let Some((width, height)) = size_str.split_once('x') else {
+        return Err(format!("expected <WIDTH>x<HEIGHT>, got {size_str}"));
+    };
+    let parse_dimension = |dimension: &str| {
+        dimension
+            .parse::<u32>()
+            .map_err(|err| format!("could not parse grid size {size_str} ({err})"))
+    };
+    Ok((parse_dimension(width)?, parse_dimension(height)?))
+}
+
 // default values for arguments
 const DEFAULT_WIDTH: u32 = 128;
 
+/// The width of the progress bar drawn for GIFs, in characters.
+const PROGRESS_BAR_WIDTH: usize = 30;
+
+/// The names of the built-in charsets.
+const CHARSET_NAMES: &[&str] = &["block", "emoji", "default", "russian", "slight", "minimal"];
+
 /// Sets the style for clap output.
 fn set_color_style() -> clap_builder::Styles {
     clap_builder::Styles::styled()
@@ -187,7 +746,8 @@ fn imgii_builder_load_font<'a>(
     // font it can find
     match load_monospace_font(&font_name) {
         Some((font, _)) => {
-            // successfully loaded font
+            // successfully loaded font, make sure it's usable before we try to render with it
+            validate_font(&font, &font_name)?;
             Ok(builder.font(font).font_name(font_name))
         }
         None => {
@@ -197,16 +757,56 @@ fn imgii_builder_load_font<'a>(
     }
 }
 
-/// Creates an instance of [`ImgiiOptions`] for the CLI for imgii.
+/// Creates a builder for [`ImgiiOptions`] from the CLI arguments for imgii.
 ///
 /// * `args`: The CLI arguments.
 /// * `rascii_charset`: The rascii
-fn create_imgii_options<'a>(
+fn create_imgii_options_builder<'a>(
     args: Args,
     rascii_charset: Charset,
-) -> Result<ImgiiOptions<'a>, ImgiiError> {
-    let mut builder: ImgiiOptionsBuilder<'a> =
-        ImgiiOptionsBuilder::new().background(args.background);
+) -> Result<ImgiiOptionsBuilder<'a>, ImgiiError> {
+    // set the size optimizations and the phosphor look first, so the individual flags can still
+    // fine-tune them
+    let mut builder: ImgiiOptionsBuilder<'a> = ImgiiOptionsBuilder::new();
+    if let Some(Rgba([red, green, blue, _])) = args.phosphor {
+        builder = builder.phosphor([red, green, blue]);
+    }
+    let mut builder = builder
+        .optimize_size(args.optimize_size)
+        .background(args.background || args.phosphor.is_some())
+        .background_color(args.background_color)
+        .disable_glyph_cache(args.disable_glyph_cache)
+        .channel_order(args.channel_order.into())
+        .bold(args.bold)
+        .grayscale(args.grayscale)
+        .skip_if_unchanged(args.skip_if_unchanged)
+        .lightest_transparent(args.lightest_transparent)
+        .match_source_resolution(args.match_source_resolution)
+        .strict_parse(args.strict_parse)
+        .cell_bg_from_source(args.cell_bg_from_source)
+        .embed_metadata(args.embed_metadata)
+        .atomic_writes(args.atomic_writes)
+        .create_dirs(args.create_dirs)
+        .ansi_input(args.ansi_input)
+        .continuous_background(args.continuous_background)
+        .timings(args.timings)
+        .preserve_icc_profile(args.preserve_icc_profile)
+        .png_alpha(args.png_alpha.into())
+        .bit_depth(args.bit_depth.into())
+        .cell_rounding(args.cell_rounding.into())
+        .gif_disposal(args.gif_disposal.into())
+        .tab_width(args.tab_width)
+        .zero_width_handling(args.zero_width.into())
+        .square_cells(args.square_cells)
+        .edge_detect(args.edge_detect)
+        .cell_color_mode(args.cell_color_mode.into())
+        .resize_filter_output(args.resize_filter_output.into())
+        .emphasis(args.emphasis.into_iter().collect())
+        .space_handling(match args.space_handling {
+            CliSpaceHandling::Background => SpaceHandling::Background,
+            CliSpaceHandling::Transparent => SpaceHandling::Transparent,
+            CliSpaceHandling::Custom => SpaceHandling::Custom(args.space_color),
+        });
     // build the complex values first
 
     // load the font
@@ -214,6 +814,65 @@ fn create_imgii_options<'a>(
     if let Some(font_size) = args.font_size {
         builder = builder.font_size(font_size);
     }
+    if let Some(pad_to_multiple) = args.pad_to_multiple {
+        builder = builder.pad_to_multiple(pad_to_multiple, args.pad_color);
+    }
+    if let Some(gif_transparent_key) = args.gif_transparent_key {
+        builder = builder.gif_transparent_key(gif_transparent_key);
+    }
+    if let Some(opacity_mask) = args.opacity_mask {
+        builder = builder.opacity_mask(opacity_mask);
+    }
+    if let Some((width, height)) = args.fixed_grid {
+        builder = builder.fixed_grid(width, height);
+    }
+    if let Some(bloom) = args.bloom {
+        builder = builder.bloom(bloom, args.bloom_strength);
+    }
+    if let Some(posterize) = args.posterize {
+        builder = builder.posterize(posterize);
+    }
+    if args.progress {
+        builder = builder.on_progress(Arc::new(draw_progress_bar));
+    }
+    if let Some(scanlines) = args.scanlines {
+        builder = builder.scanlines(scanlines, args.scanline_darkness);
+    }
+    if args.jitter_degrees.is_some() || args.jitter_offset.is_some() {
+        builder = builder.jitter(
+            args.jitter_degrees.unwrap_or(0.0),
+            args.jitter_offset.unwrap_or(0),
+            args.jitter_seed,
+        );
+    }
+    if let Some(sparsity) = args.sparsity {
+        builder = builder.sparsity(sparsity);
+    }
+    if !args.gradient.is_empty() {
+        let stops = args
+            .gradient
+            .iter()
+            .map(|Rgba([red, green, blue, _])| [*red, *green, *blue])
+            .collect();
+        builder = builder.gradient_override(
+            GradientSpec::new(stops)?.with_direction(args.gradient_direction.into()),
+        );
+    }
+    if let Some(max_frames) = args.max_frames {
+        builder = builder.max_frames(max_frames);
+    }
+    if let Some(depth) = args.gif_pipeline_depth {
+        builder = builder.gif_pipeline_depth(depth);
+    }
+    if let Some(target_duration_ms) = args.target_duration_ms {
+        builder = builder.target_duration_ms(target_duration_ms);
+    }
+    if let Some(memory_budget) = args.memory_budget {
+        builder = builder.memory_budget(memory_budget);
+    }
+    if let Some(scale_output) = args.scale_output {
+        builder = builder.scale_output(scale_output);
+    }
     if let Some(width) = args.width {
         builder = builder.width(width);
     }
@@ -225,37 +884,62 @@ fn create_imgii_options<'a>(
         builder = builder.char_override(convert_string_to_str_vec(&char_override));
     }
 
-    builder
+    Ok(builder
         .invert(args.invert)
-        .charset(from_enum(rascii_charset))
-        .build()
+        .charset(from_enum(rascii_charset)))
 }
 
 fn main() {
-    let mut args = Args::parse();
+    let matches = Args::command().get_matches();
+    let mut args = Args::from_arg_matches(&matches).unwrap_or_else(|err| err.exit());
     env_logger::init();
     setup_threads();
 
-    if args.width.is_none() && args.height.is_none() {
+    // load the preset before anything else, so we know which options it sets
+    #[cfg(feature = "serde")]
+    let preset = args.preset.as_deref().map(|preset_path| {
+        Preset::load(preset_path)
+            .unwrap_or_else(|err| panic!("could not load preset {preset_path}: {err}"))
+    });
+    #[cfg(feature = "serde")]
+    let preset_sets_size = preset
+        .as_ref()
+        .is_some_and(|preset| preset.width.is_some() || preset.height.is_some());
+    #[cfg(not(feature = "serde"))]
+    let preset_sets_size = false;
+
+    if args.width.is_none() && args.height.is_none() && !preset_sets_size {
         args.width = Some(DEFAULT_WIDTH);
     }
 
-    let input_name_format = args.input_filename.clone();
-    let output_name_format = args.output_filename.clone();
+    let input_name_format = args.input_filename.clone().unwrap_or_default();
+    let output_name_format = args.output_filename.clone().unwrap_or_default();
+    let show_info = args.info;
+    let batch_concurrency = args.batch_concurrency;
+    let manifest = args.manifest.clone();
 
     // see what image type we are working with and panic if it's unrecognized
-    let image_type = match OutputImageType::from_file_name(&args.output_filename) {
+    let image_type = match OutputImageType::from_file_name(&output_name_format) {
         Some(image_type) => image_type,
+        // there's no output to write when only printing info, and manifests always convert PNGs
+        None if show_info || manifest.is_some() => OutputImageType::Png,
         None => {
             panic!(
                 "Could not get output file type from {}, expected one of ({})",
-                args.output_filename,
+                output_name_format,
                 IMG_TYPES_ARRAY.join(", ")
             );
         }
     };
 
-    let rascii_charset = to_charset_enum(&args.charset).unwrap_or(Charset::Minimal);
+    // don't silently fall back to another charset, since that hides typos
+    let Some(rascii_charset) = to_charset_enum(&args.charset) else {
+        panic!(
+            "Unrecognized charset {}, expected one of ({})",
+            args.charset,
+            CHARSET_NAMES.join(", ")
+        );
+    };
 
     // are we doing a batch of images or a single image
     let batch_type = if let Some(final_image_idx) = args.final_image_index {
@@ -266,12 +950,65 @@ fn main() {
         ImageBatchType::Single
     };
 
+    // are we rendering a typewriter animation
+    let typewriter_options = args.typewriter.map(|cells_per_frame| {
+        let Ok(typewriter_options) =
+            TypewriterOptions::new(cells_per_frame, args.typewriter_delay_ms)
+        else {
+            panic!("could not create typewriter options, cells per frame must be non-zero");
+        };
+        if args.typewriter_column_major {
+            typewriter_options.with_reveal_order(RevealOrder::ColumnMajor)
+        } else {
+            typewriter_options
+        }
+    });
+
     // our options for rendering ASCII in imgii
-    let Ok(imgii_options) = create_imgii_options(args, rascii_charset) else {
+    let Ok(imgii_options_builder) = create_imgii_options_builder(args, rascii_charset) else {
+        panic!("could not create imgii options");
+    };
+    // apply the preset under the options, so flags passed on the command line win
+    #[cfg(feature = "serde")]
+    let imgii_options_builder = match &preset {
+        Some(preset) => {
+            let is_from_cli =
+                |name: &str| matches.value_source(name) == Some(ValueSource::CommandLine);
+            let Ok(builder) = preset.apply_except(imgii_options_builder, is_from_cli) else {
+                panic!(
+                    "could not apply preset, expected one of ({}) for its charset",
+                    CHARSET_NAMES.join(", ")
+                );
+            };
+            builder
+        }
+        None => imgii_options_builder,
+    };
+    // apply the defaults for the output format under everything else, so they only fill in what
+    // the user hasn't chosen
+    let imgii_options_builder = FormatProfile::for_output_type(image_type)
+        .apply_except(imgii_options_builder, |name: &str| {
+            matches.value_source(name) == Some(ValueSource::CommandLine)
+        });
+    let Ok(imgii_options) = imgii_options_builder.build() else {
         panic!("could not create imgii options");
     };
     log::debug!("imgii options = {}", imgii_options);
 
+    if show_info {
+        match conversion_info(&input_name_format, &imgii_options) {
+            Ok(info) => println!("{info}"),
+            Err(err) => panic!("could not get conversion info for {input_name_format}: {err}"),
+        }
+        return;
+    }
+
+    if let Some(manifest) = manifest {
+        log::debug!("Converting batch of PNGs from manifest...");
+        convert_manifest_batch(&manifest, &imgii_options);
+        return;
+    }
+
     // Now, handle the conversion
     match image_type {
         OutputImageType::Png => {
@@ -286,6 +1023,7 @@ fn main() {
                         Arc::from(input_name_format),
                         Arc::from(output_name_format),
                         Arc::from(imgii_options),
+                        batch_concurrency,
                     );
                 }
                 ImageBatchType::Single => {
@@ -295,7 +1033,9 @@ fn main() {
                         &output_name_format,
                         &imgii_options,
                     ) {
-                        Ok(_) => {}
+                        Ok(timings) => {
+                            log_timings(&output_name_format, timings);
+                        }
                         Err(_) => {
                             log::error!("Could not save PNG {}", output_name_format);
                         }
@@ -303,6 +1043,56 @@ fn main() {
                 }
             };
         }
+        OutputImageType::Raw => match batch_type {
+            ImageBatchType::Batch {
+                final_index: final_img_idx,
+            } => {
+                panic!(
+                    "Cannot convert a batch of raw images, argument final_img_idx={final_img_idx}. {}",
+                    "Do not set this argument if intending to convert a raw image."
+                );
+            }
+            ImageBatchType::Single => {
+                log::debug!("Converting single raw image");
+                match convert_to_ascii_raw(&input_name_format, &output_name_format, &imgii_options)
+                {
+                    Ok(timings) => {
+                        log::info!("Saved raw image {}", output_name_format);
+                        log_timings(&output_name_format, timings);
+                    }
+                    Err(err) => {
+                        log::error!("Could not save raw image {} ({})", output_name_format, err);
+                    }
+                }
+            }
+        },
+        OutputImageType::Ans => match batch_type {
+            ImageBatchType::Batch {
+                final_index: final_img_idx,
+            } => {
+                panic!(
+                    "Cannot convert a batch of ANSI-art files, argument final_img_idx={final_img_idx}. {}",
+                    "Do not set this argument if intending to save an ANSI-art file."
+                );
+            }
+            ImageBatchType::Single => {
+                log::debug!("Converting single ANSI-art file");
+                match convert_to_ansi_file(&input_name_format, &output_name_format, &imgii_options)
+                {
+                    Ok(timings) => {
+                        log::info!("Saved ANSI-art file {}", output_name_format);
+                        log_timings(&output_name_format, timings);
+                    }
+                    Err(err) => {
+                        log::error!(
+                            "Could not save ANSI-art file {} ({})",
+                            output_name_format,
+                            err
+                        );
+                    }
+                }
+            }
+        },
         OutputImageType::Gif => {
             match batch_type {
                 ImageBatchType::Batch {
@@ -316,13 +1106,25 @@ fn main() {
                 }
                 ImageBatchType::Single => {
                     log::debug!("Converting single GIF");
-                    match convert_to_ascii_gif(
-                        &input_name_format,
-                        &output_name_format,
-                        &imgii_options,
-                    ) {
-                        Ok(_) => {
+                    let result = match typewriter_options {
+                        Some(typewriter_options) => convert_to_typewriter_gif(
+                            &input_name_format,
+                            &output_name_format,
+                            &imgii_options,
+                            &typewriter_options,
+                        )
+                        // no timings for typewriter GIFs
+                        .map(|_| None),
+                        None => convert_to_ascii_gif(
+                            &input_name_format,
+                            &output_name_format,
+                            &imgii_options,
+                        ),
+                    };
+                    match result {
+                        Ok(timings) => {
                             log::info!("Saved GIF {}", output_name_format);
+                            log_timings(&output_name_format, timings);
                         }
                         Err(err) => {
                             log::error!("Could not save GIF {} ({})", output_name_format, err);
@@ -334,12 +1136,116 @@ fn main() {
     }
 }
 
+/// Logs the timings of each stage of a conversion, if there are any.
+///
+/// * `output_file_name`: The output file name of the conversion.
+/// * `timings`: The timings of the conversion.
+fn log_timings(output_file_name: &str, timings: Option<ConversionTimings>) {
+    if let Some(timings) = timings {
+        log::info!(
+            "Timings for {}: decode={:?}, ascii={:?}, render={:?}, encode={:?}, total={:?}",
+            output_file_name,
+            timings.decode(),
+            timings.ascii(),
+            timings.render(),
+            timings.encode(),
+            timings.total()
+        );
+    }
+}
+
+/// Draws a progress bar for a stage of converting a GIF to stderr, on a single line that's
+/// redrawn each time, with the estimated time remaining.
+///
+/// * `progress`: The progress of the stage.
+fn draw_progress_bar(progress: &Progress) {
+    let stage = match progress.stage() {
+        ProgressStage::Render => "render",
+        ProgressStage::Encode => "encode",
+    };
+    let filled = (progress.completed() * PROGRESS_BAR_WIDTH)
+        .checked_div(progress.total())
+        .unwrap_or(PROGRESS_BAR_WIDTH)
+        .min(PROGRESS_BAR_WIDTH);
+    let eta = match progress.eta() {
+        Some(eta) => format!("{}s", eta.as_secs()),
+        None => String::from("?"),
+    };
+
+    // clear the rest of the line, in case the last bar drawn was longer
+    eprint!(
+        "\r{stage} [{}{}] {}/{} ETA {eta}\x1b[K",
+        "#".repeat(filled),
+        "-".repeat(PROGRESS_BAR_WIDTH - filled),
+        progress.completed(),
+        progress.total()
+    );
+    if progress.completed() >= progress.total() {
+        eprintln!();
+    }
+}
+
+/// Renders a batch of PNGs listed in a manifest file as ASCII and saves them to PNG. Images that
+/// fail to convert are logged, without stopping the rest of the batch.
+///
+/// * `manifest`: The path to the manifest file, where each line holds an input path and an output
+///   path separated by a tab.
+/// * `imgii_options`: The imgii options for rendering ASCII as PNG.
+///
+/// # Panics
+/// If the manifest can't be read or a line doesn't hold both paths, this will cause the program
+/// to panic.
+fn convert_manifest_batch(manifest: &Path, imgii_options: &ImgiiOptions) {
+    let starting_time = Instant::now();
+
+    let contents = match std::fs::read_to_string(manifest) {
+        Ok(contents) => contents,
+        Err(err) => panic!("Could not read manifest {} ({})", manifest.display(), err),
+    };
+    let inputs: Vec<_> = contents
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty() && !line.starts_with('#'))
+        .map(|(i, line)| match line.split_once('\t') {
+            Some((input, output)) => (String::from(input), String::from(output)),
+            None => panic!(
+                "Line {} of manifest {} should hold an input and output path separated by a tab",
+                i + 1,
+                manifest.display()
+            ),
+        })
+        .collect();
+
+    let report = convert_batch(inputs, imgii_options);
+    for failure in report.failures() {
+        log::error!(
+            "Could not save PNG {} from {} ({})",
+            failure.output_file_name(),
+            failure.input_file_name(),
+            failure.error()
+        );
+    }
+
+    log::info!(
+        "Converted {} of {} PNG(s)",
+        report.succeeded(),
+        report.total()
+    );
+    log::info!(
+        "Time elapsed: {} seconds / {} milliseconds",
+        starting_time.elapsed().as_secs(),
+        starting_time.elapsed().as_millis()
+    );
+}
+
 /// Renders a batch of PNGs as ASCII and saves to PNG.
 ///
 /// * `final_image_index`: The final image index of input PNGs.
 /// * `input_name_format`: The input name format for input PNGs.
 /// * `output_name_format`: The output name format for saved PNGs.
 /// * `imgii_options`: The imgii options for rendering ASCII as PNG.
+/// * `batch_concurrency`: The maximum number of PNGs converted at once, if limited. Each PNG is
+///   still rendered in parallel.
 ///
 /// # Panics
 /// If a thread fails to convert an image to ASCII, this will cause the program to panic.
@@ -348,11 +1254,11 @@ fn convert_png_batch(
     input_name_format: Arc<String>,
     output_name_format: Arc<String>,
     imgii_options: Arc<ImgiiOptions>,
+    batch_concurrency: Option<u32>,
 ) {
     let starting_time = Instant::now();
 
-    // NOTE: if a single thread panics here, the whole program panics
-    (1..=final_image_index).into_par_iter().for_each(|i| {
+    let convert_image = |i: u32| {
         let input_name_format_arc = Arc::clone(&input_name_format);
         let output_name_format_arc = Arc::clone(&output_name_format);
         let imgii_options_arc = Arc::clone(&imgii_options);
@@ -361,14 +1267,44 @@ fn convert_png_batch(
         let input_file_name = input_name_format_arc.replace("%d", i.to_string().as_str());
         let output_file_name = output_name_format_arc.replace("%d", i.to_string().as_str());
         match convert_to_ascii_png(&input_file_name, &output_file_name, &imgii_options_arc) {
-            Ok(_) => {
+            Ok(timings) => {
                 log::info!("Saved PNG {}", output_file_name);
+                log_timings(&output_file_name, timings);
+            }
+            Err(ImgiiError::Cancelled) => {
+                // the rest of the batch is skipped the same way, so just note it
+                log::info!("Cancelled converting PNG {}", output_file_name);
             }
             Err(err) => {
                 panic!("Could not save PNG {} ({})", output_file_name, err);
             }
         };
-    });
+    };
+
+    // NOTE: if a single thread panics here, the whole program panics
+    match batch_concurrency {
+        None => (1..=final_image_index)
+            .into_par_iter()
+            .for_each(convert_image),
+        Some(batch_concurrency) => {
+            // convert images from a fixed number of threads outside of the thread pool, so each
+            // image still renders on the whole pool without every image competing for it
+            let next_index = AtomicU32::new(1);
+            thread::scope(|scope| {
+                for _ in 0..batch_concurrency {
+                    scope.spawn(|| {
+                        loop {
+                            let i = next_index.fetch_add(1, Ordering::Relaxed);
+                            if i > final_image_index {
+                                break;
+                            }
+                            convert_image(i);
+                        }
+                    });
+                }
+            });
+        }
+    }
 
     log::info!("---Success!---");
     log::info!(
@@ -1,197 +1,2429 @@
 //! The options for using imgii.
 
-use std::fmt::Display;
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    fmt::Display,
+    path::PathBuf,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+};
+
+// We need to re-export these, as they might be necessary for users of this library. Imgii's CLI
+// uses these.
+pub use rascii_art_img::RenderOptions as RasciiOptions;
+pub use rascii_art_img::{
+    charsets::{Charset, from_enum, to_charset_enum},
+    convert_string_to_str_vec,
+};
+
+use image::{GrayImage, Rgba, codecs::png::CompressionType, imageops::FilterType};
+
+use crate::{
+    ascii_source::{AsciiSource, RasciiSource},
+    encoder::{CustomEncoder, EncodeFn},
+    error::ImgiiError,
+    progress::{ProgressCallback, ProgressFn},
+    sdf_glyph_cache::{SdfGlyphCache, hash_font},
+};
+#[cfg(feature = "system-fonts")]
+use crate::{error::FontError, fonts::load_monospace_font};
+
+const DEFAULT_CHAR_FONT_SIZE: u32 = 16;
+const DEFAULT_TAB_WIDTH: u32 = 4;
+const DEFAULT_BACKGROUND_COLOR: Rgba<u8> = Rgba([0, 0, 0, u8::MAX]);
+const DEFAULT_MAX_LINE_CHARS: usize = 1 << 22;
+// a subtle glow for the phosphor look
+const PHOSPHOR_BLOOM_RADIUS: f32 = 2.0;
+const PHOSPHOR_BLOOM_STRENGTH: f32 = 0.6;
+
+/// The order of color channels in raw output bytes.
+///
+/// Only affects outputs where channel order is meaningful (raw pixel data). Formats such as PNG
+/// and GIF are standardized by the `image` crate and ignore this value.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ChannelOrder {
+    /// Red, green, blue, alpha.
+    #[default]
+    Rgba,
+    /// Blue, green, red, alpha.
+    Bgra,
+    /// Alpha, red, green, blue.
+    Argb,
+}
+
+impl ChannelOrder {
+    /// Reorders a single RGBA pixel into this channel order.
+    ///
+    /// * `rgba`: The pixel channels, in RGBA order.
+    #[must_use]
+    pub fn swizzle(&self, rgba: [u8; 4]) -> [u8; 4] {
+        let [r, g, b, a] = rgba;
+        match *self {
+            ChannelOrder::Rgba => [r, g, b, a],
+            ChannelOrder::Bgra => [b, g, r, a],
+            ChannelOrder::Argb => [a, r, g, b],
+        }
+    }
+}
+
+/// How whitespace cells are rendered.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SpaceHandling {
+    /// Whitespace cells are filled with the background if it is enabled, otherwise they are
+    /// transparent.
+    #[default]
+    Background,
+    /// Whitespace cells are always fully transparent, even when the background is enabled. Useful
+    /// for punching holes in the background.
+    Transparent,
+    /// Whitespace cells are always filled with this color.
+    Custom(Rgba<u8>),
+}
+
+/// The order that cells are revealed in for a typewriter animation.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RevealOrder {
+    /// Reveal each row from left to right, starting from the top row.
+    #[default]
+    RowMajor,
+    /// Reveal each column from top to bottom, starting from the leftmost column.
+    ColumnMajor,
+}
+
+/// Options for rendering a typewriter animation, where the ASCII is revealed cell-by-cell.
+#[derive(Debug, Clone, Copy)]
+pub struct TypewriterOptions {
+    /// The number of cells revealed by each frame.
+    cells_per_frame: usize,
+    /// The delay of each frame, in milliseconds.
+    delay_ms: u32,
+    /// The order that cells are revealed in.
+    reveal_order: RevealOrder,
+}
+
+impl TypewriterOptions {
+    /// Creates new typewriter options, revealing cells in row-major order.
+    ///
+    /// * `cells_per_frame`: The number of cells revealed by each frame. Must be non-zero.
+    /// * `delay_ms`: The delay of each frame, in milliseconds.
+    pub fn new(cells_per_frame: usize, delay_ms: u32) -> Result<Self, ImgiiError> {
+        if cells_per_frame == 0 {
+            return Err(ImgiiError::InvalidArgument);
+        }
+
+        Ok(Self {
+            cells_per_frame,
+            delay_ms,
+            reveal_order: RevealOrder::default(),
+        })
+    }
+
+    /// Sets the order that cells are revealed in.
+    ///
+    /// * `reveal_order`: The reveal order.
+    pub fn with_reveal_order(mut self, reveal_order: RevealOrder) -> Self {
+        self.reveal_order = reveal_order;
+        self
+    }
+
+    /// Gets the number of cells revealed by each frame.
+    #[must_use]
+    pub fn cells_per_frame(&self) -> usize {
+        self.cells_per_frame
+    }
+
+    /// Gets the delay of each frame, in milliseconds.
+    #[must_use]
+    pub fn delay_ms(&self) -> u32 {
+        self.delay_ms
+    }
+
+    /// Gets the order that cells are revealed in.
+    #[must_use]
+    pub fn reveal_order(&self) -> RevealOrder {
+        self.reveal_order
+    }
+}
+
+/// How the alpha channel is handled when writing a PNG.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PngAlpha {
+    /// Writes an RGBA PNG, keeping the alpha channel.
+    #[default]
+    Keep,
+    /// Writes an RGB PNG, dropping the alpha channel. Returns an error if the output image has any
+    /// pixels that aren't fully opaque, such as when the background is disabled.
+    Strip,
+    /// Writes an RGB PNG, flattening any pixels that aren't fully opaque onto a black background
+    /// before dropping the alpha channel.
+    Flatten,
+}
+
+/// The bit depth of each channel of an output PNG.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BitDepth {
+    /// Writes 8 bits per channel.
+    #[default]
+    Eight,
+    /// Writes 16 bits per channel. Glyphs are still drawn at 8 bits, but the image is stitched and
+    /// post-processed at 16 bits, which avoids banding in smooth gradients.
+    Sixteen,
+}
+
+/// How hard an output PNG is compressed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PngCompression {
+    /// Compresses quickly, for a larger file.
+    Fast,
+    /// Balances the speed of compression against the size of the file.
+    #[default]
+    Default,
+    /// Compresses as much as possible, for the smallest file at the slowest speed.
+    Best,
+}
+
+impl From<PngCompression> for CompressionType {
+    fn from(value: PngCompression) -> Self {
+        match value {
+            PngCompression::Fast => CompressionType::Fast,
+            PngCompression::Default => CompressionType::Default,
+            PngCompression::Best => CompressionType::Best,
+        }
+    }
+}
+
+impl From<PngCompression> for png::Compression {
+    fn from(value: PngCompression) -> Self {
+        match value {
+            PngCompression::Fast => png::Compression::Fast,
+            PngCompression::Default => png::Compression::Balanced,
+            PngCompression::Best => png::Compression::High,
+        }
+    }
+}
+
+/// How the size of a cell is rounded when it isn't a whole number of pixels, such as a cell half as
+/// wide as an odd font size.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CellRounding {
+    /// Rounds down, which may clip the edge of wide glyphs.
+    #[default]
+    Floor,
+    /// Rounds to the nearest pixel, rounding halves up.
+    Nearest,
+    /// Rounds up, which may leave a gap between glyphs.
+    Ceil,
+}
+
+impl CellRounding {
+    /// Rounds a cell size to a whole number of pixels.
+    ///
+    /// * `size`: The exact size of the cell, in pixels.
+    pub(crate) fn round(self, size: f64) -> u32 {
+        let rounded = match self {
+            CellRounding::Floor => size.floor(),
+            CellRounding::Nearest => size.round(),
+            CellRounding::Ceil => size.ceil(),
+        };
+        rounded as u32
+    }
+}
+
+/// How each frame of an output GIF is disposed of before the next frame is drawn.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum GifDisposal {
+    /// Leaves the frame in place, drawing the next frame on top of it. Transparent pixels of the
+    /// next frame show the previous frame, which causes ghosting with transparent frames.
+    Keep,
+    /// Clears the frame to the background before drawing the next frame. Transparent pixels of the
+    /// next frame stay transparent, so this never ghosts.
+    #[default]
+    Background,
+    /// Restores the canvas to how it was before the frame was drawn.
+    Previous,
+}
+
+/// How the color of each cell is chosen from the source image.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CellColorMode {
+    /// Uses the color RASCII samples for the cell.
+    #[default]
+    Sampled,
+    /// Uses the average color of the region of the source image that the cell covers. Smoother
+    /// than sampling, especially with a charset override where the shape doesn't follow the image.
+    Average,
+    /// Uses the exact color of the source image pixel nearest to the center of the cell. Punchier
+    /// and truer to the source than the color RASCII samples, which can look washed out.
+    Center,
+}
+
+/// The filter used when resizing the output image.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ResizeFilter {
+    /// Copies the nearest pixel, keeping glyphs crisp when upscaling.
+    #[default]
+    Nearest,
+    /// Linear filtering.
+    Triangle,
+    /// Cubic filtering.
+    CatmullRom,
+    /// Lanczos filtering with a window of 3. The smoothest, but the slowest.
+    Lanczos3,
+}
+
+impl From<ResizeFilter> for FilterType {
+    fn from(value: ResizeFilter) -> Self {
+        match value {
+            ResizeFilter::Nearest => FilterType::Nearest,
+            ResizeFilter::Triangle => FilterType::Triangle,
+            ResizeFilter::CatmullRom => FilterType::CatmullRom,
+            ResizeFilter::Lanczos3 => FilterType::Lanczos3,
+        }
+    }
+}
+
+/// How much to randomly rotate and offset each glyph.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Jitter {
+    /// The maximum rotation of a glyph, in degrees.
+    max_degrees: f32,
+    /// The maximum offset of a glyph, in pixels.
+    max_offset: u32,
+    /// The seed for the randomness.
+    seed: u64,
+}
+
+impl Jitter {
+    /// Gets the maximum rotation of a glyph, in degrees, in either direction.
+    #[must_use]
+    pub fn max_degrees(&self) -> f32 {
+        self.max_degrees
+    }
+
+    /// Gets the maximum offset of a glyph, in pixels, in either direction on each axis.
+    #[must_use]
+    pub fn max_offset(&self) -> u32 {
+        self.max_offset
+    }
+
+    /// Gets the seed for the randomness.
+    #[must_use]
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+}
+
+/// The direction a gradient runs across the grid of cells.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum GradientDirection {
+    /// Each row is one color, running from the top row to the bottom row.
+    #[default]
+    Rows,
+    /// Each column is one color, running from the leftmost column to the rightmost column.
+    Columns,
+}
+
+/// How zero-width characters in the ASCII text, such as combining accents (like the accent in
+/// `e\u{301}`) and zero-width joiners, are placed in the grid of cells.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ZeroWidthHandling {
+    /// Zero-width characters are combined into the cell before them and drawn together with its
+    /// character, so they don't take up a cell. This keeps rows the same width as the text looks
+    /// in a terminal.
+    #[default]
+    Combine,
+    /// Every zero-width character takes up a cell of its own, in the colors of the character
+    /// before it. Rows with zero-width characters are wider than the others.
+    SeparateCell,
+}
+
+/// A gradient that colors every glyph by the position of its cell, overriding the colors of the
+/// source image.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GradientSpec {
+    /// The colors of the gradient as RGB, spaced evenly from the start to the end.
+    stops: Vec<[u8; 3]>,
+    /// The direction the gradient runs across the grid.
+    direction: GradientDirection,
+}
+
+impl GradientSpec {
+    /// Creates a new gradient, running across the rows of the grid.
+    ///
+    /// * `stops`: The colors of the gradient as RGB, spaced evenly from the first row to the last.
+    ///   Must not be empty.
+    pub fn new(stops: Vec<[u8; 3]>) -> Result<Self, ImgiiError> {
+        if stops.is_empty() {
+            return Err(ImgiiError::InvalidArgument);
+        }
+
+        Ok(Self {
+            stops,
+            direction: GradientDirection::default(),
+        })
+    }
+
+    /// Sets the direction the gradient runs across the grid.
+    ///
+    /// * `direction`: The direction of the gradient.
+    pub fn with_direction(mut self, direction: GradientDirection) -> Self {
+        self.direction = direction;
+        self
+    }
+
+    /// Gets the colors of the gradient as RGB.
+    #[must_use]
+    pub fn stops(&self) -> &[[u8; 3]] {
+        &self.stops
+    }
+
+    /// Gets the direction the gradient runs across the grid.
+    #[must_use]
+    pub fn direction(&self) -> GradientDirection {
+        self.direction
+    }
+
+    /// Gets the color of the gradient for a cell. The first row (or column) is exactly the first
+    /// stop and the last is exactly the last stop, with the rest interpolated linearly between
+    /// them.
+    ///
+    /// * `(row, column)`: The position of the cell.
+    /// * `(width, height)`: The size of the grid, in cells.
+    pub(crate) fn color_at(
+        &self,
+        (row, column): (usize, usize),
+        (width, height): (usize, usize),
+    ) -> [u8; 3] {
+        let (position, len) = match self.direction {
+            GradientDirection::Rows => (row, height),
+            GradientDirection::Columns => (column, width),
+        };
+        let last_stop = self.stops.len() - 1;
+        if len <= 1 || last_stop == 0 {
+            return self.stops[0];
+        }
+
+        // where the cell falls along the stops, from 0 to the last stop
+        let along = position.min(len - 1) as f64 / (len - 1) as f64 * last_stop as f64;
+        let start = (along.floor() as usize).min(last_stop - 1);
+        let weight = along - start as f64;
+        let (from, to) = (self.stops[start], self.stops[start + 1]);
+        std::array::from_fn(|i| {
+            (f64::from(from[i]) * (1.0 - weight) + f64::from(to[i]) * weight).round() as u8
+        })
+    }
+}
+
+// NOTE: we don't want to ever make members of ImgiiOptions public so users can't cause imgii to
+// crash by setting invalid options.
+
+/// Options for creating the output ASCII PNG.
+#[derive(Debug, Clone)]
+pub struct ImgiiOptions<'a> {
+    /// The loaded bytes containing the font.
+    font: Vec<u8>,
+
+    /// The font name.
+    font_name: String,
+
+    /// A hash of the font data, identifying the font in caches shared between options.
+    font_hash: u64,
+
+    /// The font size of the output image.
+    font_size: u32,
+
+    /// Sets a background behind the image, in the background color.
+    ///
+    /// No background by default.
+    background: bool,
+
+    /// Bypasses the glyph cache, rendering every character fresh.
+    ///
+    /// Glyph cache is enabled by default.
+    disable_glyph_cache: bool,
+
+    /// The channel order of raw output bytes.
+    ///
+    /// RGBA by default.
+    channel_order: ChannelOrder,
+
+    /// Pads the final canvas so its width and height are a multiple of this value, followed by the
+    /// color to pad with.
+    ///
+    /// No padding by default.
+    pad_to_multiple: Option<(u32, Rgba<u8>)>,
+
+    /// Synthetically emboldens glyphs by drawing them more than once with a pixel offset.
+    ///
+    /// Not bold by default.
+    bold: bool,
+
+    /// The maximum playback duration of an output GIF, in milliseconds. Frames are uniformly dropped
+    /// from longer GIFs until they fit.
+    ///
+    /// No target duration by default.
+    target_duration_ms: Option<u32>,
+
+    /// How whitespace cells are rendered.
+    ///
+    /// Follows the background flag by default.
+    space_handling: SpaceHandling,
+
+    /// Returns the timings of each conversion stage from conversion functions.
+    ///
+    /// No timings by default.
+    timings: bool,
+
+    /// Darkens every nth row of the final canvas for a CRT scanline effect. Holds the spacing between
+    /// darkened rows, followed by how much to darken them.
+    ///
+    /// No scanlines by default.
+    scanlines: Option<(u32, u8)>,
+
+    /// How the alpha channel is handled when writing a PNG.
+    ///
+    /// Alpha is kept by default.
+    png_alpha: PngAlpha,
+
+    /// Randomly rotates and offsets each glyph for a hand-drawn look.
+    ///
+    /// No jitter by default.
+    jitter: Option<Jitter>,
+
+    /// Only renders the glyph of every nth cell, leaving the rest empty.
+    ///
+    /// Every cell is rendered by default.
+    sparsity: u32,
+
+    /// Embeds the ICC profile of the input image in the output PNG.
+    ///
+    /// Not preserved by default.
+    preserve_icc_profile: bool,
+
+    /// How each frame of an output GIF is disposed of.
+    ///
+    /// Background by default, which avoids ghosting with transparent frames.
+    gif_disposal: GifDisposal,
+
+    /// The number of transparent cells each tab in the ASCII text is expanded to.
+    ///
+    /// 4 by default.
+    tab_width: u32,
+
+    /// Renders each character in a square cell, instead of a cell twice as tall as it is wide.
+    ///
+    /// Disabled by default.
+    square_cells: bool,
+
+    /// The maximum number of bytes a rendered image may take up.
+    ///
+    /// Derived from the available memory with the `sysinfo` feature, otherwise unlimited by default.
+    memory_budget: Option<u64>,
+
+    /// Runs edge detection on the source image before converting it to ASCII.
+    ///
+    /// Disabled by default.
+    edge_detect: bool,
+
+    /// How the color of each cell is chosen from the source image.
+    ///
+    /// Sampled by default.
+    cell_color_mode: CellColorMode,
+
+    /// Multipliers for the font size of specific characters, drawing them larger or smaller.
+    ///
+    /// No emphasis by default.
+    emphasis: HashMap<char, f32>,
+
+    /// Scales the final output image by a factor, after it has been stitched together.
+    ///
+    /// No scaling by default.
+    scale_output: Option<f32>,
+
+    /// The filter used when scaling the final output image.
+    ///
+    /// Nearest by default.
+    resize_filter_output: ResizeFilter,
+
+    /// The maximum number of frames of an output GIF.
+    ///
+    /// No limit by default.
+    max_frames: Option<usize>,
+
+    /// Renders every glyph in the brightness of its color, instead of its color.
+    ///
+    /// Disabled by default.
+    grayscale: bool,
+
+    /// Skips writing output files that already hold exactly the rendered bytes.
+    ///
+    /// Disabled by default.
+    skip_if_unchanged: bool,
+
+    /// Converts images into the colored ASCII text to render.
+    ///
+    /// RASCII by default.
+    ascii_source: Arc<dyn AsciiSource>,
+
+    /// The color of the background, when the background is enabled. Can be semi-transparent.
+    ///
+    /// Opaque black by default.
+    background_color: Rgba<u8>,
+
+    /// Renders cells holding the lightest character of the charset (its first entry) like
+    /// whitespace, so only the denser characters are drawn.
+    ///
+    /// Disabled by default.
+    lightest_transparent: bool,
+
+    /// The bit depth of each channel of an output PNG.
+    ///
+    /// 8 bits by default.
+    bit_depth: BitDepth,
+
+    /// Encodes single output images instead of the format chosen by the file extension.
+    ///
+    /// Not set by default.
+    encoder: Option<CustomEncoder>,
+
+    /// Cancels long-running conversions once set to true.
+    ///
+    /// Not set by default.
+    cancel_flag: Option<Arc<AtomicBool>>,
+
+    /// Resizes the rendered image to exactly the size of the input image, so each cell covers the
+    /// region of the input image it was converted from.
+    ///
+    /// Disabled by default.
+    match_source_resolution: bool,
+
+    /// The (width, height) of the input image being converted, if the rendered image is resized to
+    /// match it. Only set while converting.
+    source_resolution: Option<(u32, u32)>,
+
+    /// Returns an error if any character of the ASCII text is dropped while parsing it, instead of
+    /// silently skipping it.
+    ///
+    /// Disabled by default.
+    strict_parse: bool,
+
+    /// How the width of a cell is rounded when it isn't a whole number of pixels.
+    cell_rounding: CellRounding,
+
+    /// The number of rendered GIF frames that may wait to be encoded when rendering and encoding
+    /// overlap, or `None` to render every frame before encoding.
+    gif_pipeline_depth: Option<usize>,
+
+    /// The maximum number of characters (escape sequences included) in a line of ASCII text.
+    max_line_chars: usize,
+
+    /// Fills the background of each cell with the average color of the region of the source image
+    /// that it covers.
+    cell_bg_from_source: bool,
+
+    /// Draws glyphs from a cache of signed distance fields instead of rasterizing them at the font
+    /// size.
+    ///
+    /// Not set by default.
+    sdf_glyph_cache: Option<SdfGlyphCache>,
+
+    /// Whether to embed the source file name and key render settings as text metadata in output
+    /// PNGs.
+    embed_metadata: bool,
+
+    /// Whether output files are written to a temporary file, then renamed over the output file.
+    atomic_writes: bool,
+
+    /// Whether cells are stitched at their exact fractional positions when matching the source
+    /// resolution.
+    subpixel_cells: bool,
+
+    /// Whether inputs that are colored ASCII text, rather than images, are rendered directly.
+    ansi_input: bool,
+
+    /// Whether the background is filled once across the whole image, under every glyph.
+    continuous_background: bool,
+
+    /// Whether PNGs are written with indexed color, using a palette of the colors in the image.
+    png_palette: bool,
+
+    /// How hard output PNGs are compressed.
+    png_compression: PngCompression,
+
+    /// The gradient that colors every glyph by the position of its cell, if any.
+    gradient_override: Option<GradientSpec>,
+
+    /// How zero-width characters, such as combining accents, are placed in the grid of cells.
+    zero_width_handling: ZeroWidthHandling,
+
+    /// The size of the grid of cells that is rendered, as (width, height), cropping and padding the
+    /// ASCII text to fit. The ASCII text's own size is kept when this is `None`.
+    fixed_grid: Option<(u32, u32)>,
+
+    /// The color reserved as the transparent color of output GIFs, if any. Only its RGB is used.
+    gif_transparent_key: Option<Rgba<u8>>,
+
+    /// The blur radius and strength of the glow added over the final canvas, if any.
+    bloom: Option<(f32, f32)>,
+
+    /// Whether missing parent directories of output files are created.
+    create_dirs: bool,
+
+    /// The grayscale mask that fades out cells, loaded from the mask image. Dark parts of the mask
+    /// make cells transparent.
+    opacity_mask: Option<Arc<GrayImage>>,
+
+    /// The number of levels each color channel of the source image is reduced to before it's
+    /// converted to ASCII, if any.
+    posterize: Option<u8>,
+
+    /// Reports the progress of converting a GIF as each frame is rendered and encoded.
+    ///
+    /// Not set by default.
+    progress: Option<ProgressCallback>,
+
+    /// The RASCII options for converting an image to ASCII.
+    rascii_options: RasciiOptions<'a>,
+}
+
+impl<'a> ImgiiOptions<'a> {
+    /// Gets the font data.
+    #[must_use]
+    pub fn font(&self) -> &Vec<u8> {
+        &self.font
+    }
+
+    /// Gets the font name.
+    #[must_use]
+    pub fn font_name(&self) -> &str {
+        &self.font_name
+    }
+
+    /// Gets a hash of the font data, which tells apart different fonts even if they have the same
+    /// name.
+    pub(crate) fn font_hash(&self) -> u64 {
+        self.font_hash
+    }
+
+    /// Gets the font size to use to generate the image.
+    #[must_use]
+    pub fn font_size(&self) -> u32 {
+        self.font_size
+    }
+
+    /// Gets the background flag. If true, sets a background behind the output image.
+    #[must_use]
+    pub fn background(&self) -> bool {
+        self.background
+    }
+
+    /// Gets the disable glyph cache flag. If true, every character is rendered without reusing
+    /// previously rendered glyphs.
+    #[must_use]
+    pub fn disable_glyph_cache(&self) -> bool {
+        self.disable_glyph_cache
+    }
+
+    /// Gets the channel order used when writing raw output bytes.
+    #[must_use]
+    pub fn channel_order(&self) -> ChannelOrder {
+        self.channel_order
+    }
+
+    /// Gets the padding multiple and padding color, if the canvas should be padded.
+    #[must_use]
+    pub fn pad_to_multiple(&self) -> Option<(u32, Rgba<u8>)> {
+        self.pad_to_multiple
+    }
+
+    /// Gets the bold flag. If true, glyphs are synthetically emboldened.
+    #[must_use]
+    pub fn bold(&self) -> bool {
+        self.bold
+    }
+
+    /// Gets the target duration of an output GIF in milliseconds, if any.
+    #[must_use]
+    pub fn target_duration_ms(&self) -> Option<u32> {
+        self.target_duration_ms
+    }
+
+    /// Gets how whitespace cells are rendered.
+    #[must_use]
+    pub fn space_handling(&self) -> SpaceHandling {
+        self.space_handling
+    }
+
+    /// Gets the timings flag. If true, conversion functions return the timings of each stage.
+    #[must_use]
+    pub fn timings(&self) -> bool {
+        self.timings
+    }
+
+    /// Gets the scanline spacing and darkness, if scanlines should be drawn.
+    #[must_use]
+    pub fn scanlines(&self) -> Option<(u32, u8)> {
+        self.scanlines
+    }
+
+    /// Gets how the alpha channel is handled when writing a PNG.
+    #[must_use]
+    pub fn png_alpha(&self) -> PngAlpha {
+        self.png_alpha
+    }
+
+    /// Gets the jitter applied to each glyph, if any.
+    #[must_use]
+    pub fn jitter(&self) -> Option<Jitter> {
+        self.jitter
+    }
+
+    /// Gets the sparsity. Only every nth cell has its glyph rendered.
+    #[must_use]
+    pub fn sparsity(&self) -> u32 {
+        self.sparsity
+    }
+
+    /// Gets the preserve ICC profile flag. If true, the ICC profile of the input image is embedded
+    /// in the output PNG.
+    #[must_use]
+    pub fn preserve_icc_profile(&self) -> bool {
+        self.preserve_icc_profile
+    }
+
+    /// Gets how each frame of an output GIF is disposed of.
+    #[must_use]
+    pub fn gif_disposal(&self) -> GifDisposal {
+        self.gif_disposal
+    }
+
+    /// Gets the number of transparent cells each tab in the ASCII text is expanded to.
+    #[must_use]
+    pub fn tab_width(&self) -> u32 {
+        self.tab_width
+    }
+
+    /// Gets whether each character is rendered in a square cell.
+    #[must_use]
+    pub fn square_cells(&self) -> bool {
+        self.square_cells
+    }
+
+    /// Creates a copy of these options that converts images to a grid of exactly the given size,
+    /// regardless of the configured width and height.
+    ///
+    /// * `width`: The width of the grid, in characters.
+    /// * `height`: The height of the grid, in characters.
+    pub(crate) fn with_grid_size(&self, width: u32, height: u32) -> Self {
+        let mut imgii_options = self.clone();
+        imgii_options.rascii_options.width = Some(width);
+        imgii_options.rascii_options.height = Some(height);
+        imgii_options
+    }
+
+    /// Gets these options for converting an input image of the given size. If the rendered image is
+    /// resized to match the source resolution, this is a copy holding the size of the input image.
+    ///
+    /// * `width`: The width of the input image, in pixels.
+    /// * `height`: The height of the input image, in pixels.
+    pub(crate) fn for_source(&self, width: u32, height: u32) -> Cow<'_, Self> {
+        if !self.match_source_resolution {
+            return Cow::Borrowed(self);
+        }
+        let mut imgii_options = self.clone();
+        imgii_options.source_resolution = Some((width.max(1), height.max(1)));
+        Cow::Owned(imgii_options)
+    }
+
+    /// Gets the exact (width, height) to resize the rendered image to, if it's matching the size of
+    /// an input image.
+    pub(crate) fn source_resolution(&self) -> Option<(u32, u32)> {
+        self.source_resolution
+    }
+
+    /// Gets whether rendered images are grayscale with no transparency, so they can be stored with
+    /// a single channel.
+    pub(crate) fn renders_opaque_grayscale(&self) -> bool {
+        let opaque_spaces = match self.space_handling {
+            SpaceHandling::Background => true,
+            SpaceHandling::Transparent => false,
+            SpaceHandling::Custom(color) => color.0[3] == u8::MAX,
+        };
+        self.grayscale && self.background && self.background_color.0[3] == u8::MAX && opaque_spaces
+    }
+
+    /// Gets the character to render like whitespace, if the lightest character of the charset is
+    /// transparent and it is a single character.
+    pub(crate) fn transparent_char(&self) -> Option<char> {
+        if !self.lightest_transparent {
+            return None;
+        }
+        let mut chars = self.rascii_options.charset.first()?.chars();
+        match (chars.next(), chars.next()) {
+            (Some(character), None) => Some(character),
+            _ => None,
+        }
+    }
+
+    /// Checks whether the conversion has been cancelled with the cancel flag.
+    ///
+    /// # Returns
+    /// * [`ImgiiError::Cancelled`] if the cancel flag is set, otherwise `Ok`.
+    pub(crate) fn check_cancelled(&self) -> Result<(), ImgiiError> {
+        match &self.cancel_flag {
+            Some(cancel_flag) if cancel_flag.load(Ordering::Relaxed) => Err(ImgiiError::Cancelled),
+            _ => Ok(()),
+        }
+    }
+
+    /// Gets the memory budget to check rendered images against, falling back to the available
+    /// memory with the `sysinfo` feature.
+    pub(crate) fn effective_memory_budget(&self) -> Option<u64> {
+        #[cfg(feature = "sysinfo")]
+        if self.memory_budget.is_none() {
+            let mut system = sysinfo::System::new();
+            system.refresh_memory();
+            return Some(system.available_memory());
+        }
+
+        self.memory_budget
+    }
+
+    /// Gets the RASCII options to convert an image of the given size with. If square cells are
+    /// enabled, the grid size is set here so RASCII doesn't squish the image for tall cells.
+    ///
+    /// * `image_width`: The width of the image to convert, in pixels.
+    /// * `image_height`: The height of the image to convert, in pixels.
+    pub(crate) fn rascii_options_for(
+        &self,
+        image_width: u32,
+        image_height: u32,
+    ) -> RasciiOptions<'a> {
+        let mut rascii_options = self.rascii_options.clone();
+        if !self.square_cells || image_width == 0 || image_height == 0 {
+            return rascii_options;
+        }
+
+        // keep the aspect ratio of the image, since each cell is square
+        let (image_width, image_height) = (u64::from(image_width), u64::from(image_height));
+        let scale = |value: u32, from: u64, to: u64| ((u64::from(value) * to / from).max(1)) as u32;
+        match (rascii_options.width, rascii_options.height) {
+            (Some(_), Some(_)) => {}
+            (Some(width), None) => {
+                rascii_options.height = Some(scale(width, image_width, image_height));
+            }
+            (None, Some(height)) => {
+                rascii_options.width = Some(scale(height, image_height, image_width));
+            }
+            (None, None) => {
+                rascii_options.width = Some(image_width as u32);
+                rascii_options.height = Some(image_height as u32);
+            }
+        }
+        rascii_options
+    }
+
+    /// Gets the maximum number of bytes a rendered image may take up, if one was set.
+    #[must_use]
+    pub fn memory_budget(&self) -> Option<u64> {
+        self.memory_budget
+    }
+
+    /// Gets whether edge detection is run on the source image before converting it to ASCII.
+    #[must_use]
+    pub fn edge_detect(&self) -> bool {
+        self.edge_detect
+    }
+
+    /// Gets how the color of each cell is chosen from the source image.
+    #[must_use]
+    pub fn cell_color_mode(&self) -> CellColorMode {
+        self.cell_color_mode
+    }
+
+    /// Gets the font size multiplier of each emphasized character.
+    #[must_use]
+    pub fn emphasis(&self) -> &HashMap<char, f32> {
+        &self.emphasis
+    }
+
+    /// Gets the factor the final output image is scaled by, if it is scaled.
+    #[must_use]
+    pub fn scale_output(&self) -> Option<f32> {
+        self.scale_output
+    }
+
+    /// Gets the filter used when scaling the final output image.
+    #[must_use]
+    pub fn resize_filter_output(&self) -> ResizeFilter {
+        self.resize_filter_output
+    }
+
+    /// Gets the maximum number of frames of an output GIF, if there is one.
+    #[must_use]
+    pub fn max_frames(&self) -> Option<usize> {
+        self.max_frames
+    }
+
+    /// Gets whether every glyph is rendered in grayscale.
+    #[must_use]
+    pub fn grayscale(&self) -> bool {
+        self.grayscale
+    }
+
+    /// Gets whether output files that already hold exactly the rendered bytes are left untouched.
+    #[must_use]
+    pub fn skip_if_unchanged(&self) -> bool {
+        self.skip_if_unchanged
+    }
+
+    /// Gets the source that converts images into the colored ASCII text to render.
+    #[must_use]
+    pub fn ascii_source(&self) -> &dyn AsciiSource {
+        self.ascii_source.as_ref()
+    }
+
+    /// Gets the color of the background, which is used when the background is enabled.
+    #[must_use]
+    pub fn background_color(&self) -> Rgba<u8> {
+        self.background_color
+    }
+
+    /// Gets whether cells holding the lightest character of the charset are rendered like
+    /// whitespace.
+    #[must_use]
+    pub fn lightest_transparent(&self) -> bool {
+        self.lightest_transparent
+    }
+
+    /// Gets the bit depth of each channel of an output PNG.
+    #[must_use]
+    pub fn bit_depth(&self) -> BitDepth {
+        self.bit_depth
+    }
+
+    /// Gets the custom encoder for single output images, if there is one.
+    #[must_use]
+    pub fn encoder(&self) -> Option<&EncodeFn> {
+        self.encoder.as_ref().map(|encoder| encoder.0.as_ref())
+    }
+
+    /// Gets the flag that cancels long-running conversions, if there is one.
+    #[must_use]
+    pub fn cancel_flag(&self) -> Option<&Arc<AtomicBool>> {
+        self.cancel_flag.as_ref()
+    }
+
+    /// Gets whether the rendered image is resized to exactly the size of the input image.
+    #[must_use]
+    pub fn match_source_resolution(&self) -> bool {
+        self.match_source_resolution
+    }
+
+    /// Gets whether dropping a character while parsing ASCII text is an error.
+    #[must_use]
+    pub fn strict_parse(&self) -> bool {
+        self.strict_parse
+    }
+
+    /// Gets how the width of a cell is rounded when it isn't a whole number of pixels.
+    #[must_use]
+    pub fn cell_rounding(&self) -> CellRounding {
+        self.cell_rounding
+    }
+
+    /// Gets the number of rendered GIF frames that may wait to be encoded when rendering and
+    /// encoding overlap, if they do.
+    #[must_use]
+    pub fn gif_pipeline_depth(&self) -> Option<usize> {
+        self.gif_pipeline_depth
+    }
+
+    /// Gets the maximum number of characters (escape sequences included) in a line of ASCII text.
+    #[must_use]
+    pub fn max_line_chars(&self) -> usize {
+        self.max_line_chars
+    }
+
+    /// Gets whether the background of each cell is filled with the average color of the region of
+    /// the source image that it covers.
+    #[must_use]
+    pub fn cell_bg_from_source(&self) -> bool {
+        self.cell_bg_from_source
+    }
+
+    /// Gets the signed distance field glyph cache glyphs are drawn from, if there is one.
+    #[must_use]
+    pub fn sdf_glyph_cache(&self) -> Option<&SdfGlyphCache> {
+        self.sdf_glyph_cache.as_ref()
+    }
+
+    /// Gets whether to embed the source file name and key render settings as text metadata in output
+    /// PNGs.
+    #[must_use]
+    pub fn embed_metadata(&self) -> bool {
+        self.embed_metadata
+    }
+
+    /// Gets whether output files are written to a temporary file, then renamed over the output file.
+    #[must_use]
+    pub fn atomic_writes(&self) -> bool {
+        self.atomic_writes
+    }
+
+    /// Gets whether cells are stitched at their exact fractional positions when matching the source
+    /// resolution.
+    #[must_use]
+    pub fn subpixel_cells(&self) -> bool {
+        self.subpixel_cells
+    }
+
+    /// Gets whether inputs that are colored ASCII text, rather than images, are rendered directly.
+    #[must_use]
+    pub fn ansi_input(&self) -> bool {
+        self.ansi_input
+    }
+
+    /// Gets whether the background is filled once across the whole image, under every glyph,
+    /// rather than into each cell.
+    #[must_use]
+    pub fn continuous_background(&self) -> bool {
+        self.continuous_background
+    }
+
+    /// Gets whether PNGs are written with indexed color, using a palette of the colors in the
+    /// image.
+    #[must_use]
+    pub fn png_palette(&self) -> bool {
+        self.png_palette
+    }
+
+    /// Gets how hard output PNGs are compressed.
+    #[must_use]
+    pub fn png_compression(&self) -> PngCompression {
+        self.png_compression
+    }
+
+    /// Gets the gradient that colors every glyph by the position of its cell, if any.
+    #[must_use]
+    pub fn gradient_override(&self) -> Option<&GradientSpec> {
+        self.gradient_override.as_ref()
+    }
+
+    /// Gets how zero-width characters, such as combining accents, are placed in the grid of cells.
+    #[must_use]
+    pub fn zero_width_handling(&self) -> ZeroWidthHandling {
+        self.zero_width_handling
+    }
+
+    /// Gets the size of the grid of cells that is rendered, as (width, height), if it's fixed.
+    #[must_use]
+    pub fn fixed_grid(&self) -> Option<(u32, u32)> {
+        self.fixed_grid
+    }
+
+    /// Gets the color reserved as the transparent color of output GIFs, if any.
+    #[must_use]
+    pub fn gif_transparent_key(&self) -> Option<Rgba<u8>> {
+        self.gif_transparent_key
+    }
+
+    /// Gets the blur radius and strength of the glow added over the final canvas, if there is
+    /// one.
+    #[must_use]
+    pub fn bloom(&self) -> Option<(f32, f32)> {
+        self.bloom
+    }
+
+    /// Gets whether missing parent directories of output files are created.
+    #[must_use]
+    pub fn create_dirs(&self) -> bool {
+        self.create_dirs
+    }
+
+    /// Gets the grayscale mask that fades out cells, if there is one.
+    #[must_use]
+    pub fn opacity_mask(&self) -> Option<&GrayImage> {
+        self.opacity_mask.as_deref()
+    }
+
+    /// Gets the number of levels each color channel of the source image is reduced to before
+    /// it's converted to ASCII, if it's posterized.
+    #[must_use]
+    pub fn posterize(&self) -> Option<u8> {
+        self.posterize
+    }
+
+    /// Gets the callback that reports the progress of converting a GIF, if there is one.
+    #[must_use]
+    pub fn progress(&self) -> Option<&ProgressFn> {
+        self.progress.as_ref().map(|progress| progress.0.as_ref())
+    }
+
+    /// Gets the RASCII options.
+    #[must_use]
+    pub fn rascii_options(&self) -> &RasciiOptions<'a> {
+        &self.rascii_options
+    }
+}
+
+impl<'a> Display for ImgiiOptions<'a> {
+    /// Writes an `[ImgiiOptions]` instance. This should be preferred over debug printing, as
+    /// `[ImgiiOptions]` can hold a lot of binary data.
+    ///
+    /// * `f`: The formatter.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // write everything that won't spam a bunch of binary data
+        write!(
+            f,
+            "{{font.len()={}; font_name={}; font_size={}, background={}; disable_glyph_cache={}; channel_order={:?}; pad_to_multiple={:?}; bold={}; target_duration_ms={:?}; space_handling={:?}; timings={}; scanlines={:?}; png_alpha={:?}; jitter={:?}; sparsity={}; preserve_icc_profile={}; gif_disposal={:?}; tab_width={}; square_cells={}; memory_budget={:?}; edge_detect={}; cell_color_mode={:?}; emphasis={:?}; scale_output={:?}; resize_filter_output={:?}; max_frames={:?}; grayscale={}; skip_if_unchanged={}; ascii_source={:?}; background_color={:?}; lightest_transparent={}; bit_depth={:?}; encoder={}; cancel_flag={}; match_source_resolution={}; strict_parse={}; cell_rounding={:?}; gif_pipeline_depth={:?}; max_line_chars={}; cell_bg_from_source={}; sdf_glyph_cache={}; embed_metadata={}; atomic_writes={}; subpixel_cells={}; ansi_input={}; continuous_background={}; png_palette={}; png_compression={:?}; gradient_override={:?}; zero_width_handling={:?}; fixed_grid={:?}; gif_transparent_key={:?}; bloom={:?}; create_dirs={}; opacity_mask={:?}; posterize={:?}; progress={}; rascii_options={:?}}}",
+            self.font.len(),
+            self.font_name,
+            self.font_size,
+            self.background,
+            self.disable_glyph_cache,
+            self.channel_order,
+            self.pad_to_multiple,
+            self.bold,
+            self.target_duration_ms,
+            self.space_handling,
+            self.timings,
+            self.scanlines,
+            self.png_alpha,
+            self.jitter,
+            self.sparsity,
+            self.preserve_icc_profile,
+            self.gif_disposal,
+            self.tab_width,
+            self.square_cells,
+            self.memory_budget,
+            self.edge_detect,
+            self.cell_color_mode,
+            self.emphasis,
+            self.scale_output,
+            self.resize_filter_output,
+            self.max_frames,
+            self.grayscale,
+            self.skip_if_unchanged,
+            self.ascii_source,
+            self.background_color,
+            self.lightest_transparent,
+            self.bit_depth,
+            self.encoder.is_some(),
+            self.cancel_flag.is_some(),
+            self.match_source_resolution,
+            self.strict_parse,
+            self.cell_rounding,
+            self.gif_pipeline_depth,
+            self.max_line_chars,
+            self.cell_bg_from_source,
+            self.sdf_glyph_cache.is_some(),
+            self.embed_metadata,
+            self.atomic_writes,
+            self.subpixel_cells,
+            self.ansi_input,
+            self.continuous_background,
+            self.png_palette,
+            self.png_compression,
+            self.gradient_override,
+            self.zero_width_handling,
+            self.fixed_grid,
+            self.gif_transparent_key,
+            self.bloom,
+            self.create_dirs,
+            self.opacity_mask.as_ref().map(|mask| mask.dimensions()),
+            self.posterize,
+            self.progress.is_some(),
+            self.rascii_options
+        )
+    }
+}
+
+/// Builder for [`ImgiiOptions`]. Intended way to create options for Imgii.
+#[derive(Debug, Clone)]
+pub struct ImgiiOptionsBuilder<'a> {
+    /// The name of the installed system font to render
+    font: Option<Vec<u8>>,
+
+    /// The font name.
+    font_name: Option<String>,
+
+    /// The family of an installed font to load, instead of the font bytes.
+    #[cfg(feature = "system-fonts")]
+    font_family: Option<String>,
+
+    /// The font size of the output image.
+    font_size: u32,
+
+    /// Whether to set a background behind the image.
+    background: bool,
+
+    /// Whether to bypass the glyph cache when rendering.
+    disable_glyph_cache: bool,
+
+    /// The channel order of raw output bytes.
+    channel_order: ChannelOrder,
+
+    /// The padding multiple and color for the final canvas.
+    pad_to_multiple: Option<(u32, Rgba<u8>)>,
+
+    /// Whether to synthetically embolden glyphs.
+    bold: bool,
+
+    /// The maximum playback duration of an output GIF, in milliseconds.
+    target_duration_ms: Option<u32>,
+
+    /// How whitespace cells are rendered.
+    space_handling: SpaceHandling,
+
+    /// Whether to return the timings of each conversion stage.
+    timings: bool,
+
+    /// The spacing and darkness of scanlines on the final canvas.
+    scanlines: Option<(u32, u8)>,
+
+    /// How the alpha channel is handled when writing a PNG.
+    png_alpha: PngAlpha,
+
+    /// The jitter applied to each glyph.
+    jitter: Option<Jitter>,
+
+    /// Renders the glyph of every nth cell.
+    sparsity: u32,
+
+    /// Whether to embed the ICC profile of the input image in the output PNG.
+    preserve_icc_profile: bool,
+
+    /// How each frame of an output GIF is disposed of.
+    gif_disposal: GifDisposal,
+
+    /// The number of transparent cells each tab in the ASCII text is expanded to.
+    tab_width: u32,
+
+    /// Renders each character in a square cell, instead of a cell twice as tall as it is wide.
+    square_cells: bool,
+
+    /// The maximum number of bytes a rendered image may take up.
+    memory_budget: Option<u64>,
+
+    /// Runs edge detection on the source image before converting it to ASCII.
+    edge_detect: bool,
+
+    /// How the color of each cell is chosen from the source image.
+    cell_color_mode: CellColorMode,
+
+    /// Multipliers for the font size of specific characters, drawing them larger or smaller.
+    emphasis: HashMap<char, f32>,
+
+    /// Scales the final output image by a factor, after it has been stitched together.
+    scale_output: Option<f32>,
+
+    /// The filter used when scaling the final output image.
+    resize_filter_output: ResizeFilter,
+
+    /// The maximum number of frames of an output GIF.
+    max_frames: Option<usize>,
+
+    /// Renders every glyph in the brightness of its color, instead of its color.
+    grayscale: bool,
+
+    /// Skips writing output files that already hold exactly the rendered bytes.
+    skip_if_unchanged: bool,
+
+    /// Converts images into the colored ASCII text to render.
+    ascii_source: Arc<dyn AsciiSource>,
+
+    /// The color of the background, when the background is enabled. Can be semi-transparent.
+    background_color: Rgba<u8>,
+
+    /// Renders cells holding the lightest character of the charset like whitespace.
+    lightest_transparent: bool,
+
+    /// The bit depth of each channel of an output PNG.
+    bit_depth: BitDepth,
+
+    /// Encodes single output images instead of the format chosen by the file extension.
+    encoder: Option<CustomEncoder>,
+
+    /// Cancels long-running conversions once set to true.
+    cancel_flag: Option<Arc<AtomicBool>>,
+
+    /// Resizes the rendered image to exactly the size of the input image.
+    match_source_resolution: bool,
+
+    /// Returns an error if any character of the ASCII text is dropped while parsing it.
+    strict_parse: bool,
+
+    /// How the width of a cell is rounded when it isn't a whole number of pixels.
+    cell_rounding: CellRounding,
+
+    /// The number of rendered GIF frames that may wait to be encoded, if rendering and encoding
+    /// overlap.
+    gif_pipeline_depth: Option<usize>,
+
+    /// The maximum number of characters (escape sequences included) in a line of ASCII text.
+    max_line_chars: usize,
+
+    /// Fills the background of each cell with the average color of the source image it covers.
+    cell_bg_from_source: bool,
+
+    /// Draws glyphs from a cache of signed distance fields instead of rasterizing them.
+    sdf_glyph_cache: Option<SdfGlyphCache>,
+
+    /// Whether to embed the source file name and key render settings as metadata in output PNGs.
+    embed_metadata: bool,
+
+    /// Whether output files are written to a temporary file, then renamed over the output file.
+    atomic_writes: bool,
+
+    /// Whether cells are stitched at their exact fractional positions when matching the source
+    /// resolution.
+    subpixel_cells: bool,
+
+    /// Whether inputs that are colored ASCII text, rather than images, are rendered directly.
+    ansi_input: bool,
+
+    /// Whether the background is filled once across the whole image, under every glyph.
+    continuous_background: bool,
+
+    /// Whether PNGs are written with indexed color, using a palette of the colors in the image.
+    png_palette: bool,
+
+    /// How hard output PNGs are compressed.
+    png_compression: PngCompression,
+
+    /// The gradient that colors every glyph by the position of its cell, if any.
+    gradient_override: Option<GradientSpec>,
+
+    /// How zero-width characters, such as combining accents, are placed in the grid of cells.
+    zero_width_handling: ZeroWidthHandling,
+
+    /// The size of the grid of cells that is rendered, as (width, height).
+    fixed_grid: Option<(u32, u32)>,
+
+    /// The color reserved as the transparent color of output GIFs, if any. Only its RGB is used.
+    gif_transparent_key: Option<Rgba<u8>>,
+
+    /// The blur radius and strength of the glow added over the final canvas, if any.
+    bloom: Option<(f32, f32)>,
+
+    /// Whether missing parent directories of output files are created.
+    create_dirs: bool,
+
+    /// The file name of the grayscale mask image that fades out cells.
+    opacity_mask: Option<PathBuf>,
+
+    /// The number of levels each color channel of the source image is reduced to.
+    posterize: Option<u8>,
+
+    /// Reports the progress of converting a GIF as each frame is rendered and encoded.
+    progress: Option<ProgressCallback>,
+
+    /// The RASCII options used under the hood to convert an image to ASCII.
+    rascii_options: RasciiOptions<'a>,
+}
+
+impl<'a> Default for ImgiiOptionsBuilder<'a> {
+    fn default() -> Self {
+        Self {
+            font: None,
+            font_name: None,
+            #[cfg(feature = "system-fonts")]
+            font_family: None,
+            font_size: DEFAULT_CHAR_FONT_SIZE,
+            background: false,
+            disable_glyph_cache: false,
+            channel_order: ChannelOrder::default(),
+            pad_to_multiple: None,
+            bold: false,
+            target_duration_ms: None,
+            space_handling: SpaceHandling::default(),
+            timings: false,
+            scanlines: None,
+            png_alpha: PngAlpha::default(),
+            jitter: None,
+            sparsity: 1,
+            preserve_icc_profile: false,
+            gif_disposal: GifDisposal::default(),
+            tab_width: DEFAULT_TAB_WIDTH,
+            square_cells: false,
+            memory_budget: None,
+            edge_detect: false,
+            cell_color_mode: CellColorMode::default(),
+            emphasis: HashMap::new(),
+            scale_output: None,
+            resize_filter_output: ResizeFilter::default(),
+            max_frames: None,
+            grayscale: false,
+            skip_if_unchanged: false,
+            ascii_source: Arc::new(RasciiSource),
+            background_color: DEFAULT_BACKGROUND_COLOR,
+            lightest_transparent: false,
+            bit_depth: BitDepth::Eight,
+            encoder: None,
+            cancel_flag: None,
+            match_source_resolution: false,
+            strict_parse: false,
+            cell_rounding: CellRounding::Floor,
+            gif_pipeline_depth: None,
+            max_line_chars: DEFAULT_MAX_LINE_CHARS,
+            cell_bg_from_source: false,
+            sdf_glyph_cache: None,
+            embed_metadata: false,
+            atomic_writes: false,
+            subpixel_cells: false,
+            ansi_input: false,
+            continuous_background: false,
+            png_palette: false,
+            png_compression: PngCompression::default(),
+            gradient_override: None,
+            zero_width_handling: ZeroWidthHandling::default(),
+            fixed_grid: None,
+            gif_transparent_key: None,
+            bloom: None,
+            create_dirs: false,
+            opacity_mask: None,
+            posterize: None,
+            progress: None,
+            rascii_options: RasciiOptions::default()
+                .colored(true)
+                .escape_each_colored_char(true),
+        }
+    }
+}
+
+impl<'a> ImgiiOptionsBuilder<'a> {
+    /// Creates a new builder with defaults. Behaves the same as calling
+    /// [`ImgiiOptionsBuilder::default()`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the font of the output [`ImgiiOptions`].
+    ///
+    /// NOTE: glyphs are looked up straight from the font's character map, without any OpenType
+    /// shaping. Ligatures and contextual alternates (such as those in coding fonts like Fira Code)
+    /// are never applied, so every cell renders the same no matter which font features are
+    /// enabled by default.
+    ///
+    /// * `font`: The loaded font bytes.
+    pub fn font(mut self, font: Vec<u8>) -> Self {
+        self.font = Some(font);
+        self
+    }
+
+    /// Sets the font name of the output [`ImgiiOptions`].
+    ///
+    /// * `font_name`: The name of the font.
+    pub fn font_name(mut self, font_name: String) -> Self {
+        self.font_name = Some(font_name);
+        self
+    }
+
+    /// Sets the font of the output [`ImgiiOptions`] to an installed monospace font, found by its
+    /// family name (such as "DejaVu Sans Mono"). The font is loaded when building, taking
+    /// precedence over [`Self::font`] and [`Self::font_name`], with the family used as the font
+    /// name.
+    ///
+    /// Building fails with [`FontError::FamilyNotFound`] if no installed font matches.
+    ///
+    /// * `font_family`: The family name of the installed font.
+    #[cfg(feature = "system-fonts")]
+    pub fn font_family(mut self, font_family: &str) -> Self {
+        self.font_family = Some(String::from(font_family));
+        self
+    }
+
+    /// Sets the font size of the output [`ImgiiOptions`].
+    ///
+    /// * `font_size`: The font size.
+    pub fn font_size(mut self, font_size: u32) -> Self {
+        self.font_size = font_size;
+        self
+    }
+
+    /// Sets the background flag for the output [`ImgiiOptions`]
+    ///
+    /// * `background`: The background flag.
+    pub fn background(mut self, background: bool) -> Self {
+        self.background = background;
+        self
+    }
+
+    /// Sets the disable glyph cache flag for the output [`ImgiiOptions`]. Useful for profiling
+    /// worst-case rendering performance.
+    ///
+    /// * `disable_glyph_cache`: The disable glyph cache flag.
+    pub fn disable_glyph_cache(mut self, disable_glyph_cache: bool) -> Self {
+        self.disable_glyph_cache = disable_glyph_cache;
+        self
+    }
+
+    /// Sets the channel order for raw output bytes of the output [`ImgiiOptions`]. Has no effect
+    /// on formats with a standardized channel order, such as PNG and GIF.
+    ///
+    /// * `channel_order`: The channel order.
+    pub fn channel_order(mut self, channel_order: ChannelOrder) -> Self {
+        self.channel_order = channel_order;
+        self
+    }
+
+    /// Pads the final canvas of the output [`ImgiiOptions`] so its width and height are rounded up
+    /// to a multiple of `multiple` pixels. Useful for video encoders that require even dimensions.
+    ///
+    /// * `multiple`: The multiple to round the canvas dimensions up to. Must be non-zero.
+    /// * `color`: The color of the added padding.
+    pub fn pad_to_multiple(mut self, multiple: u32, color: Rgba<u8>) -> Self {
+        self.pad_to_multiple = Some((multiple, color));
+        self
+    }
+
+    /// Sets the bold flag for the output [`ImgiiOptions`]. Useful for fonts without a bold
+    /// variant.
+    ///
+    /// * `bold`: The bold flag.
+    pub fn bold(mut self, bold: bool) -> Self {
+        self.bold = bold;
+        self
+    }
+
+    /// Sets the target duration for output GIFs of the output [`ImgiiOptions`]. If the total
+    /// delay of all source frames is longer than this duration, frames are uniformly dropped until
+    /// the total delay of the remaining frames fits. Kept frames retain their original delays, so
+    /// the animation plays at the same speed, just with fewer frames. At least one frame is
+    /// always kept.
+    ///
+    /// * `target_duration_ms`: The maximum playback duration, in milliseconds.
+    pub fn target_duration_ms(mut self, target_duration_ms: u32) -> Self {
+        self.target_duration_ms = Some(target_duration_ms);
+        self
+    }
+
+    /// Sets how whitespace cells are rendered for the output [`ImgiiOptions`].
+    ///
+    /// * `space_handling`: How whitespace cells are rendered.
+    pub fn space_handling(mut self, space_handling: SpaceHandling) -> Self {
+        self.space_handling = space_handling;
+        self
+    }
+
+    /// Sets the timings flag for the output [`ImgiiOptions`]. If true, conversion functions
+    /// return how long each stage of the conversion took.
+    ///
+    /// * `timings`: The timings flag.
+    pub fn timings(mut self, timings: bool) -> Self {
+        self.timings = timings;
+        self
+    }
+
+    /// Overlays horizontal scanlines on the final canvas of the output [`ImgiiOptions`], for a
+    /// CRT look. Every `spacing` rows, a row has the brightness of its pixels multiplied by
+    /// `(255 - darkness) / 255`. Applied to every frame of a GIF.
+    ///
+    /// * `spacing`: The number of rows between each scanline. Must be non-zero.
+    /// * `darkness`: How much to darken each scanline, where 255 is fully black.
+    pub fn scanlines(mut self, spacing: u32, darkness: u8) -> Self {
+        self.scanlines = Some((spacing, darkness));
+        self
+    }
+
+    /// Sets how the alpha channel is handled when writing a PNG for the output [`ImgiiOptions`].
+    /// Stripping the alpha channel writes an RGB PNG instead of RGBA, which shrinks the output
+    /// file. Has no effect on other output formats.
+    ///
+    /// * `png_alpha`: How the alpha channel is handled.
+    pub fn png_alpha(mut self, png_alpha: PngAlpha) -> Self {
+        self.png_alpha = png_alpha;
+        self
+    }
+
+    /// Randomly rotates and offsets each glyph of the output [`ImgiiOptions`] for a hand-drawn
+    /// look. The randomness is derived from the seed and the position of each cell, so the same
+    /// seed always produces the same output. Glyphs are clipped to the bounds of their cell.
+    ///
+    /// NOTE: since every cell is different, this bypasses the glyph cache, so every character is
+    /// rendered fresh. Expect rendering to be much slower.
+    ///
+    /// * `max_degrees`: The maximum rotation of a glyph, in degrees, in either direction.
+    /// * `max_offset`: The maximum offset of a glyph, in pixels, in either direction on each axis.
+    /// * `seed`: The seed for the randomness.
+    pub fn jitter(mut self, max_degrees: f32, max_offset: u32, seed: u64) -> Self {
+        self.jitter = Some(Jitter {
+            max_degrees,
+            max_offset,
+            seed,
+        });
+        self
+    }
+
+    /// Sets the sparsity of the output [`ImgiiOptions`], for a dotted look. Only cells where
+    /// `(row * width + column) % sparsity == 0` have their glyph rendered, and the rest are left
+    /// transparent, even if they have a cell background. The size of the grid is unchanged.
+    ///
+    /// * `sparsity`: Renders the glyph of every `sparsity`th cell. Must be non-zero.
+    pub fn sparsity(mut self, sparsity: u32) -> Self {
+        self.sparsity = sparsity;
+        self
+    }
+
+    /// Sets the preserve ICC profile flag for the output [`ImgiiOptions`]. If true, the ICC
+    /// profile of the input image (if it has one) is embedded in the output PNG, which is always
+    /// encoded as a PNG in this case. Only applies to PNG output.
+    ///
+    /// RASCII samples the input colors directly without any color management, so without this,
+    /// wide-gamut inputs (such as Display P3 photos) render with shifted colors, since viewers
+    /// will assume the output is sRGB.
+    ///
+    /// * `preserve_icc_profile`: The preserve ICC profile flag.
+    pub fn preserve_icc_profile(mut self, preserve_icc_profile: bool) -> Self {
+        self.preserve_icc_profile = preserve_icc_profile;
+        self
+    }
+
+    /// Sets how each frame of an output GIF is disposed of for the output [`ImgiiOptions`]. The
+    /// default of [`GifDisposal::Background`] avoids ghosting when frames are transparent, such as
+    /// when the background is disabled. Has no effect on other output formats.
+    ///
+    /// * `gif_disposal`: How each frame is disposed of.
+    pub fn gif_disposal(mut self, gif_disposal: GifDisposal) -> Self {
+        self.gif_disposal = gif_disposal;
+        self
+    }
+
+    /// Gets the font bytes and font name to build with, loading the font family if one was set.
+    fn resolve_font(&self) -> Result<(Vec<u8>, String), ImgiiError> {
+        #[cfg(feature = "system-fonts")]
+        if let Some(font_family) = &self.font_family {
+            return match load_monospace_font(font_family) {
+                Some((font, _)) => Ok((font, font_family.clone())),
+                None => Err(FontError::FamilyNotFound {
+                    family: font_family.clone(),
+                }
+                .into()),
+            };
+        }
+
+        match (self.font.clone(), self.font_name.clone()) {
+            (Some(font), Some(font_name)) => Ok((font, font_name)),
+            _ => Err(ImgiiError::InvalidArgument),
+        }
+    }
+
+    /// Sets the number of transparent cells each tab in the ASCII text is expanded to for the
+    /// output [`ImgiiOptions`]. A width of 0 removes tabs entirely.
+    ///
+    /// * `tab_width`: The number of cells for each tab.
+    pub fn tab_width(mut self, tab_width: u32) -> Self {
+        self.tab_width = tab_width;
+        self
+    }
+
+    /// Sets whether each character is rendered in a square cell for the output [`ImgiiOptions`],
+    /// for displays that want a pixel grid. Cells are normally twice as tall as they are wide.
+    ///
+    /// RASCII assumes cells twice as tall as they are wide, so it halves the number of rows to keep
+    /// the proportions of the image. With square cells, imgii instead picks the rows itself from the
+    /// aspect ratio of the image, so the output keeps the proportions of the image. If both the
+    /// width and height are set, they are used as-is.
+    ///
+    /// * `square_cells`: Whether to render each character in a square cell.
+    pub fn square_cells(mut self, square_cells: bool) -> Self {
+        self.square_cells = square_cells;
+        self
+    }
+
+    /// Sets the maximum number of bytes a rendered image may take up for the output
+    /// [`ImgiiOptions`]. Rendering an image that would need more memory than this returns an
+    /// error instead of allocating it, so a large width or height can't crash a constrained
+    /// machine.
+    ///
+    /// If not set, the budget is the available memory when the `sysinfo` feature is enabled,
+    /// otherwise there is no budget.
+    ///
+    /// * `memory_budget`: The maximum number of bytes.
+    pub fn memory_budget(mut self, memory_budget: u64) -> Self {
+        self.memory_budget = Some(memory_budget);
+        self
+    }
+
+    /// Sets whether edge detection is run on the source image before converting it to ASCII for
+    /// the output [`ImgiiOptions`], for a line-art look. The ASCII follows the outlines of the
+    /// image, with strong edges mapping to dense characters of the charset.
+    ///
+    /// Edges are bright on a black background, so this works best with the background enabled.
+    /// Without a background, consider inverting the image so the edges are dark instead. The
+    /// edges are grayscale, so the colors of the source image are lost.
+    ///
+    /// * `edge_detect`: Whether to run edge detection.
+    pub fn edge_detect(mut self, edge_detect: bool) -> Self {
+        self.edge_detect = edge_detect;
+        self
+    }
+
+    /// Sets how the color of each cell is chosen from the source image for the output
+    /// [`ImgiiOptions`]. Averaging is slower than sampling, since every pixel of the source image
+    /// is read.
+    ///
+    /// * `cell_color_mode`: How the color of each cell is chosen.
+    pub fn cell_color_mode(mut self, cell_color_mode: CellColorMode) -> Self {
+        self.cell_color_mode = cell_color_mode;
+        self
+    }
+
+    /// Sets font size multipliers for specific characters of the output [`ImgiiOptions`], so
+    /// they stand out (such as drawing every '@' at twice the size). Emphasized characters are
+    /// centered in their cell, and clipped to it if they are too large to fit. Emphasized
+    /// characters are never cached, so emphasizing common characters slows down rendering.
+    ///
+    /// * `emphasis`: The font size multiplier of each character. Every multiplier must be finite
+    ///   and positive.
+    pub fn emphasis(mut self, emphasis: HashMap<char, f32>) -> Self {
+        self.emphasis = emphasis;
+        self
+    }
+
+    /// Sets the factor to scale the final output image by for the output [`ImgiiOptions`]. The
+    /// image is scaled after it has been stitched together and before it is padded, using
+    /// [`ImgiiOptionsBuilder::resize_filter_output`].
+    ///
+    /// * `scale`: The factor to scale by. Must be finite and positive.
+    pub fn scale_output(mut self, scale: f32) -> Self {
+        self.scale_output = Some(scale);
+        self
+    }
+
+    /// Sets the filter used when scaling the final output image for the output [`ImgiiOptions`].
+    /// This is separate from how the input image is resized into the ASCII grid. Smooth filters
+    /// blur the edges of glyphs, so nearest is best for keeping them sharp when upscaling.
+    ///
+    /// * `resize_filter_output`: The filter to scale with.
+    pub fn resize_filter_output(mut self, resize_filter_output: ResizeFilter) -> Self {
+        self.resize_filter_output = resize_filter_output;
+        self
+    }
+
+    /// Sets the maximum number of frames for output GIFs of the output [`ImgiiOptions`]. If the
+    /// source has more frames, evenly spaced frames are kept regardless of their delays, and the
+    /// delays of dropped frames are added to the kept frame before them, so the animation keeps
+    /// its total duration. Applied before [`ImgiiOptionsBuilder::target_duration_ms`].
+    ///
+    /// * `max_frames`: The maximum number of frames. Must be greater than 0.
+    pub fn max_frames(mut self, max_frames: usize) -> Self {
+        self.max_frames = Some(max_frames);
+        self
+    }
 
-// We need to re-export these, as they might be necessary for users of this library. Imgii's CLI
-// uses these.
-pub use rascii_art_img::RenderOptions as RasciiOptions;
-pub use rascii_art_img::{
-    charsets::{Charset, from_enum, to_charset_enum},
-    convert_string_to_str_vec,
-};
+    /// Sets whether to render every glyph in grayscale for the output [`ImgiiOptions`]. When the
+    /// background is also enabled and whitespace isn't transparent, PNGs are rendered and saved
+    /// as grayscale images, which take a quarter of the memory.
+    ///
+    /// * `grayscale`: Whether to render in grayscale.
+    pub fn grayscale(mut self, grayscale: bool) -> Self {
+        self.grayscale = grayscale;
+        self
+    }
 
-use crate::error::ImgiiError;
+    /// Sets whether to skip writing output files that already hold exactly the rendered bytes for
+    /// the output [`ImgiiOptions`]. The output is encoded in memory first, then only written if it
+    /// differs from the existing file, so unchanged files keep their modification time. Useful for
+    /// incremental builds. Applies to PNG and GIF output.
+    ///
+    /// * `skip_if_unchanged`: Whether to skip writing unchanged output files.
+    pub fn skip_if_unchanged(mut self, skip_if_unchanged: bool) -> Self {
+        self.skip_if_unchanged = skip_if_unchanged;
+        self
+    }
 
-const DEFAULT_CHAR_FONT_SIZE: u32 = 16;
+    /// Sets the source that converts images into colored ASCII text for the output
+    /// [`ImgiiOptions`], so a converter other than RASCII can be used while still rendering and
+    /// encoding with imgii. Preprocessing (such as edge detection) is still applied to the image
+    /// first, and the cell colors can still be averaged afterwards.
+    ///
+    /// * `ascii_source`: The source to convert images with.
+    pub fn ascii_source(mut self, ascii_source: Arc<dyn AsciiSource>) -> Self {
+        self.ascii_source = ascii_source;
+        self
+    }
 
-// NOTE: we don't want to ever make members of ImgiiOptions public so users can't cause imgii to
-// crash by setting invalid options.
+    /// Sets the color of the background for the output [`ImgiiOptions`]. Only used when the
+    /// background is enabled. The alpha channel is kept, so a semi-transparent background still
+    /// shows what's beneath the image when it's composited, with glyphs blended over it.
+    ///
+    /// * `background_color`: The color of the background.
+    pub fn background_color(mut self, background_color: Rgba<u8>) -> Self {
+        self.background_color = background_color;
+        self
+    }
 
-/// Options for creating the output ASCII PNG.
-#[derive(Debug, Clone)]
-pub struct ImgiiOptions<'a> {
-    /// The loaded bytes containing the font.
-    font: Vec<u8>,
+    /// Sets whether cells holding the lightest character of the charset are rendered like
+    /// whitespace for the output [`ImgiiOptions`], so only the denser characters are drawn. Useful
+    /// for overlaying ASCII on other content. The lightest character is the first entry of the
+    /// charset, and it's only matched if that entry is a single character.
+    ///
+    /// NOTE: inverting the charset doesn't change which character is the lightest, but it flips
+    /// which end of the source image's brightness ends up drawn with it, so the see-through parts
+    /// of the image are flipped too.
+    ///
+    /// * `lightest_transparent`: Whether to render the lightest character like whitespace.
+    pub fn lightest_transparent(mut self, lightest_transparent: bool) -> Self {
+        self.lightest_transparent = lightest_transparent;
+        self
+    }
 
-    /// The font name.
-    font_name: String,
+    /// Sets the bit depth of each channel of an output PNG for the output [`ImgiiOptions`]. With
+    /// 16 bits, the image is stitched and post-processed (such as scaled) at 16 bits, which avoids
+    /// banding in smooth gradients, at twice the memory. Glyphs are still drawn at 8 bits.
+    ///
+    /// NOTE: only PNG output can be 16 bits. Other formats, such as GIFs and raw images, are always
+    /// 8 bits.
+    ///
+    /// * `bit_depth`: The bit depth of each channel.
+    pub fn bit_depth(mut self, bit_depth: BitDepth) -> Self {
+        self.bit_depth = bit_depth;
+        self
+    }
 
-    /// The font size of the output image.
-    font_size: u32,
+    /// Sets a custom encoder for single output images for the output [`ImgiiOptions`], so formats
+    /// imgii doesn't support natively (such as QOI) can be written without forking. The image is
+    /// rendered as 8-bit RGBA [`crate::conversion::image_data::ImageData`], then passed to the
+    /// encoder along with a writer to the output file. The file extension of the output is ignored.
+    ///
+    /// NOTE: an encoder only writes a single image, so it's only used by
+    /// [`crate::convert_to_ascii_png`]. GIFs and other animations are always encoded by imgii.
+    /// The bit depth, alpha handling and ICC profile options are also ignored, since the encoder
+    /// decides how the image is written.
+    ///
+    /// * `encoder`: Encodes the rendered image to the writer.
+    pub fn encode_with(mut self, encoder: Arc<EncodeFn>) -> Self {
+        self.encoder = Some(CustomEncoder(encoder));
+        self
+    }
 
-    /// Sets a black background behind the image.
+    /// Sets a flag that cancels long-running conversions for the output [`ImgiiOptions`]. Once
+    /// the flag is set to true, conversions return [`ImgiiError::Cancelled`] as soon as they
+    /// can. The flag is checked between each frame of a GIF and before each image, so a frame or
+    /// an image that has already started is finished first.
     ///
-    /// No background by default.
-    background: bool,
+    /// * `cancel_flag`: The flag to check, shared with whatever may cancel the conversion.
+    pub fn cancel_flag(mut self, cancel_flag: Arc<AtomicBool>) -> Self {
+        self.cancel_flag = Some(cancel_flag);
+        self
+    }
 
-    /// The RASCII options for converting an image to ASCII.
-    rascii_options: RasciiOptions<'a>,
-}
+    /// Sets whether the rendered image is resized to exactly the size of the input image for the
+    /// output [`ImgiiOptions`], so it can be composited pixel-perfectly over the input image. Each
+    /// cell ends up `source size / grid size` pixels, covering the region of the input image it
+    /// was converted from. Glyphs are drawn at the font size, then scaled with
+    /// [`ImgiiOptionsBuilder::resize_filter_output`] to fill their cells. Takes the place of
+    /// [`ImgiiOptionsBuilder::scale_output`].
+    ///
+    /// NOTE: if the aspect ratio of the grid differs from the input image, such as when both the
+    /// width and height are set, glyphs are stretched non-uniformly to fill their cells.
+    ///
+    /// * `match_source_resolution`: Whether to resize to the size of the input image.
+    pub fn match_source_resolution(mut self, match_source_resolution: bool) -> Self {
+        self.match_source_resolution = match_source_resolution;
+        self
+    }
 
-impl<'a> ImgiiOptions<'a> {
-    /// Creates a new image options object.
-    #[must_use]
-    fn new(
-        font: Vec<u8>,
-        font_name: String,
-        font_size: u32,
-        background: bool,
-        rascii_options: RasciiOptions<'a>,
-    ) -> Self {
-        Self {
-            font,
-            font_name,
-            font_size,
-            background,
-            rascii_options,
-        }
+    /// Sets whether dropping a character while parsing ASCII text is an error for the output
+    /// [`ImgiiOptions`]. Characters are dropped when they aren't colored by an escape sequence,
+    /// which usually means the ASCII text was encoded differently than imgii expects. Whitespace
+    /// and escape sequences that don't color a character (such as resets) are never counted as
+    /// dropped. Useful for pipelines that need the rendered image to match the ASCII text exactly.
+    ///
+    /// * `strict_parse`: Whether dropping a character is an error.
+    pub fn strict_parse(mut self, strict_parse: bool) -> Self {
+        self.strict_parse = strict_parse;
+        self
     }
 
-    /// Gets the font data.
-    #[must_use]
-    pub fn font(&self) -> &Vec<u8> {
-        &self.font
+    /// Sets how the width of a cell is rounded when it isn't a whole number of pixels for the
+    /// output [`ImgiiOptions`], such as a cell half as wide as an odd font size. Every cell and the
+    /// stitched canvas use the same rounded width, so wide grids never drift out of alignment.
+    ///
+    /// * `cell_rounding`: How the width of a cell is rounded.
+    pub fn cell_rounding(mut self, cell_rounding: CellRounding) -> Self {
+        self.cell_rounding = cell_rounding;
+        self
     }
 
-    /// Gets the font name.
-    #[must_use]
-    pub fn font_name(&self) -> &str {
-        &self.font_name
+    /// Sets the output [`ImgiiOptions`] to overlap rendering and encoding GIFs, instead of
+    /// rendering every frame before encoding any of them. Frames are rendered in parallel in
+    /// batches, and a single encoder thread encodes them in order as soon as they're ready, which
+    /// hides rendering time behind encoding.
+    ///
+    /// Since the stages overlap, the render timing is how long rendering took, and the encode
+    /// timing is only how long encoding took after rendering finished. If the conversion is
+    /// cancelled, the frames encoded so far may already have been written to the output GIF. With
+    /// the `wasm` feature, frames are always rendered before encoding.
+    ///
+    /// * `depth`: The number of rendered frames that may wait to be encoded. Must be non-zero.
+    pub fn gif_pipeline_depth(mut self, depth: usize) -> Self {
+        self.gif_pipeline_depth = Some(depth);
+        self
     }
 
-    /// Gets the font size to use to generate the image.
-    #[must_use]
-    pub fn font_size(&self) -> u32 {
-        self.font_size
+    /// Sets the maximum number of characters (escape sequences included) in a line of ASCII text
+    /// for the output [`ImgiiOptions`]. Lines longer than this return an error before they're
+    /// parsed, so untrusted ASCII text can't exhaust memory with a huge line.
+    ///
+    /// Defaults to 4,194,304 characters, which fits over 200,000 cells of 24-bit colored text.
+    ///
+    /// * `max_line_chars`: The maximum number of characters in a line. Must be non-zero.
+    pub fn max_line_chars(mut self, max_line_chars: usize) -> Self {
+        self.max_line_chars = max_line_chars;
+        self
     }
 
-    /// Gets the background flag. If true, sets a background behind the output image.
-    #[must_use]
-    pub fn background(&self) -> bool {
-        self.background
+    /// Sets whether the background of each cell is filled with the average color of the region of
+    /// the source image that it covers for the output [`ImgiiOptions`], with the glyph drawn on
+    /// top in its own color. Produces a dense, colorful "colored block and character" look. The
+    /// cell background replaces the background and space handling, so whitespace cells are filled
+    /// too.
+    ///
+    /// NOTE: this needs the source image alongside the ASCII text, so the background is added
+    /// while converting the image to ASCII, as a `\x1b[48;2;<R>;<G>;<B>m` escape sequence before
+    /// each character. Rendering ASCII text that already has these escape sequences (such as an
+    /// ANSI-art file) fills the cell backgrounds the same way. Works best with the sampled cell
+    /// color mode, since averaged glyph colors are the same as the background.
+    ///
+    /// * `cell_bg_from_source`: Whether to fill each cell background from the source image.
+    pub fn cell_bg_from_source(mut self, cell_bg_from_source: bool) -> Self {
+        self.cell_bg_from_source = cell_bg_from_source;
+        self
     }
 
-    /// Gets the RASCII options.
-    #[must_use]
-    pub fn rascii_options(&self) -> &RasciiOptions<'a> {
-        &self.rascii_options
+    /// Sets a cache of signed distance field glyphs to draw from for the output [`ImgiiOptions`].
+    /// Each glyph of the charset is rasterized once, then drawn at any font size by sampling its
+    /// distance field, so rendering the same charset at several font sizes doesn't rasterize it
+    /// again for every size. Share the cache between the options of each size.
+    ///
+    /// NOTE: glyphs drawn from the cache are slightly softer than glyphs rasterized at the font
+    /// size, and lose thin strokes at very small font sizes. See [`crate::sdf_glyph_cache`].
+    ///
+    /// * `sdf_glyph_cache`: The cache to draw glyphs from.
+    pub fn sdf_glyph_cache(mut self, sdf_glyph_cache: SdfGlyphCache) -> Self {
+        self.sdf_glyph_cache = Some(sdf_glyph_cache);
+        self
     }
-}
 
-impl<'a> Display for ImgiiOptions<'a> {
-    /// Writes an `[ImgiiOptions]` instance. This should be preferred over debug printing, as
-    /// `[ImgiiOptions]` can hold a lot of binary data.
+    /// Sets whether to embed the source file name and key render settings as text metadata in
+    /// output PNGs for the output [`ImgiiOptions`], for provenance. The PNG gets a `tEXt` chunk for
+    /// each of the software ("Software"), the source file name ("Source", when there is a source
+    /// file), the charset ("Charset"), the font name ("Font") and the font size ("Font Size").
+    /// Text that isn't Latin-1, such as a charset of block characters, is written to a UTF-8
+    /// `iTXt` chunk instead.
     ///
-    /// * `f`: The formatter.
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        // write everything that won't spam a bunch of binary data
-        write!(
-            f,
-            "{{font.len()={}; font_name={}; font_size={}, background={}; rascii_options={:?}}}",
-            self.font.len(),
-            self.font_name,
-            self.font_size,
-            self.background,
-            self.rascii_options
-        )
+    /// NOTE: has no effect on formats without text metadata, or when a custom encoder is set.
+    ///
+    /// * `embed_metadata`: Whether to embed metadata in output PNGs.
+    pub fn embed_metadata(mut self, embed_metadata: bool) -> Self {
+        self.embed_metadata = embed_metadata;
+        self
     }
-}
 
-/// Builder for [`ImgiiOptions`]. Intended way to create options for Imgii.
-#[derive(Debug, Clone)]
-pub struct ImgiiOptionsBuilder<'a> {
-    /// The name of the installed system font to render
-    font: Option<Vec<u8>>,
+    /// Sets whether output files are written atomically for the output [`ImgiiOptions`]. Each file
+    /// is written to a hidden temporary file in the same directory, then renamed over the output
+    /// file once it's complete, so a crash or error mid-write never leaves a truncated output file
+    /// that looks valid. Useful for batches read by other tools.
+    ///
+    /// NOTE: a crash mid-write can leave the temporary file behind instead. On Windows, an output
+    /// file that can't be renamed over is removed first, so there is a moment where it doesn't
+    /// exist. Memory-mapped raw output is always written in place.
+    ///
+    /// * `atomic_writes`: Whether to write output files atomically.
+    pub fn atomic_writes(mut self, atomic_writes: bool) -> Self {
+        self.atomic_writes = atomic_writes;
+        self
+    }
 
-    /// The font name.
-    font_name: Option<String>,
+    /// Sets whether cells are stitched at their exact fractional positions when matching the source
+    /// resolution for the output [`ImgiiOptions`]. When the size of the input image isn't a
+    /// multiple of the grid, each cell covers a fractional number of pixels. Normally the image is
+    /// stitched at a whole number of pixels per cell and resized as a whole, which blurs cells into
+    /// their neighbors with smooth resize filters. With subpixel cells, each pixel of the output is
+    /// sampled from the cell it falls in, starting every cell at its exact fractional position, so
+    /// cell boundaries never drift and there are no seams between cells.
+    ///
+    /// NOTE: only has an effect with [`ImgiiOptionsBuilder::match_source_resolution`]. Cells are
+    /// sampled with the nearest pixel if the output resize filter is
+    /// [`ResizeFilter::Nearest`], and interpolated otherwise. Scanlines are drawn after resizing,
+    /// so they're spaced in pixels of the output image.
+    ///
+    /// * `subpixel_cells`: Whether to stitch cells at their exact fractional positions.
+    pub fn subpixel_cells(mut self, subpixel_cells: bool) -> Self {
+        self.subpixel_cells = subpixel_cells;
+        self
+    }
 
-    /// The font size of the output image.
-    font_size: u32,
+    /// Sets whether inputs that are colored ASCII text, rather than images, are rendered directly
+    /// for the output [`ImgiiOptions`], skipping the conversion to ASCII entirely. This makes
+    /// [`crate::convert_to_ascii_png`] a general ANSI-art renderer, like
+    /// [`crate::convert_ansi_to_ascii_png`]. Inputs ending in ".ans" or ".txt" are always read as
+    /// text, and any other input is read as text if it isn't a recognized image format but holds
+    /// escape sequences.
+    ///
+    /// NOTE: only applies to single PNG output. The text must follow the format described by
+    /// [`crate::ascii_source::AsciiSource`].
+    ///
+    /// * `ansi_input`: Whether to render colored ASCII text inputs directly.
+    pub fn ansi_input(mut self, ansi_input: bool) -> Self {
+        self.ansi_input = ansi_input;
+        self
+    }
 
-    /// Whether to set a background behind the image.
-    background: bool,
+    /// Sets whether the background is filled once across the whole image for the output
+    /// [`ImgiiOptions`], rather than into each cell. Cells are rendered without the background,
+    /// then composited over one continuous fill as they're stitched together. This avoids faint
+    /// seams between cells when the background color isn't fully opaque. Only used when the
+    /// background is enabled. A fully opaque background can't leave seams, so it's still filled
+    /// into each cell, which is faster and looks the same.
+    ///
+    /// NOTE: the fill covers the whole image, so whitespace is filled with the background even
+    /// with [`SpaceHandling::Transparent`]. Cells with their own background color are unaffected.
+    /// Exported cells and indexed grids hold the cells without the background.
+    ///
+    /// * `continuous_background`: Whether to fill the background across the whole image.
+    pub fn continuous_background(mut self, continuous_background: bool) -> Self {
+        self.continuous_background = continuous_background;
+        self
+    }
 
-    /// The RASCII options used under the hood to convert an image to ASCII.
-    rascii_options: RasciiOptions<'a>,
-}
+    /// Sets whether PNGs are written with indexed color for the output [`ImgiiOptions`], using a
+    /// palette of the colors in the image. The palette is as small as possible, and each pixel is
+    /// stored in as few bits as the palette allows, which shrinks small images (such as icons) a
+    /// lot.
+    ///
+    /// NOTE: colors are picked like they are for GIF frames. Images with more than 256 colors are
+    /// quantized down to 256, and pixels that aren't fully transparent are made fully opaque. Only
+    /// 8-bit PNGs can be indexed, so this can't be combined with [`BitDepth::Sixteen`]. Images
+    /// wider or taller than 65535 pixels are written without a palette.
+    ///
+    /// * `png_palette`: Whether to write PNGs with indexed color.
+    pub fn png_palette(mut self, png_palette: bool) -> Self {
+        self.png_palette = png_palette;
+        self
+    }
 
-impl<'a> Default for ImgiiOptionsBuilder<'a> {
-    fn default() -> Self {
-        Self {
-            font: None,
-            font_name: None,
-            font_size: DEFAULT_CHAR_FONT_SIZE,
-            background: false,
-            rascii_options: RasciiOptions::default()
-                .colored(true)
-                .escape_each_colored_char(true),
+    /// Sets how hard output PNGs are compressed for the output [`ImgiiOptions`]. Compression is
+    /// lossless, so this only trades encoding speed for file size. Has no effect on other output
+    /// formats.
+    ///
+    /// * `png_compression`: How hard to compress PNGs.
+    pub fn png_compression(mut self, png_compression: PngCompression) -> Self {
+        self.png_compression = png_compression;
+        self
+    }
+
+    /// Sets the output [`ImgiiOptions`] up to write the smallest PNGs possible, such as for icons
+    /// and favicons. This is a shortcut for setting these options, which can still be changed
+    /// afterwards to fine-tune the output:
+    /// * [`Self::png_palette`] is enabled.
+    /// * [`Self::png_compression`] is set to [`PngCompression::Best`].
+    /// * [`Self::embed_metadata`] and [`Self::preserve_icc_profile`] are disabled, so no ancillary
+    ///   chunks are written.
+    /// * [`Self::bit_depth`] is set to [`BitDepth::Eight`].
+    ///
+    /// Passing false sets the palette and compression back to their defaults, leaving the other
+    /// options as they are.
+    ///
+    /// * `optimize_size`: Whether to write the smallest PNGs possible.
+    pub fn optimize_size(self, optimize_size: bool) -> Self {
+        if !optimize_size {
+            return self
+                .png_palette(false)
+                .png_compression(PngCompression::default());
         }
+
+        self.png_palette(true)
+            .png_compression(PngCompression::Best)
+            .embed_metadata(false)
+            .preserve_icc_profile(false)
+            .bit_depth(BitDepth::Eight)
     }
-}
 
-impl<'a> ImgiiOptionsBuilder<'a> {
-    /// Creates a new builder with defaults. Behaves the same as calling
-    /// [`ImgiiOptionsBuilder::default()`].
-    pub fn new() -> Self {
-        Self::default()
+    /// Sets a gradient that colors every glyph by the position of its cell for the output
+    /// [`ImgiiOptions`], overriding the colors of the source image, for a stylized "rainbow" look.
+    /// The characters still come from the source image (or `char_override`), so combining both
+    /// gives purely stylistic output. Cell backgrounds keep their colors.
+    ///
+    /// * `gradient`: The gradient to color glyphs with.
+    pub fn gradient_override(mut self, gradient: GradientSpec) -> Self {
+        self.gradient_override = Some(gradient);
+        self
     }
 
-    /// Sets the font of the output [`ImgiiOptions`].
+    /// Sets how zero-width characters, such as combining accents and zero-width joiners, are
+    /// placed in the grid of cells for the output [`ImgiiOptions`]. By default, they're combined
+    /// into the cell before them.
     ///
-    /// * `font`: The loaded font bytes.
-    pub fn font(mut self, font: Vec<u8>) -> Self {
-        self.font = Some(font);
+    /// Invisible zero-width characters (like a zero-width space) are drawn as blank cells when
+    /// they take up a cell of their own.
+    ///
+    /// * `zero_width_handling`: How zero-width characters are placed.
+    pub fn zero_width_handling(mut self, zero_width_handling: ZeroWidthHandling) -> Self {
+        self.zero_width_handling = zero_width_handling;
         self
     }
 
-    /// Sets the font name of the output [`ImgiiOptions`].
+    /// Sets a fixed size for the grid of cells rendered for the output [`ImgiiOptions`], no matter
+    /// the size of the ASCII text. Cells past the right or bottom edge of the grid are cropped, and
+    /// short rows and columns are padded with blank cells (transparent, or the background if
+    /// it's enabled). Useful for layouts that need exact dimensions, like an 80x25 "terminal".
     ///
-    /// * `font_name`: The name of the font.
-    pub fn font_name(mut self, font_name: String) -> Self {
-        self.font_name = Some(font_name);
+    /// The ASCII text is cropped and padded after it's parsed, so it's still converted at the size
+    /// chosen by the RASCII options.
+    ///
+    /// * `width`: The number of columns in the grid. Must be non-zero.
+    /// * `height`: The number of rows in the grid. Must be non-zero.
+    pub fn fixed_grid(mut self, width: u32, height: u32) -> Self {
+        self.fixed_grid = Some((width, height));
         self
     }
 
-    /// Sets the font size of the output [`ImgiiOptions`].
+    /// Sets the color reserved as the transparent color key of output GIFs for the output
+    /// [`ImgiiOptions`], such as magenta, for older tools that find transparency by color rather
+    /// than by the transparent palette index. Transparent pixels are encoded as this color, and
+    /// any glyph pixels of this color are shifted slightly so they stay visible, logging a warning.
     ///
-    /// * `font_size`: The font size.
-    pub fn font_size(mut self, font_size: u32) -> Self {
-        self.font_size = font_size;
+    /// By default, transparency only comes from the alpha channel of each frame. Has no effect on
+    /// other output formats.
+    ///
+    /// * `key`: The transparent color key. Its alpha is ignored.
+    pub fn gif_transparent_key(mut self, key: Rgba<u8>) -> Self {
+        self.gif_transparent_key = Some(key);
         self
     }
 
-    /// Sets the background flag for the output [`ImgiiOptions`]
+    /// Adds a soft glow (bloom) over the final canvas of the output [`ImgiiOptions`], for the look
+    /// of a glowing screen. The canvas is blurred and added back on top of itself, so bright
+    /// glyphs bleed light into their surroundings. Applied to every frame of a GIF, before any
+    /// scanlines are drawn.
     ///
-    /// * `background`: The background flag.
-    pub fn background(mut self, background: bool) -> Self {
-        self.background = background;
+    /// * `radius`: How far the glow spreads, as the standard deviation of the blur in pixels. Must
+    ///   be positive.
+    /// * `strength`: How bright the glow is, where 1.0 adds the whole blurred canvas. Must not be
+    ///   negative.
+    pub fn bloom(mut self, radius: f32, strength: f32) -> Self {
+        self.bloom = Some((radius, strength));
+        self
+    }
+
+    /// Sets the output [`ImgiiOptions`] up for the look of a classic monochrome terminal, with
+    /// glyphs in a single phosphor color (such as green or amber) glowing over a black background.
+    /// The charset is still picked by the brightness of the source image. This is a shortcut for
+    /// setting these options, which can still be changed afterwards:
+    /// * [`Self::gradient_override`] is set to a single color, so every glyph is `color`.
+    /// * [`Self::background`] is enabled, with a black [`Self::background_color`].
+    /// * [`Self::bloom`] is set to a subtle glow.
+    ///
+    /// * `color`: The phosphor color of the glyphs, as RGB.
+    pub fn phosphor(self, color: [u8; 3]) -> Self {
+        self.gradient_override(GradientSpec {
+            stops: vec![color],
+            direction: GradientDirection::default(),
+        })
+        .background(true)
+        .background_color(Rgba([0, 0, 0, u8::MAX]))
+        .bloom(PHOSPHOR_BLOOM_RADIUS, PHOSPHOR_BLOOM_STRENGTH)
+    }
+
+    /// Sets whether missing parent directories of output files are created for the output
+    /// [`ImgiiOptions`], like `mkdir -p`. Useful for batches with templated output paths. When
+    /// disabled, writing to a missing directory fails with an [`ImgiiError::Io`] naming the
+    /// directory.
+    ///
+    /// * `create_dirs`: Whether to create missing output directories.
+    pub fn create_dirs(mut self, create_dirs: bool) -> Self {
+        self.create_dirs = create_dirs;
+        self
+    }
+
+    /// Sets a grayscale mask image that fades out cells for the output [`ImgiiOptions`], for
+    /// vignettes and shaped reveals. The mask is stretched over the grid of cells, and the opacity
+    /// of each cell (including its background) is multiplied by the brightness of the mask there,
+    /// so cells under black are transparent and cells under white are drawn as usual. Colored
+    /// masks are converted to grayscale.
+    ///
+    /// The mask is loaded when the options are built.
+    ///
+    /// * `mask_file_name`: The file name of the mask image.
+    pub fn opacity_mask(mut self, mask_file_name: PathBuf) -> Self {
+        self.opacity_mask = Some(mask_file_name);
+        self
+    }
+
+    /// Posterizes the source image before it's converted to ASCII for the output
+    /// [`ImgiiOptions`], reducing each color channel to a number of evenly spaced levels. This
+    /// gives a cleaner, poster-like look with fewer distinct glyph colors (so more glyphs are
+    /// reused from the glyph cache). Since the source image itself is changed, the characters
+    /// picked for each cell change too, not just their colors.
+    ///
+    /// * `levels`: The number of levels per color channel, where 2 to 8 are typical. Must be at
+    ///   least 2.
+    pub fn posterize(mut self, levels: u8) -> Self {
+        self.posterize = Some(levels);
+        self
+    }
+
+    /// Sets a callback that reports the progress of converting a GIF for the output
+    /// [`ImgiiOptions`], such as to draw a progress bar. The callback is called once the number of
+    /// frames is known, then every time a frame is rendered or encoded, with the frames finished
+    /// so far and the estimated time remaining for that stage.
+    ///
+    /// NOTE: only GIFs converted with [`crate::convert_to_ascii_gif`] (and the functions built on
+    /// it) report their progress. The callback is called from the threads doing the work, one
+    /// call at a time, so it should return quickly.
+    ///
+    /// * `progress`: Reports the progress of each stage.
+    pub fn on_progress(mut self, progress: Arc<ProgressFn>) -> Self {
+        self.progress = Some(ProgressCallback(progress));
         self
     }
 
     /// Builds a new [`ImgiiOptions`] instance from chosen values in this builder.
     pub fn build(&self) -> Result<ImgiiOptions<'a>, ImgiiError> {
-        let (Some(font), Some(font_name)) = (self.font.clone(), self.font_name.clone()) else {
+        let (font, font_name) = self.resolve_font()?;
+        if let Some((0, _)) = self.pad_to_multiple {
+            // can't pad to a multiple of zero
             return Err(ImgiiError::InvalidArgument);
-        };
+        }
+        if let Some((width, height)) = self.fixed_grid
+            && (width == 0 || height == 0)
+        {
+            // there would be no cells to render
+            return Err(ImgiiError::InvalidArgument);
+        }
+        if let Some(jitter) = self.jitter
+            && !jitter.max_degrees.is_finite()
+        {
+            // rotating by infinity or NaN doesn't make sense
+            return Err(ImgiiError::InvalidArgument);
+        }
+        if self.sparsity == 0 {
+            // can't render every 0th cell
+            return Err(ImgiiError::InvalidArgument);
+        }
+        if self.max_frames == Some(0) {
+            // a GIF needs at least one frame
+            return Err(ImgiiError::InvalidArgument);
+        }
+        if self.max_line_chars == 0 {
+            // every line would be too long
+            return Err(ImgiiError::InvalidArgument);
+        }
+        if self.gif_pipeline_depth == Some(0) {
+            // the encoder needs room for at least one frame
+            return Err(ImgiiError::InvalidArgument);
+        }
+        if self.png_palette && self.bit_depth == BitDepth::Sixteen {
+            // indexed PNGs can't hold 16-bit colors
+            return Err(ImgiiError::InvalidArgument);
+        }
+        if let Some((radius, strength)) = self.bloom
+            && !(radius.is_finite() && radius > 0.0 && strength.is_finite() && strength >= 0.0)
+        {
+            // the blur needs a positive radius, and a negative glow would darken the canvas
+            return Err(ImgiiError::InvalidArgument);
+        }
+        if let Some(levels) = self.posterize
+            && levels < 2
+        {
+            // every channel needs at least a darkest and a lightest level
+            return Err(ImgiiError::InvalidArgument);
+        }
+        if let Some((0, _)) = self.scanlines {
+            // scanlines need space between them
+            return Err(ImgiiError::InvalidArgument);
+        }
+        if let Some(scale) = self.scale_output
+            && (!scale.is_finite() || scale <= 0.0)
+        {
+            // can't scale to a negative or infinite size
+            return Err(ImgiiError::InvalidArgument);
+        }
+        if self
+            .emphasis
+            .values()
+            .any(|multiplier| !multiplier.is_finite() || *multiplier <= 0.0)
+        {
+            // glyphs can't be drawn at a negative or infinite size
+            return Err(ImgiiError::InvalidArgument);
+        }
+        // load the mask once, rather than for every image (or frame) it's used for
+        let opacity_mask = self
+            .opacity_mask
+            .as_ref()
+            .map(|mask_file_name| image::open(mask_file_name).map(|mask| Arc::new(mask.to_luma8())))
+            .transpose()
+            .map_err(|err| -> ImgiiError { anyhow::Error::new(err).into() })?;
 
-        Ok(ImgiiOptions::new(
+        Ok(ImgiiOptions {
+            font_hash: hash_font(&font),
             font,
             font_name,
-            self.font_size,
-            self.background,
-            self.rascii_options.clone(),
-        ))
+            font_size: self.font_size,
+            background: self.background,
+            disable_glyph_cache: self.disable_glyph_cache,
+            channel_order: self.channel_order,
+            pad_to_multiple: self.pad_to_multiple,
+            bold: self.bold,
+            target_duration_ms: self.target_duration_ms,
+            space_handling: self.space_handling,
+            timings: self.timings,
+            scanlines: self.scanlines,
+            png_alpha: self.png_alpha,
+            jitter: self.jitter,
+            sparsity: self.sparsity,
+            preserve_icc_profile: self.preserve_icc_profile,
+            gif_disposal: self.gif_disposal,
+            tab_width: self.tab_width,
+            square_cells: self.square_cells,
+            memory_budget: self.memory_budget,
+            edge_detect: self.edge_detect,
+            cell_color_mode: self.cell_color_mode,
+            emphasis: self.emphasis.clone(),
+            scale_output: self.scale_output,
+            resize_filter_output: self.resize_filter_output,
+            max_frames: self.max_frames,
+            grayscale: self.grayscale,
+            skip_if_unchanged: self.skip_if_unchanged,
+            ascii_source: self.ascii_source.clone(),
+            background_color: self.background_color,
+            lightest_transparent: self.lightest_transparent,
+            bit_depth: self.bit_depth,
+            encoder: self.encoder.clone(),
+            cancel_flag: self.cancel_flag.clone(),
+            match_source_resolution: self.match_source_resolution,
+            source_resolution: None,
+            strict_parse: self.strict_parse,
+            cell_rounding: self.cell_rounding,
+            gif_pipeline_depth: self.gif_pipeline_depth,
+            max_line_chars: self.max_line_chars,
+            cell_bg_from_source: self.cell_bg_from_source,
+            sdf_glyph_cache: self.sdf_glyph_cache.clone(),
+            embed_metadata: self.embed_metadata,
+            atomic_writes: self.atomic_writes,
+            subpixel_cells: self.subpixel_cells,
+            ansi_input: self.ansi_input,
+            continuous_background: self.continuous_background,
+            png_palette: self.png_palette,
+            png_compression: self.png_compression,
+            gradient_override: self.gradient_override.clone(),
+            zero_width_handling: self.zero_width_handling,
+            fixed_grid: self.fixed_grid,
+            gif_transparent_key: self.gif_transparent_key,
+            bloom: self.bloom,
+            create_dirs: self.create_dirs,
+            opacity_mask,
+            posterize: self.posterize,
+            progress: self.progress.clone(),
+            rascii_options: self.rascii_options.clone(),
+        })
     }
 
     /*
@@ -200,18 +2432,74 @@ impl<'a> ImgiiOptionsBuilder<'a> {
      * doesn't fail.)
      */
 
-    /// Set the width of the rendered image.
+    /// Set the width (in characters) of the rendered image. If the height is not set, it is
+    /// calculated to keep the aspect ratio of the input image.
+    ///
+    /// If both the width and height are set, the aspect ratio is not preserved, and the rendered
+    /// image will be exactly `width` by `height` characters.
     pub fn width(mut self, width: u32) -> Self {
         self.rascii_options.width = Some(width);
         self
     }
 
-    /// Set the height of the rendered image.
+    /// Set the height (in characters) of the rendered image. If the width is not set, it is
+    /// calculated to keep the aspect ratio of the input image.
+    ///
+    /// If both the width and height are set, the aspect ratio is not preserved, and the rendered
+    /// image will be exactly `width` by `height` characters.
     pub fn height(mut self, height: u32) -> Self {
         self.rascii_options.height = Some(height);
         self
     }
 
+    /// Sets the width and height (in characters) and the font size so the rendered image fills as
+    /// much of a target resolution as it can, while keeping the aspect ratio of the input image.
+    ///
+    /// If the width is already set, it's kept and the font size is picked to fit it. Otherwise, the
+    /// font size is kept and the width is picked to fit it. Either way, the height is picked to
+    /// keep the aspect ratio, so the rendered image is never larger than the target.
+    ///
+    /// * `source_dimensions`: The (width, height) of the input image, in pixels.
+    /// * `target_dimensions`: The (width, height) to fill, in pixels.
+    pub fn fit_to_resolution(
+        mut self,
+        source_dimensions: (u32, u32),
+        target_dimensions: (u32, u32),
+    ) -> Self {
+        let (source_width, source_height) = (
+            u64::from(source_dimensions.0.max(1)),
+            u64::from(source_dimensions.1.max(1)),
+        );
+        let (target_width, target_height) = (
+            u64::from(target_dimensions.0.max(1)),
+            u64::from(target_dimensions.1.max(1)),
+        );
+
+        // the largest size with the aspect ratio of the input image that fits in the target
+        let (fit_width, fit_height) = if source_width * target_height > target_width * source_height
+        {
+            (target_width, target_width * source_height / source_width)
+        } else {
+            (target_height * source_width / source_height, target_height)
+        };
+
+        // cells are half as wide as they are tall, unless they are square
+        let cell_width_ratio = if self.square_cells { 1 } else { 2 };
+        let (width, cell_width) = match self.rascii_options.width {
+            Some(width) => (width, (fit_width / u64::from(width.max(1))).max(1)),
+            None => {
+                let cell_width = (u64::from(self.font_size) / cell_width_ratio).max(1);
+                ((fit_width / cell_width).max(1) as u32, cell_width)
+            }
+        };
+        let cell_height = cell_width * cell_width_ratio;
+
+        self.font_size = cell_height as u32;
+        self.rascii_options.width = Some(width);
+        self.rascii_options.height = Some((fit_height / cell_height).max(1) as u32);
+        self
+    }
+
     /// Set whether the rendered image charset should be inverted.
     pub fn invert(mut self, invert: bool) -> Self {
         self.rascii_options.invert = invert;
@@ -0,0 +1,227 @@
+//! Contains named render presets, which can be loaded from TOML or JSON files.
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::ImgiiError,
+    options::{ImgiiOptionsBuilder, from_enum, to_charset_enum},
+};
+
+/// A render preset, holding the options to apply to an [`ImgiiOptionsBuilder`]. Every option is
+/// optional, so a preset only changes the options it sets.
+///
+/// Each field is named the same as its builder method (and its CLI flag, with dashes instead of
+/// underscores).
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Preset {
+    /// The font size of the output image.
+    pub font_size: Option<u32>,
+    /// Whether to set a background behind the image.
+    pub background: Option<bool>,
+    /// Whether to bypass the glyph cache.
+    pub disable_glyph_cache: Option<bool>,
+    /// Whether to synthetically embolden each glyph.
+    pub bold: Option<bool>,
+    /// Whether to render every glyph in grayscale.
+    pub grayscale: Option<bool>,
+    /// Whether the lightest character of the charset is rendered like whitespace.
+    pub lightest_transparent: Option<bool>,
+    /// Whether to record how long each stage of the conversion took.
+    pub timings: Option<bool>,
+    /// Whether to embed the ICC profile of the input image in the output PNG.
+    pub preserve_icc_profile: Option<bool>,
+    /// Whether to render each character in a square cell.
+    pub square_cells: Option<bool>,
+    /// Whether to run edge detection on the source image.
+    pub edge_detect: Option<bool>,
+    /// The number of cells each tab is expanded to.
+    pub tab_width: Option<u32>,
+    /// Only every nth cell has its glyph rendered.
+    pub sparsity: Option<u32>,
+    /// The target duration of an output GIF, in milliseconds.
+    pub target_duration_ms: Option<u32>,
+    /// The maximum number of frames of an output GIF.
+    pub max_frames: Option<usize>,
+    /// The maximum number of bytes a rendered image may take up.
+    pub memory_budget: Option<u64>,
+    /// The width of the output image, in characters.
+    pub width: Option<u32>,
+    /// The height of the output image, in characters.
+    pub height: Option<u32>,
+    /// Whether to invert the image.
+    pub invert: Option<bool>,
+    /// The name of the built-in charset to use, such as "minimal".
+    pub charset: Option<String>,
+}
+
+impl Preset {
+    /// Loads a preset from a file. Files ending in ".toml" are read as TOML, and anything else is
+    /// read as JSON.
+    ///
+    /// * `file_name`: The file name of the preset.
+    pub fn load(file_name: &str) -> Result<Self, ImgiiError> {
+        let contents = std::fs::read_to_string(file_name)?;
+        if file_name.ends_with(".toml") {
+            toml::from_str(&contents)
+                .map_err(|err| -> ImgiiError { anyhow::Error::new(err).into() })
+        } else {
+            serde_json::from_str(&contents)
+                .map_err(|err| -> ImgiiError { anyhow::Error::new(err).into() })
+        }
+    }
+
+    /// Applies every option set in this preset to a builder.
+    ///
+    /// * `builder`: The builder to apply the preset to.
+    ///
+    /// # Returns
+    /// * The builder with the preset applied, or `Err` if the preset names an unknown charset.
+    pub fn apply<'a>(
+        &self,
+        builder: ImgiiOptionsBuilder<'a>,
+    ) -> Result<ImgiiOptionsBuilder<'a>, ImgiiError> {
+        self.apply_except(builder, |_| false)
+    }
+
+    /// Applies the options set in this preset to a builder, skipping any options that have already
+    /// been chosen elsewhere. Useful for letting CLI flags win over a preset.
+    ///
+    /// * `builder`: The builder to apply the preset to.
+    /// * `is_overridden`: Returns true for the name of each option that should be skipped.
+    ///
+    /// # Returns
+    /// * The builder with the preset applied, or `Err` if the preset names an unknown charset.
+    pub fn apply_except<'a>(
+        &self,
+        mut builder: ImgiiOptionsBuilder<'a>,
+        is_overridden: impl Fn(&str) -> bool,
+    ) -> Result<ImgiiOptionsBuilder<'a>, ImgiiError> {
+        // only use the options set in the preset that haven't been overridden
+        let keep = |name: &str| !is_overridden(name);
+
+        if let Some(font_size) = self.font_size.filter(|_| keep("font_size")) {
+            builder = builder.font_size(font_size);
+        }
+        if let Some(background) = self.background.filter(|_| keep("background")) {
+            builder = builder.background(background);
+        }
+        if let Some(disable_glyph_cache) = self
+            .disable_glyph_cache
+            .filter(|_| keep("disable_glyph_cache"))
+        {
+            builder = builder.disable_glyph_cache(disable_glyph_cache);
+        }
+        if let Some(bold) = self.bold.filter(|_| keep("bold")) {
+            builder = builder.bold(bold);
+        }
+        if let Some(grayscale) = self.grayscale.filter(|_| keep("grayscale")) {
+            builder = builder.grayscale(grayscale);
+        }
+        if let Some(lightest_transparent) = self
+            .lightest_transparent
+            .filter(|_| keep("lightest_transparent"))
+        {
+            builder = builder.lightest_transparent(lightest_transparent);
+        }
+        if let Some(timings) = self.timings.filter(|_| keep("timings")) {
+            builder = builder.timings(timings);
+        }
+        if let Some(preserve_icc_profile) = self
+            .preserve_icc_profile
+            .filter(|_| keep("preserve_icc_profile"))
+        {
+            builder = builder.preserve_icc_profile(preserve_icc_profile);
+        }
+        if let Some(square_cells) = self.square_cells.filter(|_| keep("square_cells")) {
+            builder = builder.square_cells(square_cells);
+        }
+        if let Some(edge_detect) = self.edge_detect.filter(|_| keep("edge_detect")) {
+            builder = builder.edge_detect(edge_detect);
+        }
+        if let Some(tab_width) = self.tab_width.filter(|_| keep("tab_width")) {
+            builder = builder.tab_width(tab_width);
+        }
+        if let Some(sparsity) = self.sparsity.filter(|_| keep("sparsity")) {
+            builder = builder.sparsity(sparsity);
+        }
+        if let Some(target_duration_ms) = self
+            .target_duration_ms
+            .filter(|_| keep("target_duration_ms"))
+        {
+            builder = builder.target_duration_ms(target_duration_ms);
+        }
+        if let Some(max_frames) = self.max_frames.filter(|_| keep("max_frames")) {
+            builder = builder.max_frames(max_frames);
+        }
+        if let Some(memory_budget) = self.memory_budget.filter(|_| keep("memory_budget")) {
+            builder = builder.memory_budget(memory_budget);
+        }
+        if let Some(width) = self.width.filter(|_| keep("width")) {
+            builder = builder.width(width);
+        }
+        if let Some(height) = self.height.filter(|_| keep("height")) {
+            builder = builder.height(height);
+        }
+        if let Some(invert) = self.invert.filter(|_| keep("invert")) {
+            builder = builder.invert(invert);
+        }
+        if let Some(charset) = self.charset.as_ref().filter(|_| keep("charset")) {
+            let Some(charset) = to_charset_enum(charset) else {
+                return Err(ImgiiError::InvalidArgument);
+            };
+            builder = builder.charset(from_enum(charset));
+        }
+
+        Ok(builder)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{test_dir, test_options_builder};
+
+    #[test]
+    fn command_line_options_win_over_the_preset() {
+        let dir = test_dir("command_line_options_win_over_the_preset");
+        let preset_path = dir.join("retro.toml");
+        std::fs::write(&preset_path, "font_size = 24\nbold = true\ntab_width = 8\n").unwrap();
+
+        let preset = Preset::load(preset_path.to_str().unwrap()).unwrap();
+        assert_eq!(preset.font_size, Some(24));
+        // the font size was passed on the command line, so only the rest of the preset is applied
+        let builder = test_options_builder().font_size(12);
+        let imgii_options = preset
+            .apply_except(builder, |name| name == "font_size")
+            .unwrap()
+            .build()
+            .unwrap();
+        assert_eq!(imgii_options.font_size(), 12);
+        assert!(imgii_options.bold());
+        assert_eq!(imgii_options.tab_width(), 8);
+    }
+
+    #[test]
+    fn presets_load_from_json() {
+        let dir = test_dir("presets_load_from_json");
+        let preset_path = dir.join("retro.json");
+        std::fs::write(&preset_path, r#"{"font_size": 24, "bold": true}"#).unwrap();
+
+        let preset = Preset::load(preset_path.to_str().unwrap()).unwrap();
+        assert_eq!(
+            preset,
+            Preset {
+                font_size: Some(24),
+                bold: Some(true),
+                ..Preset::default()
+            }
+        );
+        let imgii_options = preset
+            .apply(test_options_builder())
+            .unwrap()
+            .build()
+            .unwrap();
+        assert_eq!(imgii_options.font_size(), 24);
+    }
+}
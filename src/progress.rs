@@ -0,0 +1,184 @@
+//! Contains the progress of converting a GIF, reported to a callback as each frame is rendered and
+//! encoded.
+
+use std::{
+    collections::VecDeque,
+    fmt::Debug,
+    sync::{Arc, Mutex, PoisonError},
+    time::{Duration, Instant},
+};
+
+use crate::options::ImgiiOptions;
+
+/// The number of most recently finished frames the estimated time remaining is based on, so the
+/// estimate follows changes in speed, such as frames getting more detailed.
+const ETA_WINDOW: usize = 16;
+
+/// Reports the progress of a GIF conversion, such as to draw a progress bar.
+pub type ProgressFn = dyn Fn(&Progress) + Send + Sync;
+
+/// A progress callback held by the options, which can't be debug printed.
+#[derive(Clone)]
+pub(crate) struct ProgressCallback(pub(crate) Arc<ProgressFn>);
+
+impl Debug for ProgressCallback {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("ProgressCallback")
+    }
+}
+
+/// A stage of converting a GIF that reports its progress.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProgressStage {
+    /// Rendering the ASCII text of each frame into an image.
+    Render,
+    /// Encoding the rendered frames into the output GIF.
+    Encode,
+}
+
+/// The progress of a stage of converting a GIF.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Progress {
+    /// The stage the progress is for.
+    stage: ProgressStage,
+    /// The number of frames this stage has finished.
+    completed: usize,
+    /// The number of frames this stage will finish.
+    total: usize,
+    /// The time since this stage started.
+    elapsed: Duration,
+    /// The estimated time until this stage finishes, if any frames have finished yet.
+    eta: Option<Duration>,
+}
+
+impl Progress {
+    /// Gets the stage the progress is for. Stages may overlap when the GIF pipeline is enabled.
+    #[must_use]
+    pub fn stage(&self) -> ProgressStage {
+        self.stage
+    }
+
+    /// Gets the number of frames this stage has finished. Frames that failed to convert are
+    /// counted as finished while rendering.
+    #[must_use]
+    pub fn completed(&self) -> usize {
+        self.completed
+    }
+
+    /// Gets the number of frames this stage will finish. Frames that fail to render are taken
+    /// away from the total of the encode stage, so it may shrink while the GIF is converted.
+    #[must_use]
+    pub fn total(&self) -> usize {
+        self.total
+    }
+
+    /// Gets the time since this stage started.
+    #[must_use]
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    /// Gets the estimated time until this stage finishes, based on how quickly the most recent
+    /// frames were finished. Not known until the first frame has finished.
+    #[must_use]
+    pub fn eta(&self) -> Option<Duration> {
+        self.eta
+    }
+}
+
+/// The frames a stage has finished so far.
+#[derive(Debug)]
+struct TrackerState {
+    completed: usize,
+    total: usize,
+    /// When the most recent frames finished, oldest first, starting with when the stage started.
+    recent: VecDeque<Instant>,
+}
+
+/// Tracks the progress of a stage of converting a GIF, reporting it to the progress callback of
+/// the options each time a frame finishes. Can be shared between threads.
+pub(crate) struct ProgressTracker<'a> {
+    callback: &'a ProgressFn,
+    stage: ProgressStage,
+    start: Instant,
+    state: Mutex<TrackerState>,
+}
+
+impl<'a> ProgressTracker<'a> {
+    /// Starts tracking the progress of a stage, reporting that no frames have finished yet.
+    ///
+    /// * `imgii_options`: The imgii options, holding the progress callback.
+    /// * `stage`: The stage to track.
+    /// * `total`: The number of frames the stage will finish.
+    ///
+    /// # Returns
+    /// * The tracker, or `None` if there is no progress callback to report to.
+    pub(crate) fn new(
+        imgii_options: &'a ImgiiOptions,
+        stage: ProgressStage,
+        total: usize,
+    ) -> Option<Self> {
+        let start = Instant::now();
+        let tracker = Self {
+            callback: imgii_options.progress()?,
+            stage,
+            start,
+            state: Mutex::new(TrackerState {
+                completed: 0,
+                total,
+                recent: VecDeque::from([start]),
+            }),
+        };
+        tracker.update(|_| {});
+        Some(tracker)
+    }
+
+    /// Records that a frame has finished.
+    pub(crate) fn frame_done(&self) {
+        self.update(|state| {
+            state.completed += 1;
+            state.recent.push_back(Instant::now());
+            if state.recent.len() > ETA_WINDOW + 1 {
+                state.recent.pop_front();
+            }
+        });
+    }
+
+    /// Records that frames will never reach this stage, such as frames that failed to render
+    /// before they could be encoded.
+    ///
+    /// * `count`: The number of frames to take away from the total.
+    #[cfg(not(feature = "wasm"))]
+    pub(crate) fn frames_dropped(&self, count: usize) {
+        if count > 0 {
+            self.update(|state| state.total = state.total.saturating_sub(count));
+        }
+    }
+
+    /// Changes the state of the stage and reports it. The callback is called while the state is
+    /// locked, so reports arrive one at a time and in order.
+    ///
+    /// * `change`: Changes the state.
+    fn update(&self, change: impl FnOnce(&mut TrackerState)) {
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        change(&mut state);
+
+        let remaining = state.total.saturating_sub(state.completed);
+        let eta = match (state.recent.front(), state.recent.back()) {
+            (Some(oldest), Some(newest)) if state.recent.len() > 1 => {
+                let per_frame = (*newest - *oldest) / (state.recent.len() - 1) as u32;
+                Some(per_frame.saturating_mul(u32::try_from(remaining).unwrap_or(u32::MAX)))
+            }
+            // no frames have finished yet, unless there are none to finish
+            _ => (remaining == 0).then_some(Duration::ZERO),
+        };
+
+        (self.callback)(&Progress {
+            stage: self.stage,
+            completed: state.completed,
+            total: state.total,
+            elapsed: self.start.elapsed(),
+            eta,
+        });
+    }
+}
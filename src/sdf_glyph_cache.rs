@@ -0,0 +1,259 @@
+//! Contains a cache of glyphs rasterized once as signed distance fields (SDFs), so they can be
+//! drawn at any font size without rasterizing them again.
+//!
+//! NOTE: glyphs drawn from a distance field are close to, but not the same as, glyphs rasterized at
+//! the font size. Sharp corners are slightly rounded and anti-aliasing is a little softer. At very
+//! small font sizes (below about 10 pixels) thin strokes can fade and details smaller than a pixel
+//! are lost, so prefer rasterizing directly when rendering tiny cells.
+
+use std::{
+    collections::HashMap,
+    hash::{DefaultHasher, Hash, Hasher},
+    sync::{Arc, Mutex, PoisonError},
+};
+
+use ab_glyph::{FontRef, PxScale};
+use image::{GrayImage, ImageBuffer, Luma, Rgba};
+use imageproc::{
+    distance_transform::euclidean_squared_distance_transform, drawing::draw_text_mut,
+    pixelops::weighted_sum,
+};
+
+/// The font size glyphs are rasterized at before being turned into distance fields.
+const REFERENCE_SIZE: f32 = 64.0;
+
+/// The empty space around each rasterized glyph, in pixels, so the distance field extends past
+/// the edges of the glyph.
+const REFERENCE_PADDING: i32 = 8;
+
+/// Identifies a glyph in the cache, by the font it's from and the string drawn.
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+struct GlyphKey {
+    /// The hash of the font data from [`hash_font`], since different fonts can share a name.
+    font_hash: u64,
+    string: String,
+}
+
+/// Hashes the data of a font, to tell fonts apart in the cache.
+///
+/// * `font`: The loaded bytes of the font.
+pub(crate) fn hash_font(font: &[u8]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    font.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// A glyph rasterized once as a signed distance field.
+#[derive(Debug)]
+pub(crate) struct SdfGlyph {
+    /// The distance from each pixel to the nearest edge of the glyph, in reference pixels.
+    /// Negative inside of the glyph and positive outside of it.
+    distances: ImageBuffer<Luma<f32>, Vec<f32>>,
+}
+
+impl SdfGlyph {
+    /// Rasterizes a string at the reference size and turns it into a distance field.
+    ///
+    /// * `font`: The font to rasterize with.
+    /// * `string`: The string to rasterize.
+    fn new(font: &FontRef<'_>, string: &str) -> Self {
+        // glyphs can be wider than they are tall (and hang below the baseline), so give them room
+        let canvas_size = (REFERENCE_SIZE as u32) * 2 + REFERENCE_PADDING as u32 * 2;
+        let mut coverage = GrayImage::new(canvas_size, canvas_size);
+        draw_text_mut(
+            &mut coverage,
+            Luma([u8::MAX]),
+            REFERENCE_PADDING,
+            REFERENCE_PADDING,
+            PxScale::from(REFERENCE_SIZE),
+            font,
+            string,
+        );
+
+        let inside_mask = GrayImage::from_fn(canvas_size, canvas_size, |x, y| {
+            Luma([if is_inside(&coverage, x, y) {
+                u8::MAX
+            } else {
+                0
+            }])
+        });
+        let outside_mask = GrayImage::from_fn(canvas_size, canvas_size, |x, y| {
+            Luma([if is_inside(&coverage, x, y) {
+                0
+            } else {
+                u8::MAX
+            }])
+        });
+        // the distance to the nearest pixel inside of the glyph, and to the nearest one outside
+        let to_inside = euclidean_squared_distance_transform(&inside_mask);
+        let to_outside = euclidean_squared_distance_transform(&outside_mask);
+
+        // the edge lies halfway between the last pixel inside and the first pixel outside
+        let distances = ImageBuffer::from_fn(canvas_size, canvas_size, |x, y| {
+            let distance = if is_inside(&coverage, x, y) {
+                0.5 - to_outside.get_pixel(x, y).0[0].sqrt()
+            } else {
+                to_inside.get_pixel(x, y).0[0].sqrt() - 0.5
+            };
+            Luma([distance as f32])
+        });
+
+        Self { distances }
+    }
+
+    /// Draws the glyph onto an image, like [`draw_text_mut`] does for a font.
+    ///
+    /// * `image`: The image to draw onto.
+    /// * `color`: The color of the glyph.
+    /// * `x`: The x position of the glyph in the image.
+    /// * `y`: The y position of the glyph in the image.
+    /// * `glyph_size`: The font size to draw the glyph at.
+    pub(crate) fn draw(
+        &self,
+        image: &mut ImageBuffer<Rgba<u8>, Vec<u8>>,
+        color: Rgba<u8>,
+        x: i32,
+        y: i32,
+        glyph_size: f32,
+    ) {
+        if glyph_size <= 0.0 {
+            return;
+        }
+
+        let to_reference = REFERENCE_SIZE / glyph_size;
+        for (pixel_x, pixel_y, pixel) in image.enumerate_pixels_mut() {
+            // sample the distance field at the center of the pixel
+            let reference_x =
+                REFERENCE_PADDING as f32 + (pixel_x as f32 + 0.5 - x as f32) * to_reference - 0.5;
+            let reference_y =
+                REFERENCE_PADDING as f32 + (pixel_y as f32 + 0.5 - y as f32) * to_reference - 0.5;
+            let distance = self.sample(reference_x, reference_y) / to_reference;
+
+            let coverage = (0.5 - distance).clamp(0.0, 1.0);
+            if coverage > 0.0 {
+                *pixel = weighted_sum(*pixel, color, 1.0 - coverage, coverage);
+            }
+        }
+    }
+
+    /// Bilinearly samples the distance field. Anything outside of it is treated as far from the
+    /// glyph.
+    ///
+    /// * `x`: The x position to sample, in reference pixels.
+    /// * `y`: The y position to sample, in reference pixels.
+    fn sample(&self, x: f32, y: f32) -> f32 {
+        let (width, height) = self.distances.dimensions();
+        if x < 0.0 || y < 0.0 || x > (width - 1) as f32 || y > (height - 1) as f32 {
+            return f32::MAX;
+        }
+
+        let (left, top) = (x.floor() as u32, y.floor() as u32);
+        let (right, bottom) = ((left + 1).min(width - 1), (top + 1).min(height - 1));
+        let (weight_x, weight_y) = (x - left as f32, y - top as f32);
+        let distance = |x, y| self.distances.get_pixel(x, y).0[0];
+
+        let top_distance = distance(left, top) * (1.0 - weight_x) + distance(right, top) * weight_x;
+        let bottom_distance =
+            distance(left, bottom) * (1.0 - weight_x) + distance(right, bottom) * weight_x;
+        top_distance * (1.0 - weight_y) + bottom_distance * weight_y
+    }
+}
+
+/// Gets whether a pixel of a rasterized glyph is inside of the glyph.
+///
+/// * `coverage`: The rasterized glyph.
+/// * `x`: The x position of the pixel.
+/// * `y`: The y position of the pixel.
+fn is_inside(coverage: &GrayImage, x: u32, y: u32) -> bool {
+    coverage.get_pixel(x, y).0[0] > u8::MAX / 2
+}
+
+/// A cache of glyphs rasterized once as signed distance fields, which are then drawn at any font
+/// size. Sharing one cache between conversions at different font sizes skips rasterizing the
+/// charset again for every size.
+///
+/// Cloning the cache is cheap, and every clone shares the same glyphs.
+///
+/// See the [module documentation](self) for how glyphs drawn from the cache differ from glyphs
+/// rasterized directly.
+#[derive(Debug, Clone, Default)]
+pub struct SdfGlyphCache {
+    glyphs: Arc<Mutex<HashMap<GlyphKey, Arc<SdfGlyph>>>>,
+}
+
+impl SdfGlyphCache {
+    /// Creates a new, empty [`SdfGlyphCache`].
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Gets the number of glyphs in the cache.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.glyphs
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .len()
+    }
+
+    /// Gets whether the cache has no glyphs.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Gets a glyph from the cache, rasterizing it first if it isn't cached yet.
+    ///
+    /// * `font`: The font to rasterize with.
+    /// * `font_hash`: The hash of the font data from [`hash_font`].
+    /// * `string`: The string to get the glyph of.
+    pub(crate) fn glyph(&self, font: &FontRef<'_>, font_hash: u64, string: &str) -> Arc<SdfGlyph> {
+        let key = GlyphKey {
+            font_hash,
+            string: string.to_string(),
+        };
+        if let Some(glyph) = self
+            .glyphs
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(&key)
+        {
+            return Arc::clone(glyph);
+        }
+
+        // rasterize without holding the lock, so other threads can draw cached glyphs meanwhile
+        let glyph = Arc::new(SdfGlyph::new(font, string));
+        Arc::clone(
+            self.glyphs
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .entry(key)
+                .or_insert(glyph),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TEST_FONT;
+
+    #[test]
+    fn glyphs_are_cached_by_the_font_data() {
+        let font = FontRef::try_from_slice(TEST_FONT).unwrap();
+        // the same length as the test font, but different data
+        let mut other_font_data = TEST_FONT.to_vec();
+        *other_font_data.last_mut().unwrap() ^= 0xFF;
+        let cache = SdfGlyphCache::new();
+
+        let glyph = cache.glyph(&font, hash_font(TEST_FONT), "#");
+        assert!(Arc::ptr_eq(
+            &glyph,
+            &cache.glyph(&font, hash_font(TEST_FONT), "#")
+        ));
+        assert_eq!(cache.len(), 1);
+        cache.glyph(&font, hash_font(&other_font_data), "#");
+        assert_eq!(cache.len(), 2);
+    }
+}
@@ -0,0 +1,156 @@
+//! Contains types for describing a sprite sheet of the unique rendered glyph cells of an image.
+
+use std::{collections::HashMap, sync::Arc};
+
+use image::imageops;
+
+use crate::{
+    conversion::{
+        image_data::{ImageData, InternalImage},
+        render_char_to_png::ColoredStr,
+    },
+    error::ImgiiError,
+};
+
+/// The position of a single rendered glyph cell in a sprite sheet.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SpriteSheetEntry {
+    /// The string rendered in this cell.
+    pub string: String,
+    /// The color the string was rendered with, as RGB.
+    pub color: [u8; 3],
+    /// The x position (in pixels) of the cell in the sprite sheet.
+    pub x: u32,
+    /// The y position (in pixels) of the cell in the sprite sheet.
+    pub y: u32,
+}
+
+/// The index of a sprite sheet, mapping each rendered glyph and color to its cell position.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SpriteSheetIndex {
+    /// The width (in pixels) of every cell.
+    pub cell_width: u32,
+    /// The height (in pixels) of every cell.
+    pub cell_height: u32,
+    /// The cells in the sprite sheet, sorted by string, then by color.
+    pub entries: Vec<SpriteSheetEntry>,
+}
+
+#[cfg(feature = "serde")]
+impl SpriteSheetIndex {
+    /// Saves this index as JSON.
+    ///
+    /// * `file_name`: The file name to save the JSON to.
+    pub fn save_json(&self, file_name: &str) -> Result<(), ImgiiError> {
+        let file_writer = std::io::BufWriter::new(std::fs::File::create(file_name)?);
+        serde_json::to_writer_pretty(file_writer, self)
+            .map_err(|err| -> ImgiiError { anyhow::Error::new(err).into() })
+    }
+}
+
+/// Lays out the unique rendered cells into a grid, building a sprite sheet and its index.
+///
+/// * `glyph_cache`: The unique rendered cells, keyed by what was rendered in them.
+/// * `cell_width`: The width (in pixels) of every cell.
+/// * `cell_height`: The height (in pixels) of every cell.
+///
+/// # Returns
+/// * The sprite sheet and its index, or [`ImgiiError::InvalidArgument`] if there are no cells to
+///   lay out.
+pub(crate) fn build_sprite_sheet(
+    glyph_cache: &HashMap<ColoredStr, Arc<ImageData>>,
+    cell_width: u32,
+    cell_height: u32,
+) -> Result<(InternalImage, SpriteSheetIndex), ImgiiError> {
+    if glyph_cache.is_empty() {
+        // an empty sprite sheet can't be saved as an image, so fail before trying to
+        return Err(ImgiiError::InvalidArgument);
+    }
+
+    // sort the cells so the same input always produces the same sprite sheet
+    let mut cells: Vec<(&ColoredStr, &Arc<ImageData>)> = glyph_cache.iter().collect();
+    cells.sort_by(|(a, _), (b, _)| {
+        (&a.string, a.red, a.green, a.blue).cmp(&(&b.string, b.red, b.green, b.blue))
+    });
+
+    // make the sprite sheet as close to square as we can
+    let columns = (cells.len() as f64).sqrt().ceil().max(1.0) as u32;
+    let rows = (cells.len() as u32).div_ceil(columns);
+    let mut sprite_sheet: InternalImage =
+        image::ImageBuffer::new(columns * cell_width, rows * cell_height);
+
+    let entries = cells
+        .into_iter()
+        .enumerate()
+        .map(|(i, (colored, cell))| {
+            let x = (i as u32 % columns) * cell_width;
+            let y = (i as u32 / columns) * cell_height;
+            imageops::replace(&mut sprite_sheet, cell.as_buffer(), x.into(), y.into());
+            SpriteSheetEntry {
+                string: colored.string.clone(),
+                color: [colored.red, colored.green, colored.blue],
+                x,
+                y,
+            }
+        })
+        .collect();
+
+    Ok((
+        sprite_sheet,
+        SpriteSheetIndex {
+            cell_width,
+            cell_height,
+            entries,
+        },
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Creates a cell filled with a single color, keyed by its string.
+    fn cell(string: &str, color: [u8; 3]) -> (ColoredStr, Arc<ImageData>) {
+        let [red, green, blue] = color;
+        let image = image::ImageBuffer::from_pixel(2, 4, image::Rgba([red, green, blue, 255]));
+        (
+            ColoredStr {
+                red,
+                green,
+                blue,
+                string: String::from(string),
+                background: None,
+            },
+            Arc::new(ImageData::new(image)),
+        )
+    }
+
+    #[test]
+    fn empty_glyph_cache_is_an_error() {
+        let result = build_sprite_sheet(&HashMap::new(), 2, 4);
+        assert!(matches!(result, Err(ImgiiError::InvalidArgument)));
+    }
+
+    #[test]
+    fn every_cell_is_at_its_index_position() {
+        let glyph_cache = HashMap::from([
+            cell("a", [255, 0, 0]),
+            cell("b", [0, 255, 0]),
+            cell("c", [0, 0, 255]),
+        ]);
+
+        let (sprite_sheet, index) = build_sprite_sheet(&glyph_cache, 2, 4).unwrap();
+        // 3 cells fit in a 2x2 grid
+        assert_eq!(sprite_sheet.dimensions(), (4, 8));
+        assert_eq!(index.entries.len(), 3);
+        for entry in &index.entries {
+            let [red, green, blue] = entry.color;
+            assert_eq!(
+                *sprite_sheet.get_pixel(entry.x, entry.y),
+                image::Rgba([red, green, blue, 255])
+            );
+        }
+    }
+}
@@ -0,0 +1,40 @@
+//! Contains statistics gathered while rendering ASCII as an image.
+
+use std::collections::{BTreeSet, HashSet};
+
+use image::Rgb;
+
+/// Statistics about the cells that were rendered for an image.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RenderStats {
+    /// The distinct characters that were rendered as glyphs.
+    characters: BTreeSet<char>,
+    /// The distinct colors that glyphs were rendered with.
+    colors: HashSet<Rgb<u8>>,
+}
+
+impl RenderStats {
+    /// Records a rendered cell.
+    ///
+    /// * `the_str`: The string rendered in the cell.
+    /// * `color`: The color the string was rendered with.
+    pub(crate) fn record(&mut self, the_str: &str, color: Rgb<u8>) {
+        self.characters.extend(the_str.chars());
+        self.colors.insert(color);
+    }
+
+    /// Gets the distinct characters that were rendered as glyphs, in sorted order. Whitespace is
+    /// never rendered as a glyph, so it is not included.
+    ///
+    /// Useful for building a font subset containing only the glyphs that are needed.
+    #[must_use]
+    pub fn characters(&self) -> &BTreeSet<char> {
+        &self.characters
+    }
+
+    /// Gets the distinct colors that glyphs were rendered with.
+    #[must_use]
+    pub fn colors(&self) -> &HashSet<Rgb<u8>> {
+        &self.colors
+    }
+}
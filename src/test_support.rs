@@ -0,0 +1,74 @@
+//! Helpers shared by the tests of each module.
+
+use std::{
+    fs::File,
+    path::{Path, PathBuf},
+};
+
+use crate::options::ImgiiOptionsBuilder;
+
+/// The font every test renders with, so rendering doesn't depend on the fonts installed.
+pub(crate) const TEST_FONT: &[u8] = include_bytes!("../tests/fixtures/DejaVuSansMono.ttf");
+
+/// The charset tests convert images with, from darkest to lightest.
+pub(crate) const TEST_CHARSET: &[&str] = &[" ", ".", ":", "#", "@"];
+
+/// Creates an options builder with the test font and charset set, and every other option left
+/// at its default.
+pub(crate) fn test_options_builder<'a>() -> ImgiiOptionsBuilder<'a> {
+    ImgiiOptionsBuilder::new()
+        .font(TEST_FONT.to_vec())
+        .font_name(String::from("DejaVu Sans Mono"))
+        .charset(TEST_CHARSET)
+}
+
+/// Formats a character as colored ASCII text, the way RASCII colors each character.
+///
+/// * `[red, green, blue]`: The color of the character.
+/// * `character`: The character.
+pub(crate) fn ansi_cell([red, green, blue]: [u8; 3], character: char) -> String {
+    format!("\x1b[38;2;{red};{green};{blue}m{character}")
+}
+
+/// Creates an empty directory for a test to write files into, removing anything a previous run
+/// of the same test left behind.
+///
+/// * `test_name`: The name of the test, which must be unique.
+pub(crate) fn test_dir(test_name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("imgii-test-{}-{test_name}", std::process::id()));
+    // the directory may not exist yet, which is fine
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).expect("could not create the test directory");
+    dir
+}
+
+/// A frame of a test GIF, filled with a single color.
+pub(crate) struct TestFrame {
+    pub(crate) left: u16,
+    pub(crate) top: u16,
+    pub(crate) width: u16,
+    pub(crate) height: u16,
+    pub(crate) color: [u8; 4],
+    /// The delay in centiseconds.
+    pub(crate) delay: u16,
+}
+
+/// Writes a GIF with a logical screen of the given size, made up of the given frames.
+///
+/// * `path`: The path to write the GIF to.
+/// * `(width, height)`: The size of the logical screen.
+/// * `frames`: The frames of the GIF.
+pub(crate) fn write_test_gif(path: &Path, (width, height): (u16, u16), frames: &[TestFrame]) {
+    let file = File::create(path).unwrap();
+    let mut encoder = gif::Encoder::new(file, width, height, &[]).unwrap();
+    for frame in frames {
+        let mut pixels = frame
+            .color
+            .repeat(usize::from(frame.width) * usize::from(frame.height));
+        let mut gif_frame = gif::Frame::from_rgba(frame.width, frame.height, &mut pixels);
+        gif_frame.left = frame.left;
+        gif_frame.top = frame.top;
+        gif_frame.delay = frame.delay;
+        encoder.write_frame(&gif_frame).unwrap();
+    }
+}
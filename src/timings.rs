@@ -0,0 +1,50 @@
+//! Contains timings of each stage of a conversion.
+
+use std::time::Duration;
+
+/// How long each stage of a conversion took.
+///
+/// For GIFs, each stage covers every frame.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ConversionTimings {
+    /// The time taken to decode the input image.
+    pub(crate) decode: Duration,
+    /// The time taken to convert the decoded image to ASCII.
+    pub(crate) ascii: Duration,
+    /// The time taken to render the ASCII as an image.
+    pub(crate) render: Duration,
+    /// The time taken to encode and save the output image.
+    pub(crate) encode: Duration,
+}
+
+impl ConversionTimings {
+    /// Gets the time taken to decode the input image.
+    #[must_use]
+    pub fn decode(&self) -> Duration {
+        self.decode
+    }
+
+    /// Gets the time taken to convert the decoded image to ASCII.
+    #[must_use]
+    pub fn ascii(&self) -> Duration {
+        self.ascii
+    }
+
+    /// Gets the time taken to render the ASCII as an image.
+    #[must_use]
+    pub fn render(&self) -> Duration {
+        self.render
+    }
+
+    /// Gets the time taken to encode and save the output image.
+    #[must_use]
+    pub fn encode(&self) -> Duration {
+        self.encode
+    }
+
+    /// Gets the total time taken by every stage.
+    #[must_use]
+    pub fn total(&self) -> Duration {
+        self.decode + self.ascii + self.render + self.encode
+    }
+}